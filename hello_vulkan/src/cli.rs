@@ -0,0 +1,210 @@
+//! Command line of the slicer binary
+
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use slicer::filter::{Filter, MAX_BLUR_RADIUS};
+use slicer::run::patterns::Pattern;
+
+pub const DEFAULT_BLUR_RADIUS: u32 = 4;
+pub const DEFAULT_OUT_DIR: &str = "filtered";
+/// one per core of the RK3288
+pub const DEFAULT_WORKERS: usize = 4;
+
+const USAGE: &str = "usage: slicer [--config <path>] [--backend <win|display|headless|fb>]
+              [--pattern <name>] [--measure-refresh <seconds>] [--animate]
+              [--markers <count>] [--scene <count> [--workers <threads>]]
+       slicer [--config <path>] --blur <input> <output> [--blur-radius <pixels>]
+       slicer [--config <path>] --kernel <spirv> <input> <output>
+       slicer [--config <path>] --filter <spec>... [--cpu | --compare]
+              [--out-dir <dir>] <input>...
+
+  --pattern  draws a test pattern instead of the triangle, one of
+             bars, ramp, checkerboard, grid, white, red, green, blue,
+             black, moving-bar; space, right, down, a click or a touch
+             go to the next one, left and up to the previous one
+  --measure-refresh
+             presents in FIFO mode for that many seconds, then reports
+             the refresh rate measured against the display mode's
+  --animate  spins the triangle, its vertices are updated every frame
+  --markers  draws that many small triangles in a grid instead, in one
+             instanced indirect draw
+  --scene    draws that many small triangles in a grid instead, one draw
+             each, recorded in parallel by --workers threads, 4 by default
+  --blur     blurs the input image into the output one with a compute
+             kernel and quits, without opening any display;
+             --blur-radius defaults to 4 pixels, at most 64
+  --kernel   runs a SPIR-V compute kernel over the input image into the
+             output one: entry point main, local size 16x16, reading
+             the rgba8 storage image of binding 0 and writing binding 1
+  --filter   applies the filters to each input image in the order given
+             and writes the results under --out-dir, filtered/ by
+             default, with the same names, which must differ; a spec
+             is one of
+             resize=<width>x<height>, convert=<gray|sepia|invert|swap-rb>,
+             convolve=<sharpen|edge|emboss|box|weights>, threshold=<level>,
+             blur=<pixels>; they run as compute kernels, or on the CPU
+             without a Vulkan device or with --cpu
+  --compare  runs both and logs how far apart the results are";
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub config: Option<PathBuf>,
+    /// one of `slicer::run::backend::available_backends()`
+    pub backend: Option<String>,
+    pub pattern: Option<Pattern>,
+    pub measure_refresh: Option<Duration>,
+    pub animate: bool,
+    pub markers: Option<u32>,
+    pub scene: Option<u32>,
+    /// `None` for `DEFAULT_WORKERS`
+    pub workers: Option<usize>,
+    /// input and output images
+    pub blur: Option<(PathBuf, PathBuf)>,
+    /// `None` for `DEFAULT_BLUR_RADIUS`
+    pub blur_radius: Option<u32>,
+    /// SPIR-V module, input and output images
+    pub kernel: Option<(PathBuf, PathBuf, PathBuf)>,
+    /// applied to each of `inputs`, in order
+    pub filters: Vec<Filter>,
+    pub inputs: Vec<PathBuf>,
+    pub cpu: bool,
+    pub compare: bool,
+    /// `None` for `DEFAULT_OUT_DIR`
+    pub out_dir: Option<PathBuf>,
+}
+
+pub fn parse() -> Args {
+    let mut args = Args::default();
+    let mut iter = env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => args.config = Some(PathBuf::from(value(&mut iter, "--config"))),
+            "--backend" => args.backend = Some(value(&mut iter, "--backend")),
+            "--pattern" => {
+                let name = value(&mut iter, "--pattern");
+                match Pattern::from_name(&name) {
+                    Some(pattern) => args.pattern = Some(pattern),
+                    None => usage_error(&format!(
+                        "unknown pattern '{}', available: {}",
+                        name,
+                        Pattern::names().join(", ")
+                    )),
+                }
+            }
+            "--measure-refresh" => {
+                let seconds = value(&mut iter, "--measure-refresh");
+                match seconds.parse::<u64>() {
+                    Ok(seconds) if seconds > 0 => args.measure_refresh = Some(Duration::from_secs(seconds)),
+                    _ => usage_error(&format!("invalid duration '{}', expected whole seconds", seconds)),
+                }
+            }
+            "--animate" => args.animate = true,
+            "--markers" => {
+                let count = value(&mut iter, "--markers");
+                match count.parse::<u32>() {
+                    Ok(count) if count > 0 => args.markers = Some(count),
+                    _ => usage_error(&format!("invalid marker count '{}'", count)),
+                }
+            }
+            "--scene" => {
+                let count = value(&mut iter, "--scene");
+                match count.parse::<u32>() {
+                    Ok(count) if count > 0 => args.scene = Some(count),
+                    _ => usage_error(&format!("invalid draw count '{}'", count)),
+                }
+            }
+            "--workers" => {
+                let count = value(&mut iter, "--workers");
+                match count.parse::<usize>() {
+                    Ok(count) if count > 0 => args.workers = Some(count),
+                    _ => usage_error(&format!("invalid worker count '{}'", count)),
+                }
+            }
+            "--blur" => {
+                let input = PathBuf::from(value(&mut iter, "--blur"));
+                let output = PathBuf::from(value(&mut iter, "--blur"));
+                args.blur = Some((input, output));
+            }
+            "--kernel" => {
+                let spirv = PathBuf::from(value(&mut iter, "--kernel"));
+                let input = PathBuf::from(value(&mut iter, "--kernel"));
+                let output = PathBuf::from(value(&mut iter, "--kernel"));
+                args.kernel = Some((spirv, input, output));
+            }
+            "--blur-radius" => {
+                let radius = value(&mut iter, "--blur-radius");
+                match radius.parse::<u32>() {
+                    Ok(radius) if radius <= MAX_BLUR_RADIUS => args.blur_radius = Some(radius),
+                    _ => usage_error(&format!("invalid blur radius '{}'", radius)),
+                }
+            }
+            "--filter" => {
+                let spec = value(&mut iter, "--filter");
+                match Filter::parse(&spec) {
+                    Ok(filter) => args.filters.push(filter),
+                    Err(message) => usage_error(&message),
+                }
+            }
+            "--cpu" => args.cpu = true,
+            "--compare" => args.compare = true,
+            "--out-dir" => args.out_dir = Some(PathBuf::from(value(&mut iter, "--out-dir"))),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if !arg.starts_with('-') => args.inputs.push(PathBuf::from(arg)),
+            _ => usage_error(&format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    if !args.filters.is_empty() && args.inputs.is_empty() {
+        usage_error("--filter needs input images");
+    }
+    if args.filters.is_empty() && !args.inputs.is_empty() {
+        usage_error(&format!(
+            "unexpected argument '{}', input images need a --filter",
+            args.inputs[0].display()
+        ));
+    }
+    check_output_names(&args.inputs);
+    if args.blur_radius.is_some() && args.blur.is_none() {
+        usage_error("--blur-radius needs --blur");
+    }
+    if args.cpu && args.compare {
+        usage_error("--compare runs the filters on the CPU already");
+    }
+
+    args
+}
+
+/// the results are written under --out-dir by file name
+fn check_output_names(inputs: &[PathBuf]) {
+    let mut names = HashMap::new();
+    for input in inputs {
+        if let Some(name) = input.file_name() {
+            if let Some(other) = names.insert(name, input) {
+                usage_error(&format!(
+                    "'{}' and '{}' would both be written as '{}'",
+                    other.display(),
+                    input.display(),
+                    name.to_string_lossy()
+                ));
+            }
+        }
+    }
+}
+
+fn value<I: Iterator<Item = String>>(iter: &mut I, flag: &str) -> String {
+    iter.next()
+        .unwrap_or_else(|| usage_error(&format!("{} needs a value", flag)))
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2);
+}
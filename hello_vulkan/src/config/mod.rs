@@ -0,0 +1,572 @@
+//! Per board configuration
+//!
+//! Read from the TOML file given with `--config`, else from `$SLICER_CONFIG`,
+//! else from `slicer.toml` in the working directory. Without any of them
+//! the defaults below are used. Every key is optional:
+//!
+//! ```toml
+//! clear_color = [0.0, 0.0, 1.0, 1.0]
+//! validation = false
+//! pipeline_cache = "/var/cache/slicer/pipeline.cache"
+//!
+//! [device]
+//! index = 0                  # or: name = "Mali-T760"
+//!
+//! [swapchain]
+//! format = "B8G8R8A8Unorm"   # first supported format when missing
+//! present_mode = "fifo"      # immediate, mailbox, fifo, relaxed
+//!
+//! [display]                  # fbdev only
+//! index = 0
+//! plane = 0
+//! mode = [1920, 1080]        # first mode when missing
+//! refresh_rate = 60000
+//!
+//! [window]                   # win only
+//! title = "slicer"
+//! size = [1280, 1024]        # inner size, in points on macOS and in
+//!                            # pixels on X11, Wayland and Windows
+//! fullscreen = false
+//! monitor = "HDMI-1"         # fullscreen on the first monitor whose name
+//!                            # contains this, the primary one when missing
+//! decorations = true
+//! resizable = true
+//! cursor = true              # false hides it over the window
+//!
+//! [orientation]            # of the content on the panel
+//! rotation = 90              # clockwise: 0, 90, 180 or 270
+//! mirror = false             # mirrored horizontally before rotating
+//!
+//! [viewport]
+//! size = [1024.0, 1024.0]    # whole swapchain when missing
+//!
+//! [headless]
+//! size = [1024, 768]
+//! frames = 1                 # quit after writing that many, 0 runs forever
+//! output = "frame-{}.png"    # {} is replaced by the frame number
+//!
+//! [keybindings]              # key names as in winit's VirtualKeyCode,
+//!                            # Enter or Return, Backspace or Back
+//! quit = "Escape"
+//! screenshot = "F12"         # written to screenshot-{}.png
+//! fullscreen = "F11"
+//! stats = "F3"               # frame rate overlay, and in the info log
+//! reload = "F5"              # re-reads the colour correction LUT
+//!
+//! [post_process]             # effects applied to the scene, in order
+//! scale = 1.0                # scene size relative to the target
+//! effects = [
+//!     { kind = "blur", radius = 2.0 },       # gaussian, in pixels
+//!     { kind = "scale", size = 0.5 },        # following effects at half size
+//!     { kind = "vignette", strength = 0.5 }, # 1 makes the corners black
+//! ]
+//!
+//! [color_correction]         # applied to every frame before present
+//! gamma = [2.2, 2.2, 2.2]    # per channel, output = input ^ (1 / gamma),
+//!                            # or: lut = "panel.cube", a 3D LUT in .cube
+//!                            # format
+//!
+//! [text]
+//! font = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"
+//! size = 16.0                # in pixels
+//!
+//! [framebuffer]              # `fb` backend and software fallback
+//! device = "/dev/fb0"
+//! software_fallback = true   # draw on the CPU when Vulkan can't be set up
+//!
+//! [watchdog]                 # durations in milliseconds
+//! acquire_timeout = 1000     # waiting for a swapchain image
+//! fence_timeout = 2000       # waiting for the GPU to finish a submission
+//! stall = 3000               # a frame stuck in one stage that long is
+//!                            # reported, 0 disables the watchdog thread
+//! recoveries = 3             # device recreations after a lost device or
+//!                            # a timeout before giving up
+//! ```
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use toml;
+
+use input::Key;
+use run::orientation::Orientation;
+
+use vulkano::format::Format;
+use vulkano::swapchain::PresentMode;
+
+#[cfg(test)]
+mod tests;
+
+pub const PATH_ENV: &str = "SLICER_CONFIG";
+pub const DEFAULT_PATH: &str = "slicer.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub clear_color: [f32; 4],
+    pub validation: bool,
+    pub pipeline_cache: Option<PathBuf>,
+    pub device: DeviceConfig,
+    pub swapchain: SwapchainConfig,
+    pub display: DisplayConfig,
+    pub window: WindowConfig,
+    pub orientation: OrientationConfig,
+    pub viewport: ViewportConfig,
+    pub headless: HeadlessConfig,
+    pub framebuffer: FramebufferConfig,
+    pub keybindings: KeybindingsConfig,
+    pub post_process: PostProcessConfig,
+    pub color_correction: ColorCorrectionConfig,
+    pub text: TextConfig,
+    pub watchdog: WatchdogConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceConfig {
+    pub index: Option<usize>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwapchainConfig {
+    pub format: Option<String>,
+    pub present_mode: PresentModeConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub index: usize,
+    pub plane: usize,
+    pub mode: Option<[u32; 2]>,
+    pub refresh_rate: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub title: String,
+    pub size: [u32; 2],
+    pub fullscreen: bool,
+    pub monitor: Option<String>,
+    pub decorations: bool,
+    pub resizable: bool,
+    pub cursor: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrientationConfig {
+    pub rotation: u32,
+    pub mirror: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewportConfig {
+    pub size: Option<[f32; 2]>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeadlessConfig {
+    pub size: [u32; 2],
+    pub frames: u32,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FramebufferConfig {
+    pub device: PathBuf,
+    pub software_fallback: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeybindingsConfig {
+    pub quit: String,
+    pub screenshot: String,
+    pub fullscreen: String,
+    pub stats: String,
+    pub reload: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostProcessConfig {
+    pub scale: f32,
+    pub effects: Vec<EffectConfig>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum EffectConfig {
+    Blur { radius: f32 },
+    Vignette { strength: f32 },
+    /// size of the following images relative to the target
+    Scale { size: f32 },
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorCorrectionConfig {
+    pub gamma: Option<[f32; 3]>,
+    pub lut: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextConfig {
+    /// TrueType or OpenType
+    pub font: PathBuf,
+    pub size: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchdogConfig {
+    /// in milliseconds, like the others
+    pub acquire_timeout: u64,
+    pub fence_timeout: u64,
+    pub stall: u64,
+    pub recoveries: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresentModeConfig {
+    Immediate,
+    Mailbox,
+    Fifo,
+    Relaxed,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid { key: &'static str, message: String },
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            clear_color: [0.0, 0.0, 1.0, 1.0],
+            validation: false,
+            pipeline_cache: None,
+            device: DeviceConfig::default(),
+            swapchain: SwapchainConfig::default(),
+            display: DisplayConfig::default(),
+            window: WindowConfig::default(),
+            orientation: OrientationConfig::default(),
+            viewport: ViewportConfig::default(),
+            headless: HeadlessConfig::default(),
+            framebuffer: FramebufferConfig::default(),
+            keybindings: KeybindingsConfig::default(),
+            post_process: PostProcessConfig::default(),
+            color_correction: ColorCorrectionConfig::default(),
+            text: TextConfig::default(),
+            watchdog: WatchdogConfig::default(),
+        }
+    }
+}
+
+impl Default for FramebufferConfig {
+    fn default() -> FramebufferConfig {
+        FramebufferConfig {
+            device: PathBuf::from("/dev/fb0"),
+            software_fallback: true,
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            title: "slicer".to_owned(),
+            size: [1280, 1024],
+            fullscreen: false,
+            monitor: None,
+            decorations: true,
+            resizable: true,
+            cursor: true,
+        }
+    }
+}
+
+impl Default for KeybindingsConfig {
+    fn default() -> KeybindingsConfig {
+        KeybindingsConfig {
+            quit: "Escape".to_owned(),
+            screenshot: "F12".to_owned(),
+            fullscreen: "F11".to_owned(),
+            stats: "F3".to_owned(),
+            reload: "F5".to_owned(),
+        }
+    }
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> PostProcessConfig {
+        PostProcessConfig {
+            scale: 1.0,
+            effects: Vec::new(),
+        }
+    }
+}
+
+impl Default for TextConfig {
+    fn default() -> TextConfig {
+        TextConfig {
+            font: PathBuf::from("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"),
+            size: 16.0,
+        }
+    }
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> WatchdogConfig {
+        WatchdogConfig {
+            acquire_timeout: 1000,
+            fence_timeout: 2000,
+            stall: 3000,
+            recoveries: 3,
+        }
+    }
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> HeadlessConfig {
+        HeadlessConfig {
+            size: [1024, 768],
+            frames: 1,
+            output: "frame-{}.png".to_owned(),
+        }
+    }
+}
+
+impl Default for SwapchainConfig {
+    fn default() -> SwapchainConfig {
+        SwapchainConfig {
+            format: None,
+            present_mode: PresentModeConfig::Fifo,
+        }
+    }
+}
+
+impl Config {
+    /// `path` comes from the command line and takes precedence over the
+    /// environment and the default location
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => match env::var_os(PATH_ENV) {
+                Some(path) => Some(PathBuf::from(path)),
+                None => {
+                    let default = PathBuf::from(DEFAULT_PATH);
+                    if default.exists() {
+                        Some(default)
+                    } else {
+                        None
+                    }
+                }
+            },
+        };
+
+        match path {
+            Some(path) => Config::from_file(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        let config: Config =
+            toml::from_str(&contents).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// checks the values serde can't check by itself
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.clear_color.iter().any(|c| *c < 0.0 || *c > 1.0) {
+            return Err(ConfigError::invalid(
+                "clear_color",
+                "components must be between 0.0 and 1.0",
+            ));
+        }
+
+        if self.device.index.is_some() && self.device.name.is_some() {
+            return Err(ConfigError::invalid(
+                "device",
+                "set either device.index or device.name, not both",
+            ));
+        }
+
+        if let Some(ref name) = self.swapchain.format {
+            if parse_format(name).is_none() {
+                return Err(ConfigError::invalid(
+                    "swapchain.format",
+                    format!("unknown format '{}'", name),
+                ));
+            }
+        }
+
+        if let Some(mode) = self.display.mode {
+            if mode[0] == 0 || mode[1] == 0 {
+                return Err(ConfigError::invalid("display.mode", "width and height must be non zero"));
+            }
+        }
+
+        if self.window.size[0] == 0 || self.window.size[1] == 0 {
+            return Err(ConfigError::invalid("window.size", "width and height must be non zero"));
+        }
+
+        Orientation::from_config(&self.orientation)?;
+
+        if let Some(size) = self.viewport.size {
+            if size[0] <= 0.0 || size[1] <= 0.0 {
+                return Err(ConfigError::invalid("viewport.size", "width and height must be positive"));
+            }
+        }
+
+        if self.headless.size[0] == 0 || self.headless.size[1] == 0 {
+            return Err(ConfigError::invalid("headless.size", "width and height must be non zero"));
+        }
+
+        for &(key, ref name) in &[
+            ("keybindings.quit", &self.keybindings.quit),
+            ("keybindings.screenshot", &self.keybindings.screenshot),
+            ("keybindings.fullscreen", &self.keybindings.fullscreen),
+            ("keybindings.stats", &self.keybindings.stats),
+            ("keybindings.reload", &self.keybindings.reload),
+        ] {
+            if Key::from_name(name).is_none() {
+                return Err(ConfigError::invalid(key, format!("unknown key '{}'", name)));
+            }
+        }
+
+        if self.post_process.scale <= 0.0 || self.post_process.scale > 4.0 {
+            return Err(ConfigError::invalid("post_process.scale", "must be above 0.0 and at most 4.0"));
+        }
+
+        for effect in &self.post_process.effects {
+            let message = match *effect {
+                EffectConfig::Blur { radius } if radius <= 0.0 || radius > 32.0 => {
+                    "blur radius must be above 0.0 and at most 32.0"
+                }
+                EffectConfig::Vignette { strength } if strength < 0.0 || strength > 1.0 => {
+                    "vignette strength must be between 0.0 and 1.0"
+                }
+                EffectConfig::Scale { size } if size <= 0.0 || size > 4.0 => {
+                    "scale size must be above 0.0 and at most 4.0"
+                }
+                _ => continue,
+            };
+            return Err(ConfigError::invalid("post_process.effects", message));
+        }
+
+        if self.color_correction.gamma.is_some() && self.color_correction.lut.is_some() {
+            return Err(ConfigError::invalid(
+                "color_correction",
+                "set either color_correction.gamma or color_correction.lut, not both",
+            ));
+        }
+
+        if let Some(gamma) = self.color_correction.gamma {
+            if gamma.iter().any(|g| *g <= 0.0) {
+                return Err(ConfigError::invalid("color_correction.gamma", "must be positive"));
+            }
+        }
+
+        if self.text.size < 4.0 || self.text.size > 256.0 {
+            return Err(ConfigError::invalid("text.size", "must be between 4.0 and 256.0"));
+        }
+
+        if self.watchdog.acquire_timeout == 0 {
+            return Err(ConfigError::invalid("watchdog.acquire_timeout", "must be non zero"));
+        }
+
+        if self.watchdog.fence_timeout == 0 {
+            return Err(ConfigError::invalid("watchdog.fence_timeout", "must be non zero"));
+        }
+
+        Ok(())
+    }
+}
+
+impl SwapchainConfig {
+    /// `None` means "first format supported by the surface"
+    pub fn format(&self) -> Option<Format> {
+        self.format.as_ref().and_then(|name| parse_format(name))
+    }
+}
+
+impl PresentModeConfig {
+    pub fn to_present_mode(self) -> PresentMode {
+        match self {
+            PresentModeConfig::Immediate => PresentMode::Immediate,
+            PresentModeConfig::Mailbox => PresentMode::Mailbox,
+            PresentModeConfig::Fifo => PresentMode::Fifo,
+            PresentModeConfig::Relaxed => PresentMode::Relaxed,
+        }
+    }
+}
+
+/// surface formats seen on the boards and desktops we run on
+pub fn parse_format(name: &str) -> Option<Format> {
+    let format = match name {
+        "B8G8R8A8Unorm" => Format::B8G8R8A8Unorm,
+        "B8G8R8A8Srgb" => Format::B8G8R8A8Srgb,
+        "R8G8B8A8Unorm" => Format::R8G8B8A8Unorm,
+        "R8G8B8A8Srgb" => Format::R8G8B8A8Srgb,
+        "A8B8G8R8UnormPack32" => Format::A8B8G8R8UnormPack32,
+        "A8B8G8R8SrgbPack32" => Format::A8B8G8R8SrgbPack32,
+        "A2B10G10R10UnormPack32" => Format::A2B10G10R10UnormPack32,
+        "A2R10G10B10UnormPack32" => Format::A2R10G10B10UnormPack32,
+        "R5G6B5UnormPack16" => Format::R5G6B5UnormPack16,
+        "B5G6R5UnormPack16" => Format::B5G6R5UnormPack16,
+        _ => return None,
+    };
+    Some(format)
+}
+
+impl ConfigError {
+    /// `key` has a value the code or the device can't use
+    pub fn invalid<S: Into<String>>(key: &'static str, message: S) -> ConfigError {
+        ConfigError::Invalid {
+            key: key,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Parse(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Invalid { key, ref message } => write!(f, "invalid `{}`: {}", key, message),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            ConfigError::Io(..) => "failed to read configuration",
+            ConfigError::Parse(..) => "failed to parse configuration",
+            ConfigError::Invalid { .. } => "invalid configuration",
+        }
+    }
+}
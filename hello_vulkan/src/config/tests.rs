@@ -0,0 +1,83 @@
+use super::{Config, ConfigError, EffectConfig, PresentModeConfig};
+
+use std::path::PathBuf;
+
+use toml;
+
+/// the TOML example of the module documentation
+fn documented_example() -> String {
+    let source = include_str!("mod.rs");
+    source
+        .lines()
+        .skip_while(|line| *line != "//! ```toml")
+        .skip(1)
+        .take_while(|line| *line != "//! ```")
+        .map(|line| {
+            let line = &line[3..];
+            if line.starts_with(' ') {
+                &line[1..]
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn invalid_key(text: &str) -> &'static str {
+    let config: Config = toml::from_str(text).unwrap();
+    match config.validate() {
+        Err(ConfigError::Invalid { key, .. }) => key,
+        other => panic!("{:?} for {}", other, text),
+    }
+}
+
+#[test]
+fn documented_example_is_valid() {
+    let example = documented_example();
+    assert!(example.contains("[watchdog]"));
+    let config: Config = toml::from_str(&example).unwrap();
+    config.validate().unwrap();
+
+    assert_eq!(config.pipeline_cache, Some(PathBuf::from("/var/cache/slicer/pipeline.cache")));
+    assert_eq!(config.device.index, Some(0));
+    assert_eq!(config.swapchain.present_mode, PresentModeConfig::Fifo);
+    assert_eq!(config.orientation.rotation, 90);
+    assert_eq!(config.post_process.effects.len(), 3);
+    assert_eq!(config.post_process.effects[1], EffectConfig::Scale { size: 0.5 });
+    assert_eq!(config.watchdog.recoveries, 3);
+}
+
+#[test]
+fn defaults_are_valid() {
+    Config::default().validate().unwrap();
+    let config: Config = toml::from_str("").unwrap();
+    config.validate().unwrap();
+}
+
+#[test]
+fn invalid_values_name_their_key() {
+    assert_eq!(invalid_key("clear_color = [2.0, 0.0, 0.0, 1.0]"), "clear_color");
+    assert_eq!(invalid_key("[device]\nindex = 0\nname = \"Mali\""), "device");
+    assert_eq!(invalid_key("[swapchain]\nformat = \"R8Unorm\""), "swapchain.format");
+    assert_eq!(invalid_key("[window]\nsize = [0, 600]"), "window.size");
+    assert_eq!(invalid_key("[orientation]\nrotation = 45"), "orientation.rotation");
+    assert_eq!(invalid_key("[keybindings]\nquit = \"Nope\""), "keybindings.quit");
+    assert_eq!(
+        invalid_key("[post_process]\neffects = [{ kind = \"blur\", radius = 0.0 }]"),
+        "post_process.effects"
+    );
+    assert_eq!(
+        invalid_key("[color_correction]\ngamma = [2.2, 2.2, 2.2]\nlut = \"panel.cube\""),
+        "color_correction"
+    );
+    assert_eq!(invalid_key("[text]\nsize = 2.0"), "text.size");
+    assert_eq!(invalid_key("[watchdog]\nfence_timeout = 0"), "watchdog.fence_timeout");
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    assert!(toml::from_str::<Config>("pipline_cache = \"/tmp/cache\"").is_err());
+    assert!(toml::from_str::<Config>("[window]\nwidth = 640").is_err());
+    assert!(toml::from_str::<Config>("[post_process]\neffects = [{ kind = \"sharpen\" }]").is_err());
+}
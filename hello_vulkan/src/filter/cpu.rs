@@ -0,0 +1,169 @@
+//! The filters on the CPU, the reference for the compute kernels
+
+use image::RgbaImage;
+
+use super::{gaussian_weights, ColorMatrix, Filter};
+
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// normalized RGBA, read with the edges clamped
+struct Pixels {
+    width: u32,
+    height: u32,
+    data: Vec<[f32; 4]>,
+}
+
+impl Pixels {
+    fn load(image: &RgbaImage) -> Pixels {
+        Pixels {
+            width: image.width(),
+            height: image.height(),
+            data: image
+                .pixels()
+                .map(|p| {
+                    let c = p.data;
+                    [
+                        f32::from(c[0]) / 255.0,
+                        f32::from(c[1]) / 255.0,
+                        f32::from(c[2]) / 255.0,
+                        f32::from(c[3]) / 255.0,
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// rounds like a store to a UNORM image
+    fn store(&self) -> RgbaImage {
+        let to_unorm = |v: f32| (v.max(0.0).min(1.0) * 255.0 + 0.5) as u8;
+        let bytes = self.data
+            .iter()
+            .flat_map(|p| p.iter().map(|&v| to_unorm(v)).collect::<Vec<_>>())
+            .collect();
+        RgbaImage::from_raw(self.width, self.height, bytes).expect("pixels match the size")
+    }
+
+    fn at(&self, x: i64, y: i64) -> [f32; 4] {
+        let x = x.max(0).min(i64::from(self.width) - 1);
+        let y = y.max(0).min(i64::from(self.height) - 1);
+        self.data[(y * i64::from(self.width) + x) as usize]
+    }
+
+    fn map<F: Fn(i64, i64) -> [f32; 4]>(width: u32, height: u32, f: F) -> Pixels {
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for y in 0..i64::from(height) {
+            for x in 0..i64::from(width) {
+                data.push(f(x, y));
+            }
+        }
+        Pixels {
+            width: width,
+            height: height,
+            data: data,
+        }
+    }
+}
+
+pub fn apply_chain(image: &RgbaImage, filters: &[Filter]) -> RgbaImage {
+    filters
+        .iter()
+        .fold(image.clone(), |image, filter| apply(&image, filter))
+}
+
+pub fn apply(image: &RgbaImage, filter: &Filter) -> RgbaImage {
+    let source = Pixels::load(image);
+    match *filter {
+        Filter::Resize { width, height } => resize(&source, width, height).store(),
+        Filter::Convert(ref matrix) => convert(&source, matrix).store(),
+        Filter::Convolve { size, ref weights } => convolve(&source, size, weights).store(),
+        Filter::Threshold(level) => threshold(&source, level).store(),
+        Filter::Blur(radius) => {
+            // the kernels store the horizontal pass in 8 bits too
+            let weights = gaussian_weights(radius);
+            let horizontal = Pixels::load(&blur_pass(&source, &weights, [1, 0]).store());
+            blur_pass(&horizontal, &weights, [0, 1]).store()
+        }
+    }
+}
+
+fn resize(source: &Pixels, width: u32, height: u32) -> Pixels {
+    let scale = [
+        source.width as f32 / width as f32,
+        source.height as f32 / height as f32,
+    ];
+    let max = [(source.width - 1) as f32, (source.height - 1) as f32];
+    Pixels::map(width, height, |x, y| {
+        let px = ((x as f32 + 0.5) * scale[0] - 0.5).max(0.0).min(max[0]);
+        let py = ((y as f32 + 0.5) * scale[1] - 0.5).max(0.0).min(max[1]);
+        let (x0, y0) = (px.floor(), py.floor());
+        let (fx, fy) = (px - x0, py - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let (a, b) = (source.at(x0, y0), source.at(x0 + 1, y0));
+        let (c, d) = (source.at(x0, y0 + 1), source.at(x0 + 1, y0 + 1));
+        mix(mix(a, b, fx), mix(c, d, fx), fy)
+    })
+}
+
+fn convert(source: &Pixels, matrix: &ColorMatrix) -> Pixels {
+    Pixels::map(source.width, source.height, |x, y| {
+        let input = source.at(x, y);
+        let mut out = matrix.offset;
+        for (column, &value) in matrix.columns.iter().zip(input.iter()) {
+            for (out, &factor) in out.iter_mut().zip(column.iter()) {
+                *out += factor * value;
+            }
+        }
+        out
+    })
+}
+
+/// on the colour, the alpha of the centre is kept
+fn convolve(source: &Pixels, size: u32, weights: &[f32]) -> Pixels {
+    let half = i64::from(size / 2);
+    Pixels::map(source.width, source.height, |x, y| {
+        let mut out = [0.0, 0.0, 0.0, source.at(x, y)[3]];
+        for (index, &weight) in weights.iter().enumerate() {
+            let dx = index as i64 % i64::from(size) - half;
+            let dy = index as i64 / i64::from(size) - half;
+            let p = source.at(x + dx, y + dy);
+            add_scaled(&mut out, [p[0], p[1], p[2], 0.0], weight);
+        }
+        out
+    })
+}
+
+fn threshold(source: &Pixels, level: f32) -> Pixels {
+    Pixels::map(source.width, source.height, |x, y| {
+        let p = source.at(x, y);
+        let luma = p[0] * LUMA[0] + p[1] * LUMA[1] + p[2] * LUMA[2];
+        let v = if luma >= level { 1.0 } else { 0.0 };
+        [v, v, v, p[3]]
+    })
+}
+
+fn blur_pass(source: &Pixels, weights: &[f32], direction: [i64; 2]) -> Pixels {
+    Pixels::map(source.width, source.height, |x, y| {
+        let mut out = [0.0; 4];
+        add_scaled(&mut out, source.at(x, y), weights[0]);
+        for (step, &weight) in weights.iter().enumerate().skip(1) {
+            let (dx, dy) = (direction[0] * step as i64, direction[1] * step as i64);
+            add_scaled(&mut out, source.at(x + dx, y + dy), weight);
+            add_scaled(&mut out, source.at(x - dx, y - dy), weight);
+        }
+        out
+    })
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    let mut out = a;
+    for (out, &b) in out.iter_mut().zip(b.iter()) {
+        *out += (b - *out) * t;
+    }
+    out
+}
+
+fn add_scaled(out: &mut [f32; 4], p: [f32; 4], weight: f32) {
+    for (out, &p) in out.iter_mut().zip(p.iter()) {
+        *out += p * weight;
+    }
+}
@@ -0,0 +1,180 @@
+//! Image filters, run as compute kernels or on the CPU
+//!
+//! Each filter is given on the command line as `name=value`:
+//!
+//! ```text
+//! resize=640x480          bilinear
+//! convert=gray            or sepia, invert, swap-rb
+//! convolve=sharpen        or edge, emboss, box, or 9 or 25 weights
+//!                         separated by commas, row by row
+//! threshold=0.5           white where the luma reaches it, else black
+//! blur=4                  gaussian, reaching that many pixels
+//! ```
+//!
+//! Both implementations do the same maths in `f32` and round to 8 bits
+//! between passes, so their outputs differ by at most one step.
+
+use image::RgbaImage;
+
+pub mod cpu;
+
+#[cfg(test)]
+mod tests;
+
+/// longest kernel reach `blur` accepts, in pixels
+pub const MAX_BLUR_RADIUS: u32 = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Resize { width: u32, height: u32 },
+    Convert(ColorMatrix),
+    /// `size` by `size` weights, row by row
+    Convolve { size: u32, weights: Vec<f32> },
+    Threshold(f32),
+    Blur(u32),
+}
+
+/// `output = columns * input + offset`, on RGBA
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorMatrix {
+    pub columns: [[f32; 4]; 4],
+    pub offset: [f32; 4],
+}
+
+impl ColorMatrix {
+    pub fn from_name(name: &str) -> Option<ColorMatrix> {
+        let (r, g, b) = match name {
+            // Rec. 709 luma
+            "gray" => ([0.2126; 3], [0.7152; 3], [0.0722; 3]),
+            "sepia" => ([0.393, 0.349, 0.272], [0.769, 0.686, 0.534], [0.189, 0.168, 0.131]),
+            "invert" => {
+                return Some(ColorMatrix {
+                    columns: [
+                        [-1.0, 0.0, 0.0, 0.0],
+                        [0.0, -1.0, 0.0, 0.0],
+                        [0.0, 0.0, -1.0, 0.0],
+                        [0.0, 0.0, 0.0, 1.0],
+                    ],
+                    offset: [1.0, 1.0, 1.0, 0.0],
+                })
+            }
+            "swap-rb" => ([0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+            _ => return None,
+        };
+        Some(ColorMatrix {
+            columns: [
+                [r[0], r[1], r[2], 0.0],
+                [g[0], g[1], g[2], 0.0],
+                [b[0], b[1], b[2], 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            offset: [0.0; 4],
+        })
+    }
+}
+
+impl Filter {
+    /// `spec` as described in the module documentation
+    pub fn parse(spec: &str) -> Result<Filter, String> {
+        let mut parts = spec.splitn(2, '=');
+        let name = parts.next().unwrap_or("");
+        let value = match parts.next() {
+            Some(value) => value,
+            None => return Err(format!("filter '{}' needs a value, as in {}=...", spec, name)),
+        };
+        let invalid = |expected: &str| format!("invalid {} '{}', expected {}", name, value, expected);
+
+        match name {
+            "resize" => {
+                // exactly two sizes, each of which must parse
+                let mut sizes = value.split('x').map(|v| v.parse::<u32>().ok());
+                match (sizes.next(), sizes.next(), sizes.next()) {
+                    (Some(Some(width)), Some(Some(height)), None) if width > 0 && height > 0 => {
+                        Ok(Filter::Resize {
+                            width: width,
+                            height: height,
+                        })
+                    }
+                    _ => Err(invalid("<width>x<height>")),
+                }
+            }
+            "convert" => ColorMatrix::from_name(value)
+                .map(Filter::Convert)
+                .ok_or_else(|| invalid("gray, sepia, invert or swap-rb")),
+            "convolve" => {
+                let weights = match value {
+                    "sharpen" => vec![0.0, -1.0, 0.0, -1.0, 5.0, -1.0, 0.0, -1.0, 0.0],
+                    "edge" => vec![-1.0, -1.0, -1.0, -1.0, 8.0, -1.0, -1.0, -1.0, -1.0],
+                    "emboss" => vec![-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0],
+                    "box" => vec![1.0 / 9.0; 9],
+                    _ => value
+                        .split(',')
+                        .map(|w| w.trim().parse::<f32>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| invalid("a preset or comma separated weights"))?,
+                };
+                match weights.len() {
+                    9 => Ok(Filter::Convolve {
+                        size: 3,
+                        weights: weights,
+                    }),
+                    25 => Ok(Filter::Convolve {
+                        size: 5,
+                        weights: weights,
+                    }),
+                    _ => Err(invalid("9 or 25 weights")),
+                }
+            }
+            "threshold" => match value.parse::<f32>() {
+                Ok(level) if level >= 0.0 && level <= 1.0 => Ok(Filter::Threshold(level)),
+                _ => Err(invalid("a level between 0.0 and 1.0")),
+            },
+            "blur" => match value.parse::<u32>() {
+                Ok(radius) if radius <= MAX_BLUR_RADIUS => Ok(Filter::Blur(radius)),
+                _ => Err(invalid(&format!("a radius of at most {} pixels", MAX_BLUR_RADIUS))),
+            },
+            _ => Err(format!(
+                "unknown filter '{}', available: resize, convert, convolve, threshold, blur",
+                name
+            )),
+        }
+    }
+
+    /// size of the image this filter makes from one of `size`
+    pub fn output_size(&self, size: [u32; 2]) -> [u32; 2] {
+        match *self {
+            Filter::Resize { width, height } => [width, height],
+            _ => size,
+        }
+    }
+}
+
+/// the centre tap then one per pixel up to `radius`, reaching three
+/// standard deviations; the whole kernel sums to 1
+pub fn gaussian_weights(radius: u32) -> Vec<f32> {
+    if radius == 0 {
+        return vec![1.0];
+    }
+    let sigma = radius as f32 / 3.0;
+    let weights: Vec<f32> = (0..radius + 1)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+/// largest and mean difference between the channels of `a` and `b`,
+/// `None` when their sizes differ
+pub fn difference(a: &RgbaImage, b: &RgbaImage) -> Option<(u8, f64)> {
+    if a.dimensions() != b.dimensions() {
+        return None;
+    }
+    let mut largest = 0;
+    let mut total = 0u64;
+    for (x, y) in a.iter().zip(b.iter()) {
+        let d = if x > y { x - y } else { y - x };
+        largest = largest.max(d);
+        total += u64::from(d);
+    }
+    Some((largest, total as f64 / a.len().max(1) as f64))
+}
@@ -0,0 +1,100 @@
+use image::{Rgba, RgbaImage};
+
+use super::{cpu, difference, gaussian_weights, ColorMatrix, Filter};
+
+fn solid(width: u32, height: u32, color: [u8; 4]) -> RgbaImage {
+    RgbaImage::from_pixel(width, height, Rgba { data: color })
+}
+
+#[test]
+fn specs_are_parsed() {
+    assert_eq!(
+        Filter::parse("resize=640x480"),
+        Ok(Filter::Resize {
+            width: 640,
+            height: 480,
+        })
+    );
+    assert_eq!(
+        Filter::parse("convert=gray"),
+        Ok(Filter::Convert(ColorMatrix::from_name("gray").unwrap()))
+    );
+    assert_eq!(Filter::parse("threshold=0.25"), Ok(Filter::Threshold(0.25)));
+    assert_eq!(Filter::parse("blur=8"), Ok(Filter::Blur(8)));
+    match Filter::parse("convolve=1, 2, 3, 4, 5, 6, 7, 8, 9") {
+        Ok(Filter::Convolve { size: 3, ref weights }) => assert_eq!(weights[8], 9.0),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    for spec in &[
+        "resize",
+        "resize=0x480",
+        "resize=640",
+        "resize=640xfoox480",
+        "resize=640x480x",
+        "convert=purple",
+        "convolve=1,2,3",
+        "threshold=2",
+        "blur=65",
+        "sharpen=1",
+    ] {
+        assert!(Filter::parse(spec).is_err(), "{} was accepted", spec);
+    }
+}
+
+#[test]
+fn weights_are_normalized() {
+    assert_eq!(gaussian_weights(0), [1.0]);
+
+    for &radius in &[1, 4, 64] {
+        let weights = gaussian_weights(radius);
+        assert_eq!(weights.len(), radius as usize + 1);
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+        assert!((total - 1.0).abs() < 1e-5, "radius {} sums to {}", radius, total);
+        assert!(weights.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+}
+
+#[test]
+fn constant_images_stay_constant() {
+    let image = solid(5, 3, [200, 100, 50, 255]);
+    let chain = [
+        Filter::parse("resize=12x7").unwrap(),
+        Filter::parse("convolve=box").unwrap(),
+        Filter::parse("blur=3").unwrap(),
+    ];
+    let output = cpu::apply_chain(&image, &chain);
+    assert_eq!(output.dimensions(), (12, 7));
+    assert_eq!(difference(&output, &solid(12, 7, [200, 100, 50, 255])), Some((0, 0.0)));
+}
+
+#[test]
+fn colours_are_converted() {
+    let image = solid(2, 2, [10, 128, 250, 200]);
+    let invert = Filter::parse("convert=invert").unwrap();
+    assert_eq!(cpu::apply(&image, &invert).get_pixel(1, 1).data, [245, 127, 5, 200]);
+    let twice = cpu::apply_chain(&image, &[invert.clone(), invert]);
+    assert_eq!(difference(&twice, &image), Some((0, 0.0)));
+
+    let swapped = cpu::apply(&image, &Filter::parse("convert=swap-rb").unwrap());
+    assert_eq!(swapped.get_pixel(0, 0).data, [250, 128, 10, 200]);
+}
+
+#[test]
+fn threshold_splits_on_luma() {
+    let mut image = solid(2, 1, [255, 0, 0, 255]);
+    image.put_pixel(1, 0, Rgba { data: [0, 255, 0, 128] });
+    let output = cpu::apply(&image, &Filter::Threshold(0.5));
+    // red has a luma of 0.21, green of 0.72
+    assert_eq!(output.get_pixel(0, 0).data, [0, 0, 0, 255]);
+    assert_eq!(output.get_pixel(1, 0).data, [255, 255, 255, 128]);
+}
+
+#[test]
+fn differences_are_measured() {
+    let a = solid(2, 1, [10, 10, 10, 10]);
+    let mut b = a.clone();
+    b.put_pixel(0, 0, Rgba { data: [14, 10, 10, 10] });
+    assert_eq!(difference(&a, &b), Some((4, 0.5)));
+    assert_eq!(difference(&a, &solid(1, 2, [10; 4])), None);
+}
@@ -0,0 +1,729 @@
+//! Linux input event devices (`/dev/input/event*`)
+//!
+//! `Decoder` turns the `struct input_event` stream of a device into
+//! `InputEvent`s: keys and buttons, relative pointers and multi-touch
+//! protocol B. `EventReader` decodes from anything implementing `Read`, so
+//! recorded streams can stand in for devices, and `InputDevices` keeps
+//! every device of a directory open, picking up the ones plugged in later.
+//!
+//! When the kernel drops events (`SYN_DROPPED`) the rest of the report is
+//! skipped and the decoder is brought up to the `DeviceState` read from
+//! the device: keys held or released meanwhile and touches started, moved
+//! or ended come out as if reported.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use log_targets::INFO;
+
+use super::{InputEvent, Key, MouseButton, TouchPhase};
+
+pub const DEFAULT_DIR: &str = "/dev/input";
+const RESCAN_INTERVAL_SECS: u64 = 1;
+
+pub const EV_SYN: u16 = 0x00;
+pub const EV_KEY: u16 = 0x01;
+pub const EV_REL: u16 = 0x02;
+pub const EV_ABS: u16 = 0x03;
+
+pub const SYN_REPORT: u16 = 0;
+pub const SYN_DROPPED: u16 = 3;
+
+pub const REL_X: u16 = 0x00;
+pub const REL_Y: u16 = 0x01;
+pub const REL_HWHEEL: u16 = 0x06;
+pub const REL_WHEEL: u16 = 0x08;
+
+pub const ABS_MT_SLOT: u16 = 0x2f;
+pub const ABS_MT_POSITION_X: u16 = 0x35;
+pub const ABS_MT_POSITION_Y: u16 = 0x36;
+pub const ABS_MT_TRACKING_ID: u16 = 0x39;
+
+pub const BTN_LEFT: u16 = 0x110;
+pub const BTN_RIGHT: u16 = 0x111;
+pub const BTN_MIDDLE: u16 = 0x112;
+const BTN_TASK: u16 = 0x117;
+
+/// `EVIOCGABS(0)`, add the axis
+const EVIOCGABS: libc::c_ulong = 0x8018_4540;
+/// `nr` of `EVIOCGKEY(len)` and `EVIOCGMTSLOTS(len)`
+const EVIOCGKEY: libc::c_ulong = 0x18;
+const EVIOCGMTSLOTS: libc::c_ulong = 0x0a;
+const KEY_MAX: usize = 0x2ff;
+
+/// one `struct input_event`, without its timestamp
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RawEvent {
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+/// keeps the state spread over the events of a report
+pub struct Decoder {
+    /// `(min, max)` of the touch axes, `None` passes raw values through
+    touch_range: [Option<(i32, i32)>; 2],
+    screen: [u32; 2],
+    cursor: [f32; 2],
+    slots: Vec<Slot>,
+    slot: usize,
+    /// relative motion and scrolling of the current report
+    motion: [i32; 2],
+    scroll: [i32; 2],
+    /// keys and buttons of the current report
+    pending: Vec<InputEvent>,
+    /// keys and buttons down, to release them after dropped events
+    held: BTreeSet<u16>,
+    /// events were lost, everything is ignored up to the next report
+    dropped: bool,
+    /// the report after the lost events ended, see `resync`
+    stale: bool,
+}
+
+/// what a device reports as held and touched, see `query_state`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceState {
+    /// codes of the keys and buttons down
+    pub keys: Vec<u16>,
+    /// the selected multi-touch slot
+    pub slot: usize,
+    pub slots: Vec<SlotState>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SlotState {
+    pub id: Option<u32>,
+    pub position: [i32; 2],
+}
+
+/// a multi-touch slot, touches are reported when the report completes
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    id: Option<u32>,
+    position: [i32; 2],
+    started: bool,
+    moved: bool,
+    ended: Option<u32>,
+}
+
+pub struct EventReader<R> {
+    reader: R,
+    decoder: Decoder,
+    /// an event split over two reads
+    partial: Vec<u8>,
+    /// the state to resync to after dropped events
+    query: fn(&R) -> io::Result<DeviceState>,
+}
+
+/// every event device of a directory, rescanned for hotplugged ones
+pub struct InputDevices {
+    dir: PathBuf,
+    devices: Vec<(PathBuf, EventReader<File>)>,
+    /// devices which failed to open, not retried until they disappear
+    ignored: HashSet<PathBuf>,
+    last_scan: Option<Instant>,
+    screen: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct InputAbsinfo {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
+/// size of `struct input_event`, whose timestamp is a `timeval`
+pub fn event_size() -> usize {
+    mem::size_of::<libc::timeval>() + 8
+}
+
+impl RawEvent {
+    /// `bytes` holds one `struct input_event` in native byte order
+    pub fn parse(bytes: &[u8]) -> RawEvent {
+        assert!(bytes.len() >= event_size(), "truncated input event");
+        let offset = mem::size_of::<libc::timeval>();
+        unsafe {
+            RawEvent {
+                kind: ptr::read_unaligned(bytes[offset..].as_ptr() as *const u16),
+                code: ptr::read_unaligned(bytes[offset + 2..].as_ptr() as *const u16),
+                value: ptr::read_unaligned(bytes[offset + 4..].as_ptr() as *const i32),
+            }
+        }
+    }
+
+    /// the `struct input_event` with a zero timestamp, to record streams
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; event_size()];
+        let offset = mem::size_of::<libc::timeval>();
+        unsafe {
+            ptr::write_unaligned(bytes[offset..].as_mut_ptr() as *mut u16, self.kind);
+            ptr::write_unaligned(bytes[offset + 2..].as_mut_ptr() as *mut u16, self.code);
+            ptr::write_unaligned(bytes[offset + 4..].as_mut_ptr() as *mut i32, self.value);
+        }
+        bytes
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder {
+            touch_range: [None, None],
+            screen: [0, 0],
+            cursor: [0.0, 0.0],
+            slots: vec![Slot::default()],
+            slot: 0,
+            motion: [0, 0],
+            scroll: [0, 0],
+            pending: Vec::new(),
+            held: BTreeSet::new(),
+            dropped: false,
+            stale: false,
+        }
+    }
+
+    /// touches are scaled to `screen`, the cursor is kept inside and
+    /// starts at its centre
+    pub fn set_screen(&mut self, screen: [u32; 2]) {
+        self.screen = screen;
+        self.cursor = [screen[0] as f32 / 2.0, screen[1] as f32 / 2.0];
+    }
+
+    pub fn set_touch_range(&mut self, x: (i32, i32), y: (i32, i32)) {
+        self.touch_range = [Some(x), Some(y)];
+    }
+
+    /// appends to `out` the events completed by `event`
+    pub fn decode(&mut self, event: RawEvent, out: &mut Vec<InputEvent>) {
+        if self.dropped {
+            if event.kind == EV_SYN && event.code == SYN_REPORT {
+                self.dropped = false;
+                self.stale = true;
+            }
+            return;
+        }
+
+        match (event.kind, event.code) {
+            (EV_SYN, SYN_REPORT) => self.report(out),
+            (EV_SYN, SYN_DROPPED) => self.dropped = true,
+            (EV_KEY, code) => self.key(code, event.value),
+            (EV_REL, REL_X) => self.motion[0] += event.value,
+            (EV_REL, REL_Y) => self.motion[1] += event.value,
+            (EV_REL, REL_HWHEEL) => self.scroll[0] += event.value,
+            (EV_REL, REL_WHEEL) => self.scroll[1] += event.value,
+            (EV_ABS, ABS_MT_SLOT) => self.select_slot(event.value.max(0) as usize),
+            (EV_ABS, ABS_MT_TRACKING_ID) => self.tracking_id(event.value),
+            (EV_ABS, ABS_MT_POSITION_X) => self.position(0, event.value),
+            (EV_ABS, ABS_MT_POSITION_Y) => self.position(1, event.value),
+            _ => (),
+        }
+    }
+
+    /// whether events were dropped, `resync` before decoding more
+    pub fn needs_resync(&self) -> bool {
+        self.stale
+    }
+
+    /// appends to `out` what changed from the decoded state to `state`,
+    /// read from the device after dropped events; the default state
+    /// releases every key and ends every touch
+    pub fn resync(&mut self, state: &DeviceState, out: &mut Vec<InputEvent>) {
+        self.stale = false;
+        // the rest of the report was lost
+        self.pending.clear();
+        self.motion = [0, 0];
+        self.scroll = [0, 0];
+
+        let released: Vec<u16> = self.held
+            .iter()
+            .cloned()
+            .filter(|code| !state.keys.contains(code))
+            .collect();
+        for code in released {
+            self.key(code, 0);
+        }
+        for &code in &state.keys {
+            if !self.held.contains(&code) {
+                self.key(code, 1);
+            }
+        }
+
+        let selected = self.slot;
+        for index in 0..self.slots.len().max(state.slots.len()) {
+            let target = state.slots.get(index).cloned().unwrap_or_default();
+            self.select_slot(index);
+            if self.slots[index].id != target.id {
+                self.tracking_id(target.id.map_or(-1, |id| id as i32));
+            }
+            if target.id.is_some() {
+                for axis in 0..2 {
+                    if self.slots[index].position[axis] != target.position[axis] {
+                        self.position(axis, target.position[axis]);
+                    }
+                }
+            }
+        }
+        self.select_slot(if state.slots.is_empty() { selected } else { state.slot });
+        self.report(out);
+    }
+
+    fn select_slot(&mut self, slot: usize) {
+        if slot >= self.slots.len() {
+            self.slots.resize(slot + 1, Slot::default());
+        }
+        self.slot = slot;
+    }
+
+    fn key(&mut self, code: u16, value: i32) {
+        // 2 is autorepeat, `InputState` only cares about transitions
+        if value == 2 {
+            return;
+        }
+        let pressed = value != 0;
+        if pressed {
+            self.held.insert(code);
+        } else {
+            self.held.remove(&code);
+        }
+        let button = match code {
+            BTN_LEFT => Some(MouseButton::Left),
+            BTN_RIGHT => Some(MouseButton::Right),
+            BTN_MIDDLE => Some(MouseButton::Middle),
+            BTN_LEFT..=BTN_TASK => Some(MouseButton::Other((code - BTN_LEFT) as u8)),
+            _ => None,
+        };
+        let event = match button {
+            Some(button) => Some(InputEvent::MouseButton {
+                button: button,
+                pressed: pressed,
+            }),
+            None => linux_key(code).map(|key| InputEvent::Key {
+                key: key,
+                pressed: pressed,
+            }),
+        };
+        if let Some(event) = event {
+            self.pending.push(event);
+        }
+    }
+
+    fn tracking_id(&mut self, value: i32) {
+        let slot = &mut self.slots[self.slot];
+        if let Some(id) = slot.id.take() {
+            slot.ended = Some(id);
+            slot.started = false;
+            slot.moved = false;
+        }
+        if value >= 0 {
+            slot.id = Some(value as u32);
+            slot.started = true;
+        }
+    }
+
+    fn position(&mut self, axis: usize, value: i32) {
+        let slot = &mut self.slots[self.slot];
+        slot.position[axis] = value;
+        if slot.id.is_some() {
+            slot.moved = true;
+        }
+    }
+
+    fn report(&mut self, out: &mut Vec<InputEvent>) {
+        out.extend(self.pending.drain(..));
+
+        if self.motion != [0, 0] {
+            for axis in 0..2 {
+                let max = if self.screen[axis] > 0 {
+                    self.screen[axis] as f32
+                } else {
+                    ::std::f32::MAX
+                };
+                self.cursor[axis] = (self.cursor[axis] + self.motion[axis] as f32)
+                    .max(0.0)
+                    .min(max);
+            }
+            out.push(InputEvent::CursorMoved(self.cursor));
+            self.motion = [0, 0];
+        }
+
+        if self.scroll != [0, 0] {
+            out.push(InputEvent::Scroll([self.scroll[0] as f32, self.scroll[1] as f32]));
+            self.scroll = [0, 0];
+        }
+
+        for index in 0..self.slots.len() {
+            let position = self.scale(self.slots[index].position);
+            let slot = &mut self.slots[index];
+            if let Some(id) = slot.ended.take() {
+                out.push(InputEvent::Touch {
+                    id: id,
+                    phase: TouchPhase::Ended,
+                    position: position,
+                });
+            }
+            if let Some(id) = slot.id {
+                let phase = if slot.started {
+                    Some(TouchPhase::Started)
+                } else if slot.moved {
+                    Some(TouchPhase::Moved)
+                } else {
+                    None
+                };
+                if let Some(phase) = phase {
+                    out.push(InputEvent::Touch {
+                        id: id,
+                        phase: phase,
+                        position: position,
+                    });
+                }
+            }
+            slot.started = false;
+            slot.moved = false;
+        }
+    }
+
+    fn scale(&self, position: [i32; 2]) -> [f32; 2] {
+        let mut scaled = [position[0] as f32, position[1] as f32];
+        for axis in 0..2 {
+            if let Some((min, max)) = self.touch_range[axis] {
+                if max > min && self.screen[axis] > 0 {
+                    scaled[axis] = (position[axis] - min) as f32 / (max - min) as f32
+                        * self.screen[axis] as f32;
+                }
+            }
+        }
+        scaled
+    }
+}
+
+impl<R: Read> EventReader<R> {
+    /// without a device to query, dropped events release every key and
+    /// end every touch
+    pub fn new(reader: R) -> EventReader<R> {
+        EventReader::with_query(reader, |_| Ok(DeviceState::default()))
+    }
+
+    /// `query` reads the state of `reader` after dropped events
+    pub fn with_query(reader: R, query: fn(&R) -> io::Result<DeviceState>) -> EventReader<R> {
+        EventReader {
+            reader: reader,
+            decoder: Decoder::new(),
+            partial: Vec::new(),
+            query: query,
+        }
+    }
+
+    pub fn decoder_mut(&mut self) -> &mut Decoder {
+        &mut self.decoder
+    }
+
+    /// decodes until the reader would block, `Ok(false)` when the stream
+    /// ended
+    pub fn read_events(&mut self, out: &mut Vec<InputEvent>) -> io::Result<bool> {
+        let size = event_size();
+        let mut buffer = [0u8; 1024];
+        loop {
+            let read = match self.reader.read(&mut buffer) {
+                Ok(0) => return Ok(false),
+                Ok(read) => read,
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            self.partial.extend_from_slice(&buffer[..read]);
+
+            let complete = self.partial.len() / size * size;
+            for bytes in self.partial[..complete].chunks(size) {
+                self.decoder.decode(RawEvent::parse(bytes), out);
+                if self.decoder.needs_resync() {
+                    let state = (self.query)(&self.reader).unwrap_or_else(|err| {
+                        warn!(target: INFO, "failed to read the device state after dropped events: {}", err);
+                        DeviceState::default()
+                    });
+                    self.decoder.resync(&state, out);
+                }
+            }
+            self.partial.drain(..complete);
+        }
+    }
+}
+
+impl InputDevices {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> InputDevices {
+        InputDevices {
+            dir: dir.into(),
+            devices: Vec::new(),
+            ignored: HashSet::new(),
+            last_scan: None,
+            screen: [0, 0],
+        }
+    }
+
+    /// size of the panel, in pixels
+    pub fn set_screen(&mut self, screen: [u32; 2]) {
+        self.screen = screen;
+        for &mut (_, ref mut device) in &mut self.devices {
+            device.decoder_mut().set_screen(screen);
+        }
+    }
+
+    /// events of every device since the last call, never blocks
+    pub fn poll(&mut self) -> Vec<InputEvent> {
+        let rescan = self.last_scan
+            .map_or(true, |last| last.elapsed() >= Duration::from_secs(RESCAN_INTERVAL_SECS));
+        if rescan {
+            self.scan();
+        }
+
+        let mut events = Vec::new();
+        let mut removed = Vec::new();
+        for &mut (ref path, ref mut device) in &mut self.devices {
+            match device.read_events(&mut events) {
+                Ok(true) => (),
+                Ok(false) => removed.push(path.clone()),
+                Err(err) => {
+                    // ENODEV once unplugged
+                    debug!(target: INFO, "{}: {}", path.display(), err);
+                    removed.push(path.clone());
+                }
+            }
+        }
+        for path in removed {
+            info!(target: INFO, "input device {} removed", path.display());
+            self.devices.retain(|&(ref open, _)| *open != path);
+        }
+        events
+    }
+
+    fn scan(&mut self) {
+        self.last_scan = Some(Instant::now());
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                debug!(target: INFO, "{}: {}", self.dir.display(), err);
+                return;
+            }
+        };
+
+        let mut present = HashSet::new();
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let is_event = entry
+                .file_name()
+                .to_str()
+                .map_or(false, |name| name.starts_with("event"));
+            if !is_event {
+                continue;
+            }
+            let path = entry.path();
+            present.insert(path.clone());
+            if self.ignored.contains(&path) || self.devices.iter().any(|&(ref open, _)| *open == path) {
+                continue;
+            }
+
+            match open_device(&path, self.screen) {
+                Ok(device) => {
+                    info!(target: INFO, "input device {} added", path.display());
+                    self.devices.push((path, device));
+                }
+                Err(err) => {
+                    warn!(target: INFO, "failed to open {}: {}", path.display(), err);
+                    self.ignored.insert(path);
+                }
+            }
+        }
+        self.ignored.retain(|path| present.contains(path));
+    }
+}
+
+fn open_device(path: &Path, screen: [u32; 2]) -> io::Result<EventReader<File>> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+    let touch_range = (
+        query_abs_range(&file, ABS_MT_POSITION_X),
+        query_abs_range(&file, ABS_MT_POSITION_Y),
+    );
+
+    let mut device = EventReader::with_query(file, query_state);
+    device.decoder_mut().set_screen(screen);
+    if let (Some(x), Some(y)) = touch_range {
+        device.decoder_mut().set_touch_range(x, y);
+    }
+    Ok(device)
+}
+
+/// `None` when the device has no such axis
+fn query_abs_range(file: &File, axis: u16) -> Option<(i32, i32)> {
+    match query_abs(file, axis) {
+        Ok(ref info) if info.maximum > info.minimum => Some((info.minimum, info.maximum)),
+        _ => None,
+    }
+}
+
+fn query_abs(file: &File, axis: u16) -> io::Result<InputAbsinfo> {
+    let mut info: InputAbsinfo = Default::default();
+    ioctl(file, EVIOCGABS + axis as libc::c_ulong, &mut info as *mut _)?;
+    Ok(info)
+}
+
+/// the keys held and the touches of `file` now
+pub fn query_state(file: &File) -> io::Result<DeviceState> {
+    let mut bits = [0u8; KEY_MAX / 8 + 1];
+    ioctl(file, ioc_read(EVIOCGKEY, bits.len()), bits.as_mut_ptr())?;
+    let mut state = DeviceState {
+        keys: pressed_keys(&bits),
+        slot: 0,
+        slots: Vec::new(),
+    };
+    // fails without multi-touch slots
+    if let Ok((slot, slots)) = query_slots(file) {
+        state.slot = slot;
+        state.slots = slots;
+    }
+    Ok(state)
+}
+
+/// the selected slot and every slot of a multi-touch device
+fn query_slots(file: &File) -> io::Result<(usize, Vec<SlotState>)> {
+    let info = query_abs(file, ABS_MT_SLOT)?;
+    let count = info.maximum.max(0) as usize + 1;
+    let ids = query_mt_slots(file, ABS_MT_TRACKING_ID, count)?;
+    let x = query_mt_slots(file, ABS_MT_POSITION_X, count)?;
+    let y = query_mt_slots(file, ABS_MT_POSITION_Y, count)?;
+    let slots = (0..count)
+        .map(|index| SlotState {
+            id: if ids[index] >= 0 { Some(ids[index] as u32) } else { None },
+            position: [x[index], y[index]],
+        })
+        .collect();
+    Ok((info.value.max(0) as usize, slots))
+}
+
+/// the codes set in the bitmap of `EVIOCGKEY`
+pub fn pressed_keys(bits: &[u8]) -> Vec<u16> {
+    (0..bits.len() * 8)
+        .filter(|&code| bits[code / 8] & (1 << (code % 8)) != 0)
+        .map(|code| code as u16)
+        .collect()
+}
+
+/// the value of `code` in each of the `count` slots
+fn query_mt_slots(file: &File, code: u16, count: usize) -> io::Result<Vec<i32>> {
+    // `struct input_mt_request_layout`: the code, then the values
+    let mut request = vec![0i32; count + 1];
+    request[0] = i32::from(code);
+    let len = request.len() * mem::size_of::<i32>();
+    ioctl(file, ioc_read(EVIOCGMTSLOTS, len), request.as_mut_ptr())?;
+    request.remove(0);
+    Ok(request)
+}
+
+/// `_IOC(_IOC_READ, 'E', nr, len)`
+fn ioc_read(nr: libc::c_ulong, len: usize) -> libc::c_ulong {
+    (2 << 30) | ((len as libc::c_ulong) << 16) | (0x45 << 8) | nr
+}
+
+fn ioctl<T>(file: &File, request: libc::c_ulong, arg: *mut T) -> io::Result<()> {
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg) };
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// `KEY_*` codes of `linux/input-event-codes.h`
+fn linux_key(code: u16) -> Option<Key> {
+    let key = match code {
+        1 => Key::Escape,
+        2 => Key::Key1,
+        3 => Key::Key2,
+        4 => Key::Key3,
+        5 => Key::Key4,
+        6 => Key::Key5,
+        7 => Key::Key6,
+        8 => Key::Key7,
+        9 => Key::Key8,
+        10 => Key::Key9,
+        11 => Key::Key0,
+        12 => Key::Minus,
+        13 => Key::Equals,
+        14 => Key::Backspace,
+        15 => Key::Tab,
+        16 => Key::Q,
+        17 => Key::W,
+        18 => Key::E,
+        19 => Key::R,
+        20 => Key::T,
+        21 => Key::Y,
+        22 => Key::U,
+        23 => Key::I,
+        24 => Key::O,
+        25 => Key::P,
+        28 => Key::Enter,
+        29 => Key::LControl,
+        30 => Key::A,
+        31 => Key::S,
+        32 => Key::D,
+        33 => Key::F,
+        34 => Key::G,
+        35 => Key::H,
+        36 => Key::J,
+        37 => Key::K,
+        38 => Key::L,
+        42 => Key::LShift,
+        44 => Key::Z,
+        45 => Key::X,
+        46 => Key::C,
+        47 => Key::V,
+        48 => Key::B,
+        49 => Key::N,
+        50 => Key::M,
+        51 => Key::Comma,
+        52 => Key::Period,
+        53 => Key::Slash,
+        54 => Key::RShift,
+        56 => Key::LAlt,
+        57 => Key::Space,
+        59 => Key::F1,
+        60 => Key::F2,
+        61 => Key::F3,
+        62 => Key::F4,
+        63 => Key::F5,
+        64 => Key::F6,
+        65 => Key::F7,
+        66 => Key::F8,
+        67 => Key::F9,
+        68 => Key::F10,
+        87 => Key::F11,
+        88 => Key::F12,
+        96 => Key::Enter,
+        97 => Key::RControl,
+        100 => Key::RAlt,
+        102 => Key::Home,
+        103 => Key::Up,
+        104 => Key::PageUp,
+        105 => Key::Left,
+        106 => Key::Right,
+        107 => Key::End,
+        108 => Key::Down,
+        109 => Key::PageDown,
+        110 => Key::Insert,
+        111 => Key::Delete,
+        _ => return None,
+    };
+    Some(key)
+}
@@ -0,0 +1,267 @@
+//! Keyboard, mouse and touch input, independent of the backend
+//!
+//! Backends translate what their window system delivers into
+//! `InputEvent`s, the render loop feeds them to an `InputState` and
+//! queries it once per frame:
+//!
+//! ```ignore
+//! for event in backend.pump_events() {
+//!     if let BackendEvent::Input(event) = event {
+//!         input.handle(&event);
+//!     }
+//! }
+//! if input.was_pressed(Key::Space) { ... }
+//! input.end_frame();
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use config::KeybindingsConfig;
+
+#[cfg(target_os = "linux")]
+pub mod evdev;
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests;
+
+macro_rules! keys {
+    ($($key:ident),*) => {
+        /// named after winit's `VirtualKeyCode`, which is also how they
+        /// are spelled in the configuration, but for `Enter` and
+        /// `Backspace`, winit's `Return` and `Back`
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub enum Key {
+            $($key,)*
+        }
+
+        impl Key {
+            /// also takes winit's `Return` and `Back`
+            pub fn from_name(name: &str) -> Option<Key> {
+                match name {
+                    $(stringify!($key) => Some(Key::$key),)*
+                    "Return" => Some(Key::Enter),
+                    "Back" => Some(Key::Backspace),
+                    _ => None,
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $(Key::$key => stringify!($key),)*
+                }
+            }
+        }
+    };
+}
+
+keys!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Escape, Enter, Space, Tab, Backspace, Insert, Delete, Home, End, PageUp, PageDown,
+    Left, Right, Up, Down,
+    LShift, RShift, LControl, RControl, LAlt, RAlt,
+    Minus, Equals, Comma, Period, Slash
+);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u8),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    Key { key: Key, pressed: bool },
+    MouseButton { button: MouseButton, pressed: bool },
+    /// in pixels, from the top left corner of the content
+    CursorMoved([f32; 2]),
+    /// in lines, positive y scrolls up
+    Scroll([f32; 2]),
+    /// `id` stays the same from `Started` to `Ended`, position in pixels
+    /// like the cursor
+    Touch {
+        id: u32,
+        phase: TouchPhase,
+        position: [f32; 2],
+    },
+}
+
+/// what the configurable keybindings trigger
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Screenshot,
+    ToggleFullscreen,
+    ToggleStats,
+    /// re-reads the colour correction LUT
+    ReloadCorrection,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keybindings {
+    bindings: Vec<(Key, Action)>,
+}
+
+/// current state of the input devices, plus what changed since the last
+/// `end_frame`
+#[derive(Debug, Default)]
+pub struct InputState {
+    keys: HashSet<Key>,
+    buttons: HashSet<MouseButton>,
+    cursor: Option<[f32; 2]>,
+    pressed_keys: HashSet<Key>,
+    pressed_buttons: HashSet<MouseButton>,
+    scroll: [f32; 2],
+    touches: HashMap<u32, [f32; 2]>,
+}
+
+impl InputEvent {
+    /// applies `map` to the positions carried by the event, e.g.
+    /// `Renderer::map_input`
+    pub fn map_position<F: Fn([f32; 2]) -> [f32; 2]>(self, map: F) -> InputEvent {
+        match self {
+            InputEvent::CursorMoved(position) => InputEvent::CursorMoved(map(position)),
+            InputEvent::Touch {
+                id,
+                phase,
+                position,
+            } => InputEvent::Touch {
+                id: id,
+                phase: phase,
+                position: map(position),
+            },
+            event => event,
+        }
+    }
+}
+
+impl Keybindings {
+    /// `config` must have been validated
+    pub fn from_config(config: &KeybindingsConfig) -> Keybindings {
+        let bind = |name: &str, action| {
+            let key = Key::from_name(name)
+                .unwrap_or_else(|| panic!("`keybindings`: unknown key '{}'", name));
+            (key, action)
+        };
+        Keybindings {
+            bindings: vec![
+                bind(&config.quit, Action::Quit),
+                bind(&config.screenshot, Action::Screenshot),
+                bind(&config.fullscreen, Action::ToggleFullscreen),
+                bind(&config.stats, Action::ToggleStats),
+                bind(&config.reload, Action::ReloadCorrection),
+            ],
+        }
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == key)
+            .map(|&(_, action)| action)
+    }
+
+    /// the action triggered by `event`, on key press only
+    pub fn triggered(&self, event: &InputEvent) -> Option<Action> {
+        match *event {
+            InputEvent::Key { key, pressed: true } => self.action(key),
+            _ => None,
+        }
+    }
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState::default()
+    }
+
+    pub fn handle(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Key { key, pressed } => {
+                if pressed {
+                    if self.keys.insert(key) {
+                        self.pressed_keys.insert(key);
+                    }
+                } else {
+                    self.keys.remove(&key);
+                }
+            }
+            InputEvent::MouseButton { button, pressed } => {
+                if pressed {
+                    if self.buttons.insert(button) {
+                        self.pressed_buttons.insert(button);
+                    }
+                } else {
+                    self.buttons.remove(&button);
+                }
+            }
+            InputEvent::CursorMoved(position) => self.cursor = Some(position),
+            InputEvent::Scroll(delta) => {
+                self.scroll[0] += delta[0];
+                self.scroll[1] += delta[1];
+            }
+            InputEvent::Touch {
+                id,
+                phase,
+                position,
+            } => match phase {
+                TouchPhase::Started | TouchPhase::Moved => {
+                    self.touches.insert(id, position);
+                }
+                TouchPhase::Ended => {
+                    self.touches.remove(&id);
+                }
+            },
+        }
+    }
+
+    /// forgets what happened during the frame, call once per frame after
+    /// the queries
+    pub fn end_frame(&mut self) {
+        self.pressed_keys.clear();
+        self.pressed_buttons.clear();
+        self.scroll = [0.0, 0.0];
+    }
+
+    pub fn is_down(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    /// went down during this frame, key repeats don't count
+    pub fn was_pressed(&self, key: Key) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    /// `None` until the cursor moved over the content once
+    pub fn cursor(&self) -> Option<[f32; 2]> {
+        self.cursor
+    }
+
+    /// scrolled during this frame
+    pub fn scroll(&self) -> [f32; 2] {
+        self.scroll
+    }
+
+    /// positions of the fingers currently down, by touch id
+    pub fn touches(&self) -> &HashMap<u32, [f32; 2]> {
+        &self.touches
+    }
+}
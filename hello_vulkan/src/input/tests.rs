@@ -0,0 +1,325 @@
+use super::evdev::*;
+use super::{InputEvent, InputState, Key, MouseButton, TouchPhase};
+
+use std::io::{self, Cursor, Read};
+
+fn stream(events: &[(u16, u16, i32)]) -> Vec<u8> {
+    events
+        .iter()
+        .flat_map(|&(kind, code, value)| {
+            RawEvent {
+                kind: kind,
+                code: code,
+                value: value,
+            }.to_bytes()
+        })
+        .collect()
+}
+
+fn decode(events: &[(u16, u16, i32)], screen: [u32; 2]) -> Vec<InputEvent> {
+    let mut reader = EventReader::new(Cursor::new(stream(events)));
+    reader.decoder_mut().set_screen(screen);
+    reader.decoder_mut().set_touch_range((0, 4095), (0, 4095));
+    let mut out = Vec::new();
+    assert_eq!(reader.read_events(&mut out).unwrap(), false);
+    out
+}
+
+/// hands out one byte per read, with a `WouldBlock` in between
+struct Trickle {
+    bytes: Vec<u8>,
+    position: usize,
+    block: bool,
+}
+
+impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.block = !self.block;
+        if self.block {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "no data"));
+        }
+        if self.position == self.bytes.len() {
+            return Ok(0);
+        }
+        buf[0] = self.bytes[self.position];
+        self.position += 1;
+        Ok(1)
+    }
+}
+
+#[test]
+fn raw_event_round_trip() {
+    let event = RawEvent {
+        kind: EV_ABS,
+        code: ABS_MT_POSITION_X,
+        value: -1234,
+    };
+    let bytes = event.to_bytes();
+    assert_eq!(bytes.len(), event_size());
+    assert_eq!(RawEvent::parse(&bytes), event);
+}
+
+#[test]
+fn keys_and_buttons() {
+    let events = decode(
+        &[
+            (EV_KEY, 1, 1),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_KEY, 1, 2),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_KEY, 1, 0),
+            (EV_KEY, BTN_RIGHT, 1),
+            (EV_SYN, SYN_REPORT, 0),
+        ],
+        [800, 480],
+    );
+    assert_eq!(
+        events,
+        vec![
+            InputEvent::Key {
+                key: Key::Escape,
+                pressed: true,
+            },
+            InputEvent::Key {
+                key: Key::Escape,
+                pressed: false,
+            },
+            InputEvent::MouseButton {
+                button: MouseButton::Right,
+                pressed: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn events_wait_for_the_report() {
+    let events = decode(&[(EV_KEY, 57, 1)], [800, 480]);
+    assert!(events.is_empty());
+}
+
+#[test]
+fn relative_pointer_is_clamped() {
+    let events = decode(
+        &[
+            (EV_REL, REL_X, 10),
+            (EV_REL, REL_Y, -5),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_REL, REL_X, -1000),
+            (EV_REL, REL_WHEEL, -1),
+            (EV_SYN, SYN_REPORT, 0),
+        ],
+        [800, 480],
+    );
+    assert_eq!(
+        events,
+        vec![
+            InputEvent::CursorMoved([410.0, 235.0]),
+            InputEvent::CursorMoved([0.0, 235.0]),
+            InputEvent::Scroll([0.0, -1.0]),
+        ]
+    );
+}
+
+#[test]
+fn multi_touch_protocol_b() {
+    let events = decode(
+        &[
+            (EV_ABS, ABS_MT_SLOT, 0),
+            (EV_ABS, ABS_MT_TRACKING_ID, 7),
+            (EV_ABS, ABS_MT_POSITION_X, 0),
+            (EV_ABS, ABS_MT_POSITION_Y, 4095),
+            (EV_ABS, ABS_MT_SLOT, 1),
+            (EV_ABS, ABS_MT_TRACKING_ID, 8),
+            (EV_ABS, ABS_MT_POSITION_X, 4095),
+            (EV_ABS, ABS_MT_POSITION_Y, 0),
+            (EV_SYN, SYN_REPORT, 0),
+            // slot 1 is still selected
+            (EV_ABS, ABS_MT_POSITION_X, 0),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_ABS, ABS_MT_SLOT, 0),
+            (EV_ABS, ABS_MT_TRACKING_ID, -1),
+            (EV_SYN, SYN_REPORT, 0),
+        ],
+        [800, 480],
+    );
+    let touch = |id, phase, position| InputEvent::Touch {
+        id: id,
+        phase: phase,
+        position: position,
+    };
+    assert_eq!(
+        events,
+        vec![
+            touch(7, TouchPhase::Started, [0.0, 480.0]),
+            touch(8, TouchPhase::Started, [800.0, 0.0]),
+            touch(8, TouchPhase::Moved, [0.0, 0.0]),
+            touch(7, TouchPhase::Ended, [0.0, 480.0]),
+        ]
+    );
+
+    let mut state = InputState::new();
+    for event in &events {
+        state.handle(event);
+    }
+    assert_eq!(state.touches().len(), 1);
+    assert_eq!(state.touches()[&8], [0.0, 0.0]);
+}
+
+#[test]
+fn dropped_events_end_the_touches() {
+    let events = decode(
+        &[
+            (EV_ABS, ABS_MT_TRACKING_ID, 3),
+            (EV_ABS, ABS_MT_POSITION_X, 0),
+            (EV_ABS, ABS_MT_POSITION_Y, 0),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_SYN, SYN_DROPPED, 0),
+            (EV_KEY, 1, 1),
+            (EV_SYN, SYN_REPORT, 0),
+        ],
+        [800, 480],
+    );
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[1],
+        InputEvent::Touch {
+            id: 3,
+            phase: TouchPhase::Ended,
+            position: [0.0, 0.0],
+        }
+    );
+}
+
+/// a stream recorded with the state of its device after the drop
+struct Recorded {
+    bytes: Cursor<Vec<u8>>,
+    state: DeviceState,
+}
+
+impl Read for Recorded {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.bytes.read(buf)
+    }
+}
+
+fn recorded_state(recorded: &Recorded) -> io::Result<DeviceState> {
+    Ok(recorded.state.clone())
+}
+
+#[test]
+fn dropped_events_resync_to_the_device() {
+    let bytes = stream(&[
+        (EV_KEY, 30, 1),
+        (EV_ABS, ABS_MT_TRACKING_ID, 3),
+        (EV_ABS, ABS_MT_POSITION_X, 0),
+        (EV_ABS, ABS_MT_POSITION_Y, 0),
+        (EV_SYN, SYN_REPORT, 0),
+        (EV_SYN, SYN_DROPPED, 0),
+        (EV_KEY, 30, 0),
+        (EV_SYN, SYN_REPORT, 0),
+        // the device had slot 1 selected
+        (EV_ABS, ABS_MT_POSITION_X, 4095),
+        (EV_SYN, SYN_REPORT, 0),
+    ]);
+    let state = DeviceState {
+        keys: vec![1],
+        slot: 1,
+        slots: vec![
+            SlotState {
+                id: Some(3),
+                position: [4095, 0],
+            },
+            SlotState {
+                id: Some(9),
+                position: [0, 4095],
+            },
+        ],
+    };
+    let recorded = Recorded {
+        bytes: Cursor::new(bytes),
+        state: state,
+    };
+    let mut reader = EventReader::with_query(recorded, recorded_state);
+    reader.decoder_mut().set_screen([800, 480]);
+    reader.decoder_mut().set_touch_range((0, 4095), (0, 4095));
+    let mut events = Vec::new();
+    assert_eq!(reader.read_events(&mut events).unwrap(), false);
+
+    let key = |key, pressed| InputEvent::Key {
+        key: key,
+        pressed: pressed,
+    };
+    let touch = |id, phase, position| InputEvent::Touch {
+        id: id,
+        phase: phase,
+        position: position,
+    };
+    assert_eq!(
+        events,
+        vec![
+            key(Key::A, true),
+            touch(3, TouchPhase::Started, [0.0, 0.0]),
+            key(Key::A, false),
+            key(Key::Escape, true),
+            touch(3, TouchPhase::Moved, [800.0, 0.0]),
+            touch(9, TouchPhase::Started, [0.0, 480.0]),
+            touch(9, TouchPhase::Moved, [800.0, 480.0]),
+        ]
+    );
+}
+
+#[test]
+fn key_bitmap() {
+    assert_eq!(pressed_keys(&[0b10, 0, 0, 0b1000_0000]), vec![1, 31]);
+    assert!(pressed_keys(&[0; 96]).is_empty());
+}
+
+#[test]
+fn partial_reads() {
+    let mut reader = EventReader::new(Trickle {
+        bytes: stream(&[(EV_KEY, 30, 1), (EV_SYN, SYN_REPORT, 0)]),
+        position: 0,
+        block: false,
+    });
+    let mut out = Vec::new();
+    while reader.read_events(&mut out).unwrap() {}
+    assert_eq!(
+        out,
+        vec![InputEvent::Key {
+            key: Key::A,
+            pressed: true,
+        }]
+    );
+}
+
+#[test]
+fn input_state_tracks_transitions() {
+    let mut state = InputState::new();
+    let press = InputEvent::Key {
+        key: Key::Space,
+        pressed: true,
+    };
+    state.handle(&press);
+    assert!(state.is_down(Key::Space) && state.was_pressed(Key::Space));
+
+    state.end_frame();
+    state.handle(&press);
+    assert!(state.is_down(Key::Space) && !state.was_pressed(Key::Space));
+
+    state.handle(&InputEvent::Key {
+        key: Key::Space,
+        pressed: false,
+    });
+    assert!(!state.is_down(Key::Space));
+}
+
+#[test]
+fn key_names_take_both_spellings() {
+    assert_eq!(Key::from_name("Enter"), Some(Key::Enter));
+    assert_eq!(Key::from_name("Return"), Some(Key::Enter));
+    assert_eq!(Key::from_name("Back"), Some(Key::Backspace));
+    assert_eq!(Key::Backspace.name(), "Backspace");
+    assert_eq!(Key::from_name("Tab"), Some(Key::Tab));
+    assert_eq!(Key::from_name("Enter "), None);
+}
@@ -2,14 +2,40 @@
 extern crate vulkano;
 
 extern crate image;
+extern crate libc;
+
+#[macro_use]
+extern crate log;
+
+extern crate rusttype;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+extern crate vk_sys;
 
 #[cfg(feature = "win")]
 extern crate vulkano_win;
 #[cfg(feature = "win")]
 extern crate winit;
+#[cfg(all(feature = "win", target_os = "macos"))]
+extern crate cocoa;
+#[cfg(all(feature = "win", target_os = "macos"))]
+extern crate metal_rs;
+#[cfg(all(feature = "win", target_os = "macos"))]
+extern crate objc;
 
 #[macro_use]
 extern crate vulkano_shader_derive;
 
 
+pub mod config;
+pub mod filter;
+pub mod input;
+#[cfg(target_os = "linux")]
+pub mod linuxfb;
+pub mod log_targets;
+pub mod math;
 pub mod run;
+pub mod software;
@@ -0,0 +1,331 @@
+//! Linux framebuffer device (`/dev/fb*`) mapped into memory
+//!
+//! Used to show something on the panel when there is no usable Vulkan
+//! driver. The geometry and pixel layout come from the fbdev ioctls, or
+//! are given explicitly with `with_geometry` so any file can stand in for
+//! the device.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use libc;
+
+#[cfg(test)]
+mod tests;
+
+const FBIOGET_VSCREENINFO: libc::c_ulong = 0x4600;
+const FBIOGET_FSCREENINFO: libc::c_ulong = 0x4602;
+
+/// position of one channel inside a pixel, in bits
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Bitfield {
+    pub offset: u32,
+    pub length: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Geometry {
+    /// visible resolution
+    pub width: u32,
+    pub height: u32,
+    /// first visible pixel, the panning offset of the device
+    pub xoffset: u32,
+    pub yoffset: u32,
+    pub bits_per_pixel: u32,
+    /// bytes per row, including padding
+    pub line_length: u32,
+    pub red: Bitfield,
+    pub green: Bitfield,
+    pub blue: Bitfield,
+    pub transp: Bitfield,
+}
+
+pub struct LinuxFramebuffer {
+    _file: File,
+    geometry: Geometry,
+    map: *mut u8,
+    map_len: usize,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct FbBitfield {
+    offset: u32,
+    length: u32,
+    msb_right: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct FbVarScreeninfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    pixclock: u32,
+    left_margin: u32,
+    right_margin: u32,
+    upper_margin: u32,
+    lower_margin: u32,
+    hsync_len: u32,
+    vsync_len: u32,
+    sync: u32,
+    vmode: u32,
+    rotate: u32,
+    colorspace: u32,
+    reserved: [u32; 4],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct FbFixScreeninfo {
+    id: [u8; 16],
+    smem_start: libc::c_ulong,
+    smem_len: u32,
+    type_: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: libc::c_ulong,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+impl Geometry {
+    /// packed 32 bit layout, e.g. `B8G8R8A8` in memory
+    pub fn xrgb8888(width: u32, height: u32) -> Geometry {
+        Geometry {
+            width: width,
+            height: height,
+            xoffset: 0,
+            yoffset: 0,
+            bits_per_pixel: 32,
+            line_length: width * 4,
+            red: Bitfield { offset: 16, length: 8 },
+            green: Bitfield { offset: 8, length: 8 },
+            blue: Bitfield { offset: 0, length: 8 },
+            transp: Bitfield { offset: 24, length: 8 },
+        }
+    }
+
+    /// packed 16 bit layout
+    pub fn rgb565(width: u32, height: u32) -> Geometry {
+        Geometry {
+            width: width,
+            height: height,
+            xoffset: 0,
+            yoffset: 0,
+            bits_per_pixel: 16,
+            line_length: width * 2,
+            red: Bitfield { offset: 11, length: 5 },
+            green: Bitfield { offset: 5, length: 6 },
+            blue: Bitfield { offset: 0, length: 5 },
+            transp: Bitfield::default(),
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        ((self.bits_per_pixel + 7) / 8) as usize
+    }
+
+    /// bytes needed to hold the visible area, panning included
+    pub fn required_len(&self) -> usize {
+        (self.yoffset + self.height) as usize * self.line_length as usize
+    }
+
+    /// packs an 8 bit per channel colour into the device layout
+    pub fn pack(&self, r: u8, g: u8, b: u8, a: u8) -> u32 {
+        channel(r, self.red) | channel(g, self.green) | channel(b, self.blue)
+            | channel(a, self.transp)
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        let bpp = self.bits_per_pixel;
+        if bpp != 16 && bpp != 24 && bpp != 32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported framebuffer depth {} bpp", bpp),
+            ));
+        }
+        if (self.line_length as usize) < (self.xoffset + self.width) as usize * self.bytes_per_pixel() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "framebuffer line length is shorter than a row",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn channel(value: u8, field: Bitfield) -> u32 {
+    if field.length == 0 {
+        return 0;
+    }
+    let value = if field.length >= 8 {
+        (value as u32) << (field.length - 8)
+    } else {
+        (value as u32) >> (8 - field.length)
+    };
+    value << field.offset
+}
+
+impl LinuxFramebuffer {
+    /// opens a framebuffer device and queries its geometry
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<LinuxFramebuffer> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let geometry = query_geometry(&file)?;
+        LinuxFramebuffer::map(file, geometry)
+    }
+
+    /// opens any file with a known geometry, regular files are grown to
+    /// the needed size
+    pub fn with_geometry<P: AsRef<Path>>(path: P, geometry: Geometry) -> io::Result<LinuxFramebuffer> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
+        let metadata = file.metadata()?;
+        if metadata.is_file() && (metadata.len() as usize) < geometry.required_len() {
+            file.set_len(geometry.required_len() as u64)?;
+        }
+        LinuxFramebuffer::map(file, geometry)
+    }
+
+    fn map(file: File, geometry: Geometry) -> io::Result<LinuxFramebuffer> {
+        geometry.validate()?;
+        let map_len = geometry.required_len();
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(LinuxFramebuffer {
+            _file: file,
+            geometry: geometry,
+            map: map as *mut u8,
+            map_len: map_len,
+        })
+    }
+
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        [self.geometry.width, self.geometry.height]
+    }
+
+    /// copies a tightly packed RGBA8 image to the top left corner, the
+    /// part which doesn't fit is clipped
+    pub fn blit_rgba(&mut self, pixels: &[u8], width: u32, height: u32) {
+        assert!(pixels.len() >= (width * height * 4) as usize, "image is smaller than its size");
+
+        let geometry = self.geometry;
+        let bpp = geometry.bytes_per_pixel();
+        let columns = width.min(geometry.width) as usize;
+        let rows = height.min(geometry.height) as usize;
+        let memory = unsafe { slice::from_raw_parts_mut(self.map, self.map_len) };
+
+        for y in 0..rows {
+            let src_row = &pixels[y * width as usize * 4..];
+            let dst_start = (geometry.yoffset as usize + y) * geometry.line_length as usize
+                + geometry.xoffset as usize * bpp;
+            let dst_row = &mut memory[dst_start..dst_start + columns * bpp];
+
+            for x in 0..columns {
+                let p = &src_row[x * 4..x * 4 + 4];
+                let packed = geometry.pack(p[0], p[1], p[2], p[3]);
+                for i in 0..bpp {
+                    dst_row[x * bpp + i] = (packed >> (8 * i)) as u8;
+                }
+            }
+        }
+    }
+
+    /// fills the visible area with one colour
+    pub fn clear(&mut self, color: [u8; 4]) {
+        let (width, height) = (self.geometry.width, self.geometry.height);
+        let row: Vec<u8> = (0..width).flat_map(|_| color.iter().cloned()).collect();
+        let pixels: Vec<u8> = (0..height).flat_map(|_| row.iter().cloned()).collect();
+        self.blit_rgba(&pixels, width, height);
+    }
+}
+
+impl Drop for LinuxFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.map as *mut libc::c_void, self.map_len);
+        }
+    }
+}
+
+fn query_geometry(file: &File) -> io::Result<Geometry> {
+    let mut var: FbVarScreeninfo = Default::default();
+    let mut fix: FbFixScreeninfo = Default::default();
+
+    unsafe {
+        if libc::ioctl(file.as_raw_fd(), FBIOGET_VSCREENINFO as _, &mut var as *mut _) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::ioctl(file.as_raw_fd(), FBIOGET_FSCREENINFO as _, &mut fix as *mut _) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(Geometry {
+        width: var.xres,
+        height: var.yres,
+        xoffset: var.xoffset,
+        yoffset: var.yoffset,
+        bits_per_pixel: var.bits_per_pixel,
+        line_length: fix.line_length,
+        red: Bitfield {
+            offset: var.red.offset,
+            length: var.red.length,
+        },
+        green: Bitfield {
+            offset: var.green.offset,
+            length: var.green.length,
+        },
+        blue: Bitfield {
+            offset: var.blue.offset,
+            length: var.blue.length,
+        },
+        transp: Bitfield {
+            offset: var.transp.offset,
+            length: var.transp.length,
+        },
+    })
+}
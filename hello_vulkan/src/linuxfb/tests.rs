@@ -0,0 +1,68 @@
+use super::{Geometry, LinuxFramebuffer};
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process;
+
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("slicer-fb-{}-{}", process::id(), name))
+}
+
+fn read_back(path: &PathBuf) -> Vec<u8> {
+    let mut contents = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut contents).unwrap();
+    fs::remove_file(path).unwrap();
+    contents
+}
+
+#[test]
+fn blit_xrgb8888() {
+    let path = temp_path("xrgb8888");
+    {
+        let mut fb = LinuxFramebuffer::with_geometry(&path, Geometry::xrgb8888(2, 1)).unwrap();
+        fb.blit_rgba(&[255, 0, 0, 255, 1, 2, 3, 4], 2, 1);
+    }
+    assert_eq!(read_back(&path), vec![0, 0, 255, 255, 3, 2, 1, 4]);
+}
+
+#[test]
+fn blit_rgb565() {
+    let path = temp_path("rgb565");
+    {
+        let mut fb = LinuxFramebuffer::with_geometry(&path, Geometry::rgb565(3, 1)).unwrap();
+        fb.blit_rgba(&[255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255], 3, 1);
+    }
+    assert_eq!(read_back(&path), vec![0x00, 0xf8, 0xe0, 0x07, 0x1f, 0x00]);
+}
+
+#[test]
+fn blit_clips_and_honours_padding_and_panning() {
+    let path = temp_path("padding");
+    let geometry = Geometry {
+        line_length: 12,
+        yoffset: 1,
+        ..Geometry::xrgb8888(2, 1)
+    };
+    {
+        let mut fb = LinuxFramebuffer::with_geometry(&path, geometry).unwrap();
+        // 3x2 image on a 2x1 visible area
+        let pixels: Vec<u8> = (0..6).flat_map(|i| vec![i, i, i, 255]).collect();
+        fb.blit_rgba(&pixels, 3, 2);
+    }
+    let contents = read_back(&path);
+    assert_eq!(contents.len(), 24);
+    // the first line is panned away and must stay untouched
+    assert_eq!(&contents[0..12], &[0; 12]);
+    assert_eq!(&contents[12..20], &[0, 0, 0, 255, 1, 1, 1, 255]);
+    assert_eq!(&contents[20..24], &[0; 4]);
+}
+
+#[test]
+fn open_regular_file_fails() {
+    let path = temp_path("not-a-device");
+    File::create(&path).unwrap();
+    assert!(LinuxFramebuffer::open(&path).is_err());
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,11 @@
+//! Log targets used by slicer, so the binary can route or filter them
+//! separately, e.g. `SLICER_LOG=info,slicer::frame_stats=debug`
+
+/// device, display and surface dumps
+pub const INFO: &str = "slicer::info";
+
+/// messages coming from the Vulkan debug report callback
+pub const DEBUG_REPORT: &str = "slicer::debug_report";
+
+/// per frame timings
+pub const FRAME_STATS: &str = "slicer::frame_stats";
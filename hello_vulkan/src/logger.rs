@@ -0,0 +1,188 @@
+//! Logger of the slicer binary
+//!
+//! `SLICER_LOG` selects the levels, either a single level (`debug`) or a
+//! default followed by per-target overrides
+//! (`info,slicer::frame_stats=debug,slicer::debug_report=warn`).
+//!
+//! `SLICER_LOG_OUTPUT` selects where records go: `stderr` (default),
+//! `syslog`, or a file path to append to.
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::process;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use log::{self, Level, LevelFilter, Log, Metadata, Record};
+
+pub const LEVEL_ENV: &str = "SLICER_LOG";
+pub const OUTPUT_ENV: &str = "SLICER_LOG_OUTPUT";
+
+const SYSLOG_SOCKET: &str = "/dev/log";
+/// LOG_USER
+const SYSLOG_FACILITY: u8 = 1;
+
+enum Output {
+    Stderr,
+    File(Mutex<File>),
+    Syslog(UnixDatagram),
+}
+
+struct Logger {
+    default_level: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+    output: Output,
+}
+
+impl Logger {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        // the longest matching prefix wins
+        self.targets
+            .iter()
+            .filter(|&&(ref name, _)| target.starts_with(name.as_str()))
+            .max_by_key(|&&(ref name, _)| name.len())
+            .map(|&(_, level)| level)
+            .unwrap_or(self.default_level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|&(_, level)| level)
+            .fold(self.default_level, |a, b| if b > a { b } else { a })
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // nothing sensible can be done when logging itself fails
+        let _ = match self.output {
+            Output::Stderr => {
+                let stderr = io::stderr();
+                let mut handle = stderr.lock();
+                writeln!(
+                    handle,
+                    "{:5} {}: {}",
+                    record.level(),
+                    record.target(),
+                    record.args()
+                )
+            }
+            Output::File(ref file) => match file.lock() {
+                Ok(mut file) => writeln!(
+                    file,
+                    "{:5} {}: {}",
+                    record.level(),
+                    record.target(),
+                    record.args()
+                ),
+                Err(_) => Ok(()),
+            },
+            Output::Syslog(ref socket) => {
+                let message = format!(
+                    "<{}>slicer[{}]: {}: {}",
+                    SYSLOG_FACILITY * 8 + syslog_severity(record.level()),
+                    process::id(),
+                    record.target(),
+                    record.args()
+                );
+                socket.send(message.as_bytes()).map(|_| ())
+            }
+        };
+    }
+
+    fn flush(&self) {
+        match self.output {
+            Output::Stderr => {
+                let _ = io::stderr().flush();
+            }
+            Output::File(ref file) => {
+                if let Ok(mut file) = file.lock() {
+                    let _ = file.flush();
+                }
+            }
+            Output::Syslog(_) => {}
+        }
+    }
+}
+
+fn syslog_severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+fn parse_levels(spec: &str) -> (LevelFilter, Vec<(String, LevelFilter)>) {
+    let mut default_level = LevelFilter::Info;
+    let mut targets = Vec::new();
+
+    for directive in spec.split(',').map(|d| d.trim()).filter(|d| !d.is_empty()) {
+        let mut parts = directive.splitn(2, '=');
+        let first = parts.next().unwrap_or("");
+        match parts.next() {
+            Some(level) => match LevelFilter::from_str(level.trim()) {
+                Ok(level) => targets.push((first.trim().to_owned(), level)),
+                Err(_) => eprintln!("{}: ignoring invalid level in '{}'", LEVEL_ENV, directive),
+            },
+            None => match LevelFilter::from_str(first) {
+                Ok(level) => default_level = level,
+                Err(_) => eprintln!("{}: ignoring invalid level '{}'", LEVEL_ENV, directive),
+            },
+        }
+    }
+
+    (default_level, targets)
+}
+
+fn open_output(spec: &str) -> io::Result<Output> {
+    match spec {
+        "" | "stderr" => Ok(Output::Stderr),
+        "syslog" => {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(SYSLOG_SOCKET)?;
+            Ok(Output::Syslog(socket))
+        }
+        path => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Ok(Output::File(Mutex::new(file)))
+        }
+    }
+}
+
+/// installs the logger, falls back to stderr if the output can't be opened
+pub fn init() {
+    let (default_level, targets) = parse_levels(&env::var(LEVEL_ENV).unwrap_or_default());
+
+    let output_spec = env::var(OUTPUT_ENV).unwrap_or_default();
+    let output = open_output(&output_spec).unwrap_or_else(|err| {
+        eprintln!(
+            "{}: failed to open '{}' ({}), logging to stderr",
+            OUTPUT_ENV, output_spec, err
+        );
+        Output::Stderr
+    });
+
+    let logger = Logger {
+        default_level: default_level,
+        targets: targets,
+        output: output,
+    };
+
+    log::set_max_level(logger.max_level());
+    if log::set_boxed_logger(Box::new(logger)).is_err() {
+        eprintln!("logger is already initialized");
+    }
+}
@@ -1,8 +1,579 @@
+extern crate image;
 extern crate slicer;
+extern crate vulkano;
+
+#[macro_use]
+extern crate log;
+
+mod cli;
+mod logger;
+
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::Instant;
+#[cfg(target_os = "linux")]
+use std::thread;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+use vulkano::buffer::{BufferAccess, BufferSlice};
+use vulkano::command_buffer::DrawIndirectCommand;
+
+use slicer::config::{Config, PresentModeConfig};
+use slicer::filter;
+use slicer::input::{Action, InputEvent, InputState, Key, Keybindings, MouseButton, TouchPhase};
+#[cfg(target_os = "linux")]
+use slicer::linuxfb::LinuxFramebuffer;
+use slicer::run::backend::{self, BackendEvent, SurfaceBackend};
+use slicer::run::canvas::{Batch, Canvas2d};
+use slicer::run::compute::{FilterChain, GaussianBlur, Kernel, KernelLayout};
+use slicer::run::{create_vk_struct, VulkanStruct};
+use slicer::run::headless::HeadlessBackend;
+#[cfg(target_os = "linux")]
+use slicer::run::orientation::Orientation;
+use slicer::run::patterns::{Pattern, PatternRenderer};
+use slicer::run::refresh::RefreshMeter;
+use slicer::run::renderer::{RenderError, Renderer};
+use slicer::run::scene::{Mesh, Scene, SceneRecorder};
+use slicer::run::shaders::{Instance, Vertex};
+use slicer::run::target::PresentTarget;
+use slicer::run::text::{Font, StatsOverlay, TextRenderer, TextStyle};
+use slicer::run::watchdog::Recovery;
+#[cfg(target_os = "linux")]
+use slicer::software::Canvas;
+
+fn triangle() -> Vec<Vertex> {
+    vec![
+        Vertex {
+            position: [-0.5, -0.5],
+        },
+        Vertex {
+            position: [0.0, 0.5],
+        },
+        Vertex {
+            position: [0.5, -0.25],
+        },
+    ]
+}
+
+/// `triangle()` turned by `time` radians around its centre, with
+/// `aspect`, width over height, keeping its shape on screen
+fn spinning_triangle(time: f32, aspect: f32) -> Vec<Vertex> {
+    let vertices = triangle();
+    let centre = vertices.iter().fold([0.0, 0.0], |sum, vertex| {
+        [sum[0] + vertex.position[0] / 3.0, sum[1] + vertex.position[1] / 3.0]
+    });
+    let (sin, cos) = time.sin_cos();
+    vertices
+        .into_iter()
+        .map(|vertex| {
+            let x = vertex.position[0] - centre[0];
+            let y = vertex.position[1] - centre[1];
+            Vertex {
+                position: [centre[0] + (x * cos - y * sin) / aspect, centre[1] + x * sin + y * cos],
+            }
+        })
+        .collect()
+}
+
+/// `count` copies of `triangle()` filling a square grid, shaded by
+/// position
+fn marker_grid(count: u32) -> Vec<Instance> {
+    let columns = (count as f32).sqrt().ceil() as u32;
+    let cell = 2.0 / columns as f32;
+    (0..count)
+        .map(|index| {
+            let (column, row) = (index % columns, index / columns);
+            let (u, v) = (column as f32 / columns as f32, row as f32 / columns as f32);
+            Instance {
+                offset: [-1.0 + (column as f32 + 0.5) * cell, -1.0 + (row as f32 + 0.5) * cell],
+                scale: cell * 0.8,
+                color: [u, v, 1.0 - u, 1.0],
+            }
+        })
+        .collect()
+}
+
+/// the markers of `marker_grid(count)` as separate triangles, three
+/// vertices each
+fn marker_triangles(count: u32) -> Vec<Vertex> {
+    marker_grid(count)
+        .into_iter()
+        .flat_map(|marker| {
+            triangle().into_iter().map(move |vertex| Vertex {
+                position: [
+                    marker.offset[0] + vertex.position[0] * marker.scale,
+                    marker.offset[1] + vertex.position[1] * marker.scale,
+                ],
+            })
+        })
+        .collect()
+}
 
 fn main() {
-    println!("Hello, Vulkan!");
+    logger::init();
+
+    let args = cli::parse();
+    let mut config = match Config::load(args.config.as_ref().map(|p| p.as_path())) {
+        Ok(config) => config,
+        Err(err) => {
+            error!("configuration: {}", err);
+            process::exit(1);
+        }
+    };
+
+    if let Some((ref input, ref output)) = args.blur {
+        let radius = args.blur_radius.unwrap_or(cli::DEFAULT_BLUR_RADIUS);
+        if let Err(err) = blur_image(&config, input, output, radius) {
+            error!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some((ref spirv, ref input, ref output)) = args.kernel {
+        if let Err(err) = run_kernel(&config, spirv, input, output) {
+            error!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if !args.filters.is_empty() {
+        if let Err(err) = filter_images(&config, &args) {
+            error!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.measure_refresh.is_some() && config.swapchain.present_mode != PresentModeConfig::Fifo {
+        info!("measuring the refresh rate, presenting in fifo mode");
+        config.swapchain.present_mode = PresentModeConfig::Fifo;
+    }
+
+    let mut backend = match backend::create_backend(args.backend.as_ref().map(|b| b.as_str()), &config) {
+        Ok(backend) => backend,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    info!("Hello, Vulkan! ({} backend)", backend.name());
+
+    if let Err(err) = run(&config, &mut *backend, &args) {
+        error!("{}", err);
+        process::exit(1);
+    }
+}
+
+/// what the user toggled, kept when the device is recreated
+struct Session {
+    pattern: Option<Pattern>,
+    fullscreen: bool,
+    screenshots: u32,
+    stats: bool,
+    started: Instant,
+}
+
+/// runs on a new device after a lost device or a GPU timeout, up to
+/// `watchdog.recoveries` times
+fn run(config: &Config, backend: &mut SurfaceBackend, args: &cli::Args) -> Result<(), RenderError> {
+    let mut vulkan_obj = match create_vk_struct(config, backend) {
+        Ok(vulkan_obj) => vulkan_obj,
+        Err(err) => {
+            if !config.framebuffer.software_fallback {
+                return Err(RenderError::Vulkan(Box::new(err)));
+            }
+            warn!("Vulkan is unavailable ({}), drawing on the CPU instead", err);
+            return software_fallback(config, backend).map_err(|err| RenderError::Vulkan(Box::new(err)));
+        }
+    };
+
+    let mut session = Session {
+        pattern: args.pattern,
+        // the window starts as configured
+        fullscreen: config.window.fullscreen,
+        screenshots: 0,
+        stats: false,
+        started: Instant::now(),
+    };
+    let mut recovery = Recovery::new(config.watchdog.recoveries);
+    loop {
+        let target = match backend.create_target(vulkan_obj.clone(), config) {
+            Ok(target) => target,
+            Err(err) => {
+                if recovery.done() > 0 {
+                    // the swapchain leaked with the lost device may hold
+                    // the surface, e.g. the display plane
+                    error!(
+                        "can't recreate the {} target after recovering the device, restart slicer",
+                        backend.name()
+                    );
+                }
+                return Err(err);
+            }
+        };
+        match run_device(config, backend, args, vulkan_obj.clone(), target, &mut session) {
+            Err(ref err) if recovery.recover(err) => {
+                // only the GPU objects stay behind
+                vulkan_obj.stop_watchdog();
+                error!(
+                    "{}, recreating the device ({} of {})",
+                    err,
+                    recovery.done(),
+                    recovery.limit()
+                );
+            }
+            result => return result,
+        }
+        vulkan_obj = create_vk_struct(config, backend).map_err(|err| RenderError::Vulkan(Box::new(err)))?;
+    }
+}
+
+/// `args.pattern` replaces the triangle by test patterns,
+/// `args.measure_refresh` reports the refresh rate after that long and
+/// quits, `args.animate` spins the triangle, `args.markers` draws a grid
+/// of them and `args.scene` too, with one draw each recorded on
+/// `args.workers` threads
+fn run_device(
+    config: &Config,
+    backend: &mut SurfaceBackend,
+    args: &cli::Args,
+    vulkan_obj: Arc<VulkanStruct>,
+    target: Box<PresentTarget>,
+    session: &mut Session,
+) -> Result<(), RenderError> {
+    let mut renderer = Renderer::new(vulkan_obj, target, config)?;
+    let pipeline = renderer.create_default_pipeline()?;
+    let patterns = match session.pattern {
+        Some(_) => Some(PatternRenderer::new(&renderer)?),
+        None => None,
+    };
+    let canvas = Canvas2d::new(&renderer)?;
+    let mut text = match Font::load(&config.text.font) {
+        Ok(font) => Some(TextRenderer::new(&renderer, font)?),
+        Err(err) => {
+            warn!("no stats overlay, {}: {}", config.text.font.display(), err);
+            None
+        }
+    };
+    let text_style = TextStyle::new(config.text.size);
 
-    slicer::run::run();
+    let vertex_buffer = renderer.create_vertex_buffer(triangle().into_iter())?;
+    let vertex_pool = renderer.create_vertex_pool::<Vertex>();
+    let markers = match args.markers {
+        Some(count) => {
+            let instances = renderer.create_vertex_buffer(marker_grid(count).into_iter())?;
+            let commands = renderer.create_indirect_buffer(
+                vec![
+                    DrawIndirectCommand {
+                        vertex_count: 3,
+                        instance_count: count,
+                        first_vertex: 0,
+                        first_instance: 0,
+                    },
+                ].into_iter(),
+            )?;
+            Some((renderer.create_instanced_pipeline()?, instances, commands))
+        }
+        None => None,
+    };
+    renderer.vulkan_obj().save_pipeline_cache();
+
+    let scene = match args.scene {
+        Some(count) => {
+            let upload = renderer.create_vertex_buffer(marker_triangles(count).into_iter())?;
+            // the meshes keep slices of the buffer, the first frame waits
+            let vertices = renderer.use_upload(&upload)?;
+            let mut scene = Scene::new();
+            for index in 0..count as usize {
+                let triangle = BufferSlice::from_typed_buffer_access(vertices.clone())
+                    .slice(index * 3..index * 3 + 3)
+                    .expect("three vertices per marker");
+                scene.add(Mesh::new(
+                    pipeline.clone(),
+                    vec![Arc::new(triangle) as Arc<BufferAccess + Send + Sync>],
+                ));
+            }
+            let recorder = SceneRecorder::new(args.workers.unwrap_or(cli::DEFAULT_WORKERS))
+                .map_err(|err| RenderError::Vulkan(Box::new(err)))?;
+            info!("recording {} draws on {} threads", scene.len(), recorder.workers());
+            Some((scene, recorder))
+        }
+        None => None,
+    };
+
+    let keybindings = Keybindings::from_config(&config.keybindings);
+    let mut input = InputState::new();
+    let mut overlay = StatsOverlay::new(Instant::now());
+    let mut batch = Batch::new();
+    let started = session.started;
+    let mut meter = args.measure_refresh.map(|duration| RefreshMeter::new(duration, backend.refresh_rate()));
+
+    loop {
+        for event in backend.pump_events() {
+            match event {
+                BackendEvent::Quit => return Ok(()),
+                BackendEvent::Resized(dimensions) => renderer.resize(dimensions),
+                BackendEvent::Input(event) => {
+                    let event = event.map_position(|position| renderer.map_input(position));
+                    input.handle(&event);
+                    if let (Some(pattern), Some(forward)) = (session.pattern.as_mut(), pattern_step(&event)) {
+                        *pattern = if forward { pattern.next() } else { pattern.previous() };
+                        info!("pattern {}", pattern.name());
+                    }
+                    match keybindings.triggered(&event) {
+                        Some(Action::Quit) => return Ok(()),
+                        Some(Action::Screenshot) => {
+                            let path = PathBuf::from(format!("screenshot-{}.png", session.screenshots));
+                            renderer.request_screenshot(path);
+                            session.screenshots += 1;
+                        }
+                        Some(Action::ToggleFullscreen) => {
+                            session.fullscreen = !session.fullscreen;
+                            backend.set_fullscreen(session.fullscreen);
+                        }
+                        Some(Action::ToggleStats) => {
+                            session.stats = !session.stats;
+                            overlay = StatsOverlay::new(Instant::now());
+                        }
+                        Some(Action::ReloadCorrection) => {
+                            if let Err(err) = renderer.reload_effects() {
+                                warn!("failed to reload the post-processing effects: {}", err);
+                            }
+                        }
+                        None => (),
+                    }
+                }
+            }
+        }
+
+        let mut frame = match renderer.begin_frame() {
+            Ok(frame) => frame,
+            Err(RenderError::OutOfDate) => continue,
+            Err(err) => return Err(err),
+        };
+        if let Some(ref mut meter) = meter {
+            meter.record(Instant::now());
+        }
+
+        match (session.pattern, patterns.as_ref(), markers.as_ref(), scene.as_ref()) {
+            (Some(pattern), Some(patterns), _, _) => {
+                let elapsed = started.elapsed();
+                let time = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+                patterns.draw(&renderer, &mut frame, pattern, time)?;
+            }
+            (_, _, _, Some(&(ref scene, ref recorder))) => recorder.draw(&renderer, &mut frame, scene)?,
+            (_, _, Some(&(ref instanced_pipeline, ref instances, ref commands)), _) => {
+                let vertices = vec![
+                    renderer.use_upload(&vertex_buffer)? as Arc<BufferAccess + Send + Sync>,
+                    renderer.use_upload(instances)? as Arc<BufferAccess + Send + Sync>,
+                ];
+                let commands = renderer.use_upload(commands)?;
+                let pre_rotation = renderer.pre_rotation();
+                renderer.draw_indirect(
+                    &mut frame,
+                    instanced_pipeline.clone(),
+                    vertices,
+                    commands,
+                    (),
+                    pre_rotation,
+                )?;
+            }
+            _ => {
+                let vertices = if args.animate {
+                    let elapsed = started.elapsed();
+                    let time = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+                    let size = renderer.content_dimensions();
+                    let aspect = size[0] as f32 / size[1] as f32;
+                    let chunk = vertex_pool.chunk(spinning_triangle(time, aspect))?;
+                    Arc::new(chunk) as Arc<BufferAccess + Send + Sync>
+                } else {
+                    renderer.use_upload(&vertex_buffer)? as Arc<BufferAccess + Send + Sync>
+                };
+                let pre_rotation = renderer.pre_rotation();
+                renderer.draw(&mut frame, pipeline.clone(), vec![vertices], (), pre_rotation)?;
+            }
+        }
+
+        if session.stats {
+            if let Some(ref mut text) = text {
+                // not blurred or corrected with the scene
+                renderer.begin_overlay(&mut frame)?;
+                overlay.queue(&mut batch, text, &text_style);
+                canvas.draw(&renderer, &mut frame, &batch)?;
+                batch.clear();
+                text.draw(&mut renderer, &mut frame)?;
+            }
+        }
+
+        match renderer.end_frame(frame) {
+            Ok(()) | Err(RenderError::OutOfDate) => (),
+            Err(err) => return Err(err),
+        }
+        input.end_frame();
+
+        if session.stats && overlay.frame(Instant::now()) {
+            info!("{}", overlay.label());
+        }
+
+        if let Some(ref meter) = meter {
+            if meter.is_done() {
+                match meter.report() {
+                    Some(report) => for line in report.to_string().lines() {
+                        info!("{}", line);
+                    },
+                    None => warn!("no frame presented, can't measure the refresh rate"),
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// blurs `input` into `output` on the compute queue, without any surface
+fn blur_image(config: &Config, input: &Path, output: &Path, radius: u32) -> Result<(), Box<Error>> {
+    let image = image::open(input)?.to_rgba();
+    let vulkan_obj = create_vk_struct(config, &HeadlessBackend::new())?;
+    let blur = GaussianBlur::new(vulkan_obj)?;
+
+    let started = Instant::now();
+    let blurred = blur.apply(&image, radius)?;
+    info!("blurred {} by {} pixels in {:?}", input.display(), radius, started.elapsed());
+    blurred.save(output)?;
+    Ok(())
+}
+
+/// runs the compute kernel of the `spirv` file over `input` into `output`
+fn run_kernel(config: &Config, spirv: &Path, input: &Path, output: &Path) -> Result<(), Box<Error>> {
+    let module = fs::read(spirv)?;
+    let image = image::open(input)?.to_rgba();
+    let vulkan_obj = create_vk_struct(config, &HeadlessBackend::new())?;
+    // the module is whatever the user passed, only its header is checked
+    let kernel = unsafe { Kernel::from_spirv(vulkan_obj, &module, "main", KernelLayout::image_filter())? };
+
+    let started = Instant::now();
+    let result = kernel.apply(&image)?;
+    info!("ran {} over {} in {:?}", spirv.display(), input.display(), started.elapsed());
+    result.save(output)?;
+    Ok(())
+}
+
+/// applies `args.filters` to each of `args.inputs`, on the compute queue
+/// unless there is no Vulkan device or `args.cpu` is set
+fn filter_images(config: &Config, args: &cli::Args) -> Result<(), Box<Error>> {
+    let chain = if args.cpu {
+        None
+    } else {
+        match create_vk_struct(config, &HeadlessBackend::new()) {
+            Ok(vulkan_obj) => Some(FilterChain::new(vulkan_obj)?),
+            Err(err) => {
+                warn!("no Vulkan device ({}), filtering on the CPU", err);
+                None
+            }
+        }
+    };
+    if args.compare && chain.is_none() {
+        return Err("--compare needs a Vulkan device".into());
+    }
+
+    let out_dir = args.out_dir.clone().unwrap_or_else(|| PathBuf::from(cli::DEFAULT_OUT_DIR));
+    fs::create_dir_all(&out_dir)?;
+
+    for input in &args.inputs {
+        let image = image::open(input)?.to_rgba();
+        let started = Instant::now();
+        let filtered = match chain {
+            Some(ref chain) => chain.apply(&image, &args.filters)?,
+            None => filter::cpu::apply_chain(&image, &args.filters),
+        };
+        info!("filtered {} in {:?}", input.display(), started.elapsed());
+
+        if args.compare {
+            let started = Instant::now();
+            let reference = filter::cpu::apply_chain(&image, &args.filters);
+            info!("filtered {} on the CPU in {:?}", input.display(), started.elapsed());
+            match filter::difference(&filtered, &reference) {
+                Some((largest, mean)) => info!("largest difference {}, mean {:.4}", largest, mean),
+                None => warn!("the results of {} have different sizes", input.display()),
+            }
+        }
+
+        let name = input.file_name().ok_or("input without a file name")?;
+        filtered.save(out_dir.join(name))?;
+    }
+    Ok(())
 }
 
+/// `Some(true)` for the next test pattern (space, right, down, a click or
+/// a touch), `Some(false)` for the previous (left, up)
+fn pattern_step(event: &InputEvent) -> Option<bool> {
+    match *event {
+        InputEvent::Key { key: Key::Space, pressed: true }
+        | InputEvent::Key { key: Key::Right, pressed: true }
+        | InputEvent::Key { key: Key::Down, pressed: true }
+        | InputEvent::MouseButton { button: MouseButton::Left, pressed: true }
+        | InputEvent::Touch { phase: TouchPhase::Started, .. } => Some(true),
+        InputEvent::Key { key: Key::Left, pressed: true } | InputEvent::Key { key: Key::Up, pressed: true } => {
+            Some(false)
+        }
+        _ => None,
+    }
+}
+
+/// draws the triangle once into `framebuffer.device`, then keeps it on
+/// screen until `backend` quits or the quit key is pressed
+#[cfg(target_os = "linux")]
+fn software_fallback(config: &Config, backend: &mut SurfaceBackend) -> io::Result<()> {
+    let mut fb = LinuxFramebuffer::open(&config.framebuffer.device)?;
+    let dimensions = fb.dimensions();
+    info!(
+        "software rendering to {} ({}x{})",
+        config.framebuffer.device.display(),
+        dimensions[0],
+        dimensions[1]
+    );
+
+    let orientation = Orientation::from_config(&config.orientation)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let vertices: Vec<[f32; 2]> = triangle()
+        .iter()
+        .map(|vertex| orientation.apply(vertex.position))
+        .collect();
+    let mut canvas = Canvas::new(dimensions[0], dimensions[1]);
+    canvas.clear(config.clear_color);
+    canvas.fill_triangle(
+        &[vertices[0], vertices[1], vertices[2]],
+        [1.0, 0.0, 0.0, 1.0],
+    );
+    fb.blit_rgba(canvas.pixels(), dimensions[0], dimensions[1]);
+
+    let keybindings = Keybindings::from_config(&config.keybindings);
+    loop {
+        for event in backend.pump_events() {
+            match event {
+                BackendEvent::Quit => return Ok(()),
+                BackendEvent::Input(ref event) if keybindings.triggered(event) == Some(Action::Quit) => {
+                    return Ok(())
+                }
+                // the picture is drawn once, for the framebuffer's size
+                _ => (),
+            }
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn software_fallback(_config: &Config, _backend: &mut SurfaceBackend) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "no framebuffer device on this platform",
+    ))
+}
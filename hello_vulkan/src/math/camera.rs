@@ -0,0 +1,211 @@
+//! Cameras steered with the mouse, touch and keyboard
+
+use std::f32::consts::PI;
+
+use input::{InputEvent, InputState, Key, MouseButton, TouchPhase};
+use super::{Mat4, Vec3};
+
+/// radians per pixel dragged
+const ROTATE_SPEED: f32 = 0.005;
+/// distance factor per line scrolled
+const ZOOM_STEP: f32 = 0.9;
+/// just short of straight up or down, where `look_at` breaks
+const MAX_PITCH: f32 = PI * 0.49;
+
+pub trait Camera {
+    fn view(&self) -> Mat4;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Perspective {
+    /// vertical, in radians
+    pub fovy: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Perspective {
+    fn default() -> Perspective {
+        Perspective {
+            fovy: PI / 3.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+impl Perspective {
+    /// `size` of the upright content, in pixels
+    pub fn matrix(&self, size: [u32; 2]) -> Mat4 {
+        let aspect = size[0] as f32 / size[1].max(1) as f32;
+        Mat4::perspective(self.fovy, aspect, self.near, self.far)
+    }
+}
+
+/// follows a pointer dragged with a mouse button held or one finger
+#[derive(Debug, Clone, Default)]
+struct Drag {
+    cursor: Option<[f32; 2]>,
+    dragging: bool,
+    touch: Option<u32>,
+    /// accumulated since the last `take`
+    delta: [f32; 2],
+}
+
+impl Drag {
+    fn handle(&mut self, event: &InputEvent, button: MouseButton) {
+        match *event {
+            InputEvent::MouseButton { button: b, pressed } if b == button => self.dragging = pressed,
+            InputEvent::CursorMoved(position) => {
+                if let (true, Some(cursor)) = (self.dragging, self.cursor) {
+                    self.delta[0] += position[0] - cursor[0];
+                    self.delta[1] += position[1] - cursor[1];
+                }
+                self.cursor = Some(position);
+            }
+            InputEvent::Touch {
+                id,
+                phase,
+                position,
+            } => match phase {
+                TouchPhase::Started if self.touch.is_none() => {
+                    self.touch = Some(id);
+                    self.cursor = Some(position);
+                }
+                TouchPhase::Moved if self.touch == Some(id) => {
+                    if let Some(cursor) = self.cursor {
+                        self.delta[0] += position[0] - cursor[0];
+                        self.delta[1] += position[1] - cursor[1];
+                    }
+                    self.cursor = Some(position);
+                }
+                TouchPhase::Ended if self.touch == Some(id) => self.touch = None,
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    fn take(&mut self) -> [f32; 2] {
+        let delta = self.delta;
+        self.delta = [0.0, 0.0];
+        delta
+    }
+}
+
+/// turns around `target`: drag to rotate, scroll to zoom
+#[derive(Debug, Clone)]
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    /// around y, 0 puts the camera on +z
+    pub yaw: f32,
+    /// above the target's horizon
+    pub pitch: f32,
+    drag: Drag,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Vec3, distance: f32) -> OrbitCamera {
+        OrbitCamera {
+            target: target,
+            distance: distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            drag: Drag::default(),
+        }
+    }
+
+    pub fn handle(&mut self, event: &InputEvent) {
+        self.drag.handle(event, MouseButton::Left);
+        let delta = self.drag.take();
+        self.yaw -= delta[0] * ROTATE_SPEED;
+        self.pitch = (self.pitch + delta[1] * ROTATE_SPEED).max(-MAX_PITCH).min(MAX_PITCH);
+
+        if let InputEvent::Scroll(lines) = *event {
+            self.distance = (self.distance * ZOOM_STEP.powf(lines[1])).max(1e-3);
+        }
+    }
+
+    pub fn eye(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        self.target + Vec3::new(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch) * self.distance
+    }
+}
+
+impl Camera for OrbitCamera {
+    fn view(&self) -> Mat4 {
+        Mat4::look_at(self.eye(), self.target, Vec3::up())
+    }
+}
+
+/// WASD to move, Q and E down and up, shift to go faster, right mouse
+/// button or a finger dragged to look around
+#[derive(Debug, Clone)]
+pub struct FlyCamera {
+    pub position: Vec3,
+    /// around y, 0 looks down -z
+    pub yaw: f32,
+    pub pitch: f32,
+    /// units per second
+    pub speed: f32,
+    drag: Drag,
+}
+
+impl FlyCamera {
+    pub fn new(position: Vec3) -> FlyCamera {
+        FlyCamera {
+            position: position,
+            yaw: 0.0,
+            pitch: 0.0,
+            speed: 2.0,
+            drag: Drag::default(),
+        }
+    }
+
+    pub fn handle(&mut self, event: &InputEvent) {
+        self.drag.handle(event, MouseButton::Right);
+        let delta = self.drag.take();
+        self.yaw -= delta[0] * ROTATE_SPEED;
+        self.pitch = (self.pitch - delta[1] * ROTATE_SPEED).max(-MAX_PITCH).min(MAX_PITCH);
+    }
+
+    /// moves for the keys held during `seconds`
+    pub fn update(&mut self, input: &InputState, seconds: f32) {
+        let forward = self.forward();
+        let right = forward.cross(Vec3::up()).normalize();
+        let mut direction = Vec3::zero();
+        for &(key, towards) in &[
+            (Key::W, forward),
+            (Key::S, -forward),
+            (Key::D, right),
+            (Key::A, -right),
+            (Key::E, Vec3::up()),
+            (Key::Q, -Vec3::up()),
+        ] {
+            if input.is_down(key) {
+                direction += towards;
+            }
+        }
+
+        let boost = if input.is_down(Key::LShift) || input.is_down(Key::RShift) {
+            4.0
+        } else {
+            1.0
+        };
+        self.position += direction.normalize() * (self.speed * boost * seconds);
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vec3::new(-sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch)
+    }
+}
+
+impl Camera for FlyCamera {
+    fn view(&self) -> Mat4 {
+        Mat4::look_at(self.position, self.position + self.forward(), Vec3::up())
+    }
+}
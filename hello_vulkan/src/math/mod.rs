@@ -0,0 +1,335 @@
+//! Vectors, matrices and quaternions for 3D scenes
+//!
+//! The world is right handed with y up and cameras looking down -z.
+//! Projections map into Vulkan's clip space: y points down and depth goes
+//! from 0 at the near plane to 1 at the far plane. Matrices are column
+//! major, `Mat4::cols` goes as is into a GLSL `mat4` push constant.
+
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+mod camera;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::camera::{Camera, FlyCamera, OrbitCamera, Perspective};
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// column major
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat4 {
+    pub cols: [[f32; 4]; 4],
+}
+
+/// unit quaternions are rotations
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Vec3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Vec3 {
+        Vec3 { x: x, y: y, z: z }
+    }
+
+    pub fn zero() -> Vec3 {
+        Vec3::default()
+    }
+
+    pub fn up() -> Vec3 {
+        Vec3::new(0.0, 1.0, 0.0)
+    }
+
+    pub fn dot(self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// the zero vector stays zero
+    pub fn normalize(self) -> Vec3 {
+        let length = self.length();
+        if length > 0.0 {
+            self * (1.0 / length)
+        } else {
+            self
+        }
+    }
+
+    pub fn to_array(self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from(v: [f32; 3]) -> Vec3 {
+        Vec3::new(v[0], v[1], v[2])
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, factor: f32) -> Vec3 {
+        Vec3::new(self.x * factor, self.y * factor, self.z * factor)
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mat4 {
+    pub fn identity() -> Mat4 {
+        Mat4 {
+            cols: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        let mut matrix = Mat4::identity();
+        matrix.cols[3] = [offset.x, offset.y, offset.z, 1.0];
+        matrix
+    }
+
+    pub fn scale(factors: Vec3) -> Mat4 {
+        let mut matrix = Mat4::identity();
+        matrix.cols[0][0] = factors.x;
+        matrix.cols[1][1] = factors.y;
+        matrix.cols[2][2] = factors.z;
+        matrix
+    }
+
+    pub fn rotation(rotation: Quat) -> Mat4 {
+        let Quat { x, y, z, w } = rotation;
+        Mat4 {
+            cols: [
+                [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w), 2.0 * (x * z - y * w), 0.0],
+                [2.0 * (x * y - z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w), 0.0],
+                [2.0 * (x * z + y * w), 2.0 * (y * z - x * w), 1.0 - 2.0 * (x * x + y * y), 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// view matrix of a camera at `eye` looking at `target`
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        let forward = (target - eye).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        Mat4 {
+            cols: [
+                [right.x, up.x, -forward.x, 0.0],
+                [right.y, up.y, -forward.y, 0.0],
+                [right.z, up.z, -forward.z, 0.0],
+                [-right.dot(eye), -up.dot(eye), forward.dot(eye), 1.0],
+            ],
+        }
+    }
+
+    /// `fovy` in radians, `aspect` is width over height
+    pub fn perspective(fovy: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fovy * 0.5).tan();
+        Mat4 {
+            cols: [
+                [f / aspect, 0.0, 0.0, 0.0],
+                [0.0, -f, 0.0, 0.0],
+                [0.0, 0.0, far / (near - far), -1.0],
+                [0.0, 0.0, near * far / (near - far), 0.0],
+            ],
+        }
+    }
+
+    /// the view volume goes from `left` to `right`, `bottom` to `top`
+    /// and `-near` to `-far` along z
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        Mat4 {
+            cols: [
+                [2.0 / (right - left), 0.0, 0.0, 0.0],
+                [0.0, -2.0 / (top - bottom), 0.0, 0.0],
+                [0.0, 0.0, -1.0 / (far - near), 0.0],
+                [
+                    -(right + left) / (right - left),
+                    (top + bottom) / (top - bottom),
+                    -near / (far - near),
+                    1.0,
+                ],
+            ],
+        }
+    }
+
+    /// the 2x2 `PreRotation` matrix of the renderer acting on clip space
+    /// x and y, to apply after the projection
+    pub fn pre_rotation(matrix: [f32; 4]) -> Mat4 {
+        let mut rotation = Mat4::identity();
+        rotation.cols[0][0] = matrix[0];
+        rotation.cols[0][1] = matrix[1];
+        rotation.cols[1][0] = matrix[2];
+        rotation.cols[1][1] = matrix[3];
+        rotation
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut cols = [[0.0; 4]; 4];
+        for (c, col) in cols.iter_mut().enumerate() {
+            for (r, value) in col.iter_mut().enumerate() {
+                *value = self.cols[r][c];
+            }
+        }
+        Mat4 { cols: cols }
+    }
+
+    /// with the perspective divide
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        let v = self.transform([point.x, point.y, point.z, 1.0]);
+        Vec3::new(v[0] / v[3], v[1] / v[3], v[2] / v[3])
+    }
+
+    /// ignores the translation
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        let v = self.transform([vector.x, vector.y, vector.z, 0.0]);
+        Vec3::new(v[0], v[1], v[2])
+    }
+
+    pub fn transform(&self, v: [f32; 4]) -> [f32; 4] {
+        let mut result = [0.0; 4];
+        for (col, &factor) in self.cols.iter().zip(v.iter()) {
+            for (value, &entry) in result.iter_mut().zip(col.iter()) {
+                *value += entry * factor;
+            }
+        }
+        result
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    /// applies `other` first
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut cols = [[0.0; 4]; 4];
+        for (c, col) in cols.iter_mut().enumerate() {
+            *col = self.transform(other.cols[c]);
+        }
+        Mat4 { cols: cols }
+    }
+}
+
+impl Quat {
+    pub fn identity() -> Quat {
+        Quat {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        }
+    }
+
+    /// counter clockwise by `angle` radians looking down `axis`
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let axis = axis.normalize();
+        let (sin, cos) = (angle * 0.5).sin_cos();
+        Quat {
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+            w: cos,
+        }
+    }
+
+    pub fn conjugate(self) -> Quat {
+        Quat {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: self.w,
+        }
+    }
+
+    /// keeps long chains of products a rotation
+    pub fn normalize(self) -> Quat {
+        let length = (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt();
+        Quat {
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+            w: self.w / length,
+        }
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let axis = Vec3::new(self.x, self.y, self.z);
+        let t = axis.cross(v) * 2.0;
+        v + t * self.w + axis.cross(t)
+    }
+}
+
+impl Mul for Quat {
+    type Output = Quat;
+
+    /// applies `other` first
+    fn mul(self, other: Quat) -> Quat {
+        Quat {
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::f32::consts::PI;
+
+use input::{InputEvent, InputState, Key, MouseButton};
+use super::{Camera, FlyCamera, Mat4, OrbitCamera, Quat, Vec3};
+
+fn assert_near(actual: Vec3, expected: Vec3) {
+    assert!((actual - expected).length() < 1e-4, "{:?} != {:?}", actual, expected);
+}
+
+#[test]
+fn perspective_uses_vulkan_clip_space() {
+    let projection = Mat4::perspective(PI / 2.0, 2.0, 1.0, 10.0);
+    assert_near(projection.transform_point(Vec3::new(0.0, 0.0, -1.0)), Vec3::new(0.0, 0.0, 0.0));
+    assert_near(projection.transform_point(Vec3::new(0.0, 0.0, -10.0)), Vec3::new(0.0, 0.0, 1.0));
+    // up in the world is down in clip space, x is divided by the aspect
+    let corner = projection.transform_point(Vec3::new(2.0, 1.0, -1.0));
+    assert!((corner.x - 1.0).abs() < 1e-5 && (corner.y + 1.0).abs() < 1e-5);
+
+    let ortho = Mat4::orthographic(-4.0, 4.0, -2.0, 2.0, 1.0, 5.0);
+    assert_near(ortho.transform_point(Vec3::new(4.0, 2.0, -1.0)), Vec3::new(1.0, -1.0, 0.0));
+    assert_near(ortho.transform_point(Vec3::new(-4.0, -2.0, -5.0)), Vec3::new(-1.0, 1.0, 1.0));
+}
+
+#[test]
+fn look_at_puts_the_target_ahead() {
+    let view = Mat4::look_at(Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 2.0, -2.0), Vec3::up());
+    assert_near(view.transform_point(Vec3::new(1.0, 2.0, 3.0)), Vec3::zero());
+    assert_near(view.transform_point(Vec3::new(1.0, 2.0, -2.0)), Vec3::new(0.0, 0.0, -5.0));
+    assert_near(view.transform_point(Vec3::new(2.0, 3.0, 3.0)), Vec3::new(1.0, 1.0, 0.0));
+}
+
+#[test]
+fn quaternions_match_matrices() {
+    let quarter = Quat::from_axis_angle(Vec3::up(), PI / 2.0);
+    assert_near(quarter.rotate(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 0.0, -1.0));
+
+    let rotation = (quarter * Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), 0.3)).normalize();
+    let v = Vec3::new(0.5, -2.0, 1.5);
+    assert_near(Mat4::rotation(rotation).transform_vector(v), rotation.rotate(v));
+    assert_near(rotation.conjugate().rotate(rotation.rotate(v)), v);
+
+    let model = Mat4::translation(Vec3::new(1.0, 0.0, 0.0)) * Mat4::scale(Vec3::new(2.0, 2.0, 2.0));
+    assert_near(model.transform_point(Vec3::new(1.0, 1.0, 1.0)), Vec3::new(3.0, 2.0, 2.0));
+    assert_eq!(model.transpose().transpose(), model);
+}
+
+#[test]
+fn orbit_camera_follows_the_drag() {
+    let mut camera = OrbitCamera::new(Vec3::zero(), 5.0);
+    assert_near(camera.eye(), Vec3::new(0.0, 0.0, 5.0));
+
+    camera.handle(&InputEvent::CursorMoved([100.0, 100.0]));
+    camera.handle(&InputEvent::MouseButton {
+        button: MouseButton::Left,
+        pressed: true,
+    });
+    camera.handle(&InputEvent::CursorMoved([100.0, 10000.0]));
+    assert!(camera.pitch < PI / 2.0 && camera.eye().y > 4.9);
+    assert_near(camera.view().transform_point(Vec3::zero()), Vec3::new(0.0, 0.0, -5.0));
+
+    camera.handle(&InputEvent::Scroll([0.0, 1.0]));
+    assert!((camera.distance - 4.5).abs() < 1e-5);
+}
+
+#[test]
+fn fly_camera_moves_with_the_keys() {
+    let mut camera = FlyCamera::new(Vec3::zero());
+    let mut input = InputState::new();
+    input.handle(&InputEvent::Key {
+        key: Key::W,
+        pressed: true,
+    });
+    input.handle(&InputEvent::Key {
+        key: Key::D,
+        pressed: true,
+    });
+    camera.update(&input, 1.0);
+    let step = 2.0 / 2.0f32.sqrt();
+    assert_near(camera.position, Vec3::new(step, 0.0, -step));
+
+    // nothing held
+    let before = camera.position;
+    camera.update(&InputState::new(), 1.0);
+    assert_eq!(camera.position, before);
+}
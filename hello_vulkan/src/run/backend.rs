@@ -0,0 +1,139 @@
+//! Surface backends
+//!
+//! A backend knows which extensions its surface needs, creates the
+//! `PresentTarget` the renderer draws into and pumps the events of its
+//! window system. Several backends can be compiled in, one is picked at
+//! runtime by name:
+//!
+//! * `win`: a winit window (feature `win`)
+//! * `display`: a `VK_KHR_display` surface on the panel (feature `fbdev`)
+//! * `headless`: offscreen images written out as PNG, always available
+//! * `fb`: offscreen images copied to a Linux framebuffer device, for
+//!   boards where the display driver has no Vulkan support
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use vulkano::instance::{DeviceExtensions, InstanceExtensions};
+
+use config::Config;
+use input::InputEvent;
+#[cfg(target_os = "linux")]
+use linuxfb::LinuxFramebuffer;
+
+use super::VulkanStruct;
+use super::headless::HeadlessBackend;
+use super::renderer::RenderError;
+use super::target::PresentTarget;
+
+#[cfg(feature = "fbdev")]
+use super::fbdev::DisplayBackend;
+#[cfg(feature = "win")]
+use super::win::WindowBackend;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendEvent {
+    Quit,
+    Resized([u32; 2]),
+    Input(InputEvent),
+}
+
+pub trait SurfaceBackend {
+    fn name(&self) -> &'static str;
+
+    /// instance extensions the surface needs
+    fn required_extensions(&self) -> InstanceExtensions;
+
+    /// device extensions the target needs
+    fn required_device_extensions(&self) -> DeviceExtensions;
+
+    fn create_target(
+        &mut self,
+        vulkan_obj: Arc<VulkanStruct>,
+        config: &Config,
+    ) -> Result<Box<PresentTarget>, RenderError>;
+
+    /// returns the events received since the last call, never blocks
+    fn pump_events(&mut self) -> Vec<BackendEvent>;
+
+    /// ignored by backends which always cover the whole display
+    fn set_fullscreen(&mut self, _fullscreen: bool) {}
+
+    /// refresh rate of the display mode in mHz, once the target is
+    /// created; `None` when the backend can't tell
+    fn refresh_rate(&self) -> Option<u32> {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum BackendError {
+    Unknown(String),
+    /// the backend exists but can't be set up, e.g. a missing device
+    Unavailable(&'static str, String),
+}
+
+/// names of the backends compiled in, in order of preference
+pub fn available_backends() -> Vec<&'static str> {
+    let mut names = Vec::new();
+    if cfg!(feature = "win") {
+        names.push("win");
+    }
+    if cfg!(feature = "fbdev") {
+        names.push("display");
+    }
+    names.push("headless");
+    if cfg!(target_os = "linux") {
+        names.push("fb");
+    }
+    names
+}
+
+/// `None` picks the first of `available_backends()`
+pub fn create_backend(name: Option<&str>, config: &Config) -> Result<Box<SurfaceBackend>, BackendError> {
+    let name = name.unwrap_or(available_backends()[0]);
+    match name {
+        #[cfg(feature = "win")]
+        "win" => Ok(Box::new(WindowBackend::new())),
+        #[cfg(feature = "fbdev")]
+        "display" => Ok(Box::new(DisplayBackend::new())),
+        "headless" => Ok(Box::new(HeadlessBackend::new())),
+        #[cfg(target_os = "linux")]
+        "fb" => {
+            let fb = LinuxFramebuffer::open(&config.framebuffer.device).map_err(|err| {
+                BackendError::Unavailable(
+                    "fb",
+                    format!("{}: {}", config.framebuffer.device.display(), err),
+                )
+            })?;
+            Ok(Box::new(HeadlessBackend::framebuffer(fb)))
+        }
+        name => Err(BackendError::Unknown(name.to_owned())),
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BackendError::Unknown(ref name) => write!(
+                f,
+                "unknown backend '{}', available: {}",
+                name,
+                available_backends().join(", ")
+            ),
+            BackendError::Unavailable(name, ref reason) => {
+                write!(f, "backend '{}' is unavailable: {}", name, reason)
+            }
+        }
+    }
+}
+
+impl Error for BackendError {
+    fn description(&self) -> &str {
+        match *self {
+            BackendError::Unknown(_) => "unknown backend",
+            BackendError::Unavailable(..) => "backend is unavailable",
+        }
+    }
+}
@@ -0,0 +1,13 @@
+#version 450
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+layout(push_constant) uniform BlitParams {
+    // output size in pixels
+    vec2 size;
+} params;
+
+void main() {
+    f_color = texture(source, gl_FragCoord.xy / params.size);
+}
@@ -0,0 +1,30 @@
+#version 450
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+// one direction of a separable gaussian blur
+layout(push_constant) uniform BlurParams {
+    // output size in pixels
+    vec2 size;
+    // (1, 0) or (0, 1)
+    vec2 direction;
+    // standard deviation in output pixels
+    float radius;
+} params;
+
+void main() {
+    vec2 uv = gl_FragCoord.xy / params.size;
+    vec2 step = params.direction / params.size;
+    int taps = int(ceil(params.radius * 3.0));
+    float sigma2 = 2.0 * params.radius * params.radius;
+
+    vec4 sum = texture(source, uv);
+    float total = 1.0;
+    for (int i = 1; i <= taps; i++) {
+        float weight = exp(-float(i * i) / sigma2);
+        sum += weight * (texture(source, uv + step * float(i)) + texture(source, uv - step * float(i)));
+        total += 2.0 * weight;
+    }
+    f_color = sum / total;
+}
@@ -0,0 +1,283 @@
+//! Tessellation of the canvas shapes into triangles, on the CPU
+
+use std::f32::consts::PI;
+
+use super::super::shaders::ColorVertex;
+
+/// largest distance in pixels between a curve and its polygon
+const TOLERANCE: f32 = 0.25;
+/// miters longer than that many half widths are bevelled
+const MITER_LIMIT: f32 = 4.0;
+/// points of a path closer than that in pixels are merged
+const SAME_POINT: f32 = 1e-3;
+
+pub type Point = [f32; 2];
+pub type Color = [f32; 4];
+
+/// shapes of one frame, as a triangle list in pixels
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    vertices: Vec<ColorVertex>,
+}
+
+impl Batch {
+    pub fn new() -> Batch {
+        Batch::default()
+    }
+
+    pub fn vertices(&self) -> &[ColorVertex] {
+        &self.vertices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// keeps the allocation for the next frame
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn triangle(&mut self, a: Point, b: Point, c: Point, color: Color) {
+        for &position in &[a, b, c] {
+            self.vertices.push(ColorVertex {
+                position: position,
+                color: color,
+            });
+        }
+    }
+
+    /// `a b c d` in order around the quad
+    fn quad(&mut self, a: Point, b: Point, c: Point, d: Point, color: Color) {
+        self.triangle(a, b, c, color);
+        self.triangle(a, c, d, color);
+    }
+
+    /// `origin` is the top left corner
+    pub fn fill_rect(&mut self, origin: Point, size: [f32; 2], color: Color) {
+        let (x0, y0) = (origin[0], origin[1]);
+        let (x1, y1) = (x0 + size[0], y0 + size[1]);
+        self.quad([x0, y0], [x1, y0], [x1, y1], [x0, y1], color);
+    }
+
+    /// the stroke is centred on the outline
+    pub fn stroke_rect(&mut self, origin: Point, size: [f32; 2], width: f32, color: Color) {
+        let (x0, y0) = (origin[0], origin[1]);
+        let (x1, y1) = (x0 + size[0], y0 + size[1]);
+        self.stroke_path(&[[x0, y0], [x1, y0], [x1, y1], [x0, y1]], true, width, color);
+    }
+
+    /// `radius` is clamped to half the smallest side
+    pub fn fill_rounded_rect(&mut self, origin: Point, size: [f32; 2], radius: f32, color: Color) {
+        let outline = rounded_rect(origin, size, radius);
+        self.fill_convex(&outline, color);
+    }
+
+    pub fn stroke_rounded_rect(
+        &mut self,
+        origin: Point,
+        size: [f32; 2],
+        radius: f32,
+        width: f32,
+        color: Color,
+    ) {
+        let outline = rounded_rect(origin, size, radius);
+        self.stroke_path(&outline, true, width, color);
+    }
+
+    pub fn fill_circle(&mut self, centre: Point, radius: f32, color: Color) {
+        let outline = circle(centre, radius);
+        self.fill_convex(&outline, color);
+    }
+
+    pub fn stroke_circle(&mut self, centre: Point, radius: f32, width: f32, color: Color) {
+        let outline = circle(centre, radius);
+        self.stroke_path(&outline, true, width, color);
+    }
+
+    pub fn line(&mut self, from: Point, to: Point, width: f32, color: Color) {
+        self.stroke_path(&[from, to], false, width, color);
+    }
+
+    /// mitered joins, bevelled when too sharp; butt ends. Nothing is
+    /// covered twice, unless a bevelled join is sharper than its segments
+    /// are long
+    pub fn polyline(&mut self, points: &[Point], width: f32, color: Color) {
+        self.stroke_path(points, false, width, color);
+    }
+
+    /// fan from the first point
+    pub fn fill_convex(&mut self, points: &[Point], color: Color) {
+        for pair in points.windows(2).skip(1) {
+            self.triangle(points[0], pair[0], pair[1], color);
+        }
+    }
+
+    fn stroke_path(&mut self, points: &[Point], closed: bool, width: f32, color: Color) {
+        let mut path: Vec<Point> = Vec::with_capacity(points.len());
+        for &point in points {
+            if path.last().map_or(true, |&last| distance(last, point) > SAME_POINT) {
+                path.push(point);
+            }
+        }
+        if closed && path.len() > 1 && distance(path[0], path[path.len() - 1]) <= SAME_POINT {
+            path.pop();
+        }
+        let count = path.len();
+        if count < 2 {
+            return;
+        }
+
+        let half = width * 0.5;
+        // left and right offsets where the segments come in and go out
+        let mut incoming = Vec::with_capacity(count);
+        let mut outgoing = Vec::with_capacity(count);
+        for (i, &point) in path.iter().enumerate() {
+            let previous = if i > 0 {
+                Some(path[i - 1])
+            } else if closed {
+                Some(path[count - 1])
+            } else {
+                None
+            };
+            let next = if i + 1 < count {
+                Some(path[i + 1])
+            } else if closed {
+                Some(path[0])
+            } else {
+                None
+            };
+
+            match (previous, next) {
+                (Some(previous), Some(next)) => {
+                    let (n1, n2) = (normal(previous, point), normal(point, next));
+                    let sum = [n1[0] + n2[0], n1[1] + n2[1]];
+                    let length = (sum[0] * sum[0] + sum[1] * sum[1]).sqrt();
+                    let cos = if length > 1e-6 {
+                        (sum[0] * n1[0] + sum[1] * n1[1]) / length
+                    } else {
+                        0.0
+                    };
+                    if cos * MITER_LIMIT > 1.0 {
+                        let miter = half / (cos * length);
+                        let offset = [sum[0] * miter, sum[1] * miter];
+                        let sides = (add(point, offset), sub(point, offset));
+                        incoming.push(sides);
+                        outgoing.push(sides);
+                    } else {
+                        // +1 turning towards the left side, which is then
+                        // the inner one
+                        let side = if n1[0] * n2[1] - n1[1] * n2[0] > 0.0 { 1.0 } else { -1.0 };
+                        let o1 = [n1[0] * half * side, n1[1] * half * side];
+                        let o2 = [n2[0] * half * side, n2[1] * half * side];
+                        let room = distance(previous, point).min(distance(point, next));
+                        // the inner sides end where they cross, when that
+                        // is within both segments; else they overlap
+                        let (inner1, inner2, pivot) = if cos > 1e-6 && half / cos <= room {
+                            let miter = side * half / (cos * length);
+                            let crossing = add(point, [sum[0] * miter, sum[1] * miter]);
+                            (crossing, crossing, crossing)
+                        } else {
+                            (add(point, o1), add(point, o2), point)
+                        };
+                        let (outer1, outer2) = (sub(point, o1), sub(point, o2));
+                        // the bevel fills the gap on the outer side only
+                        self.triangle(pivot, outer1, outer2, color);
+                        if side > 0.0 {
+                            incoming.push((inner1, outer1));
+                            outgoing.push((inner2, outer2));
+                        } else {
+                            incoming.push((outer1, inner1));
+                            outgoing.push((outer2, inner2));
+                        }
+                    }
+                }
+                (Some(previous), None) => {
+                    let n = normal(previous, point);
+                    let offset = [n[0] * half, n[1] * half];
+                    let sides = (add(point, offset), sub(point, offset));
+                    incoming.push(sides);
+                    outgoing.push(sides);
+                }
+                (None, Some(next)) => {
+                    let n = normal(point, next);
+                    let offset = [n[0] * half, n[1] * half];
+                    let sides = (add(point, offset), sub(point, offset));
+                    incoming.push(sides);
+                    outgoing.push(sides);
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+
+        let segments = if closed { count } else { count - 1 };
+        for i in 0..segments {
+            let j = (i + 1) % count;
+            let (left0, right0) = outgoing[i];
+            let (left1, right1) = incoming[j];
+            self.quad(left0, left1, right1, right0, color);
+        }
+    }
+}
+
+/// unit normal of the segment `a b`, to its left in a y down space
+fn normal(a: Point, b: Point) -> Point {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length = (dx * dx + dy * dy).sqrt();
+    [-dy / length, dx / length]
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    (dx * dx + dy * dy).sqrt()
+}
+
+fn add(a: Point, b: Point) -> Point {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+/// segments of a whole circle staying within `TOLERANCE` of it
+pub fn circle_segments(radius: f32) -> usize {
+    if radius <= TOLERANCE {
+        return 8;
+    }
+    let step = 2.0 * (1.0 - TOLERANCE / radius).acos();
+    let segments = (2.0 * PI / step).ceil() as usize;
+    segments.max(8).min(256)
+}
+
+/// `segments + 1` points from angle `start` to `end`, clockwise on screen
+fn arc(centre: Point, radius: f32, start: f32, end: f32, segments: usize) -> Vec<Point> {
+    (0..segments + 1)
+        .map(|i| {
+            let angle = start + (end - start) * i as f32 / segments as f32;
+            [centre[0] + radius * angle.cos(), centre[1] + radius * angle.sin()]
+        })
+        .collect()
+}
+
+/// the outline of a whole circle, without repeating the first point: the
+/// one computed at 2π isn't exactly it
+fn circle(centre: Point, radius: f32) -> Vec<Point> {
+    let mut outline = arc(centre, radius, 0.0, 2.0 * PI, circle_segments(radius));
+    outline.pop();
+    outline
+}
+
+fn rounded_rect(origin: Point, size: [f32; 2], radius: f32) -> Vec<Point> {
+    let radius = radius.max(0.0).min(size[0].min(size[1]) * 0.5);
+    let (x0, y0) = (origin[0] + radius, origin[1] + radius);
+    let (x1, y1) = (origin[0] + size[0] - radius, origin[1] + size[1] - radius);
+    let segments = ((circle_segments(radius) + 3) / 4).max(2);
+    let mut outline = Vec::with_capacity(4 * (segments + 1));
+    // clockwise from the top left corner
+    outline.extend(arc([x0, y0], radius, PI, 1.5 * PI, segments));
+    outline.extend(arc([x1, y0], radius, 1.5 * PI, 2.0 * PI, segments));
+    outline.extend(arc([x1, y1], radius, 0.0, 0.5 * PI, segments));
+    outline.extend(arc([x0, y1], radius, 0.5 * PI, PI, segments));
+    outline
+}
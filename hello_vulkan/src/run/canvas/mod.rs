@@ -0,0 +1,91 @@
+//! Immediate-mode 2D drawing
+//!
+//! Shapes are tessellated into a `Batch` on the CPU, in pixels with the
+//! origin at the top left of the upright content, then uploaded to a
+//! vertex buffer from a pool and drawn in one call:
+//!
+//! ```ignore
+//! let mut canvas = Canvas2d::new(&renderer)?;
+//! let mut batch = Batch::new();
+//! loop {
+//!     let mut frame = renderer.begin_frame()?;
+//!     renderer.begin_overlay(&mut frame)?;
+//!     batch.fill_rounded_rect([20.0, 20.0], [200.0, 80.0], 12.0, [0.1, 0.1, 0.1, 0.8]);
+//!     batch.line([20.0, 120.0], [220.0, 120.0], 2.0, [1.0, 1.0, 1.0, 1.0]);
+//!     canvas.draw(&renderer, &mut frame, &batch)?;
+//!     batch.clear();
+//!     renderer.end_frame(frame)?;
+//! }
+//! ```
+//!
+//! Shapes are blended over the frame in order, without anti-aliasing.
+//! Drawn after `Renderer::begin_overlay` they skip the post-processing.
+
+use std::sync::Arc;
+
+use vulkano::buffer::{BufferAccess, CpuBufferPool};
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+
+use super::pipeline_cache::{Blend, CachedPipeline};
+use super::renderer::{Frame, RenderError, Renderer};
+use super::shaders::ColorVertex;
+use super::shaders::canvas_fragment_shader::Shader as FragmentShader;
+use super::shaders::canvas_vertex_shader::Shader as VertexShader;
+use super::shaders::canvas_vertex_shader::ty::CanvasTransform;
+
+mod batch;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::batch::{circle_segments, Batch, Color, Point};
+
+pub struct Canvas2d {
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    /// recycles the buffers of the frames the GPU is done with
+    pool: CpuBufferPool<ColorVertex>,
+}
+
+impl Canvas2d {
+    pub fn new(renderer: &Renderer) -> Result<Canvas2d, RenderError> {
+        let device = renderer.vulkan_obj().device.clone();
+        let vertex_shader = VertexShader::load(device.clone())?;
+        let fragment_shader = FragmentShader::load(device.clone())?;
+
+        let pipeline = CachedPipeline::new(
+            renderer.vulkan_obj(),
+            SingleBufferDefinition::<ColorVertex>::new(),
+            vertex_shader.main_entry_point(),
+            fragment_shader.main_entry_point(),
+            Blend::Alpha,
+            renderer.subpass(),
+        )?;
+
+        Ok(Canvas2d {
+            pipeline: Arc::new(pipeline),
+            pool: CpuBufferPool::vertex_buffer(device),
+        })
+    }
+
+    /// records `batch`, it can be cleared right away
+    pub fn draw(&self, renderer: &Renderer, frame: &mut Frame, batch: &Batch) -> Result<(), RenderError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let vertices = self.pool.chunk(batch.vertices().iter().cloned())?;
+        let size = renderer.content_dimensions();
+        let transform = CanvasTransform {
+            matrix: renderer.pre_rotation().matrix,
+            size: [size[0] as f32, size[1] as f32],
+        };
+        renderer.draw(
+            frame,
+            self.pipeline.clone(),
+            vec![Arc::new(vertices) as Arc<BufferAccess + Send + Sync>],
+            (),
+            transform,
+        )
+    }
+}
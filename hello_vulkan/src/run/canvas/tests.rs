@@ -0,0 +1,138 @@
+use super::{circle_segments, Batch};
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+fn positions(batch: &Batch) -> Vec<[f32; 2]> {
+    batch.vertices().iter().map(|vertex| vertex.position).collect()
+}
+
+/// sum of the triangle areas, in square pixels
+fn area(batch: &Batch) -> f32 {
+    positions(batch)
+        .chunks(3)
+        .map(|t| {
+            ((t[1][0] - t[0][0]) * (t[2][1] - t[0][1]) - (t[2][0] - t[0][0]) * (t[1][1] - t[0][1])).abs() * 0.5
+        })
+        .sum()
+}
+
+#[test]
+fn filled_rect() {
+    let mut batch = Batch::new();
+    batch.fill_rect([10.0, 20.0], [30.0, 40.0], WHITE);
+    assert_eq!(batch.vertices().len(), 6);
+    assert_eq!(area(&batch), 1200.0);
+    assert!(positions(&batch).contains(&[40.0, 60.0]));
+
+    batch.clear();
+    assert!(batch.is_empty());
+}
+
+#[test]
+fn stroked_rect_has_square_corners() {
+    let mut batch = Batch::new();
+    batch.stroke_rect([0.0, 0.0], [10.0, 10.0], 2.0, WHITE);
+    // 12x12 outside, 8x8 inside
+    assert!((area(&batch) - 80.0).abs() < 1e-3);
+    let near = |target: [f32; 2]| {
+        positions(&batch)
+            .iter()
+            .any(|p| (p[0] - target[0]).abs() < 1e-4 && (p[1] - target[1]).abs() < 1e-4)
+    };
+    assert!(near([-1.0, -1.0]) && near([1.0, 1.0]));
+}
+
+#[test]
+fn sharp_joins_are_bevelled() {
+    let mut batch = Batch::new();
+    batch.polyline(&[[0.0, 0.0], [100.0, 0.0], [0.0, 1.0]], 2.0, WHITE);
+    let reach = positions(&batch).iter().map(|p| p[0]).fold(0.0, f32::max);
+    assert!(reach < 102.0, "miter reaches {}", reach);
+}
+
+#[test]
+fn degenerate_lines() {
+    let mut batch = Batch::new();
+    batch.line([5.0, 5.0], [5.0, 5.0], 2.0, WHITE);
+    batch.polyline(&[[1.0, 1.0]], 2.0, WHITE);
+    assert!(batch.is_empty());
+
+    batch.line([0.0, 0.0], [10.0, 0.0], 2.0, WHITE);
+    assert_eq!(area(&batch), 20.0);
+}
+
+#[test]
+fn circles_follow_the_tolerance() {
+    assert_eq!(circle_segments(0.1), 8);
+    assert!(circle_segments(10.0) < circle_segments(100.0));
+    assert_eq!(circle_segments(1.0e6), 256);
+
+    let mut batch = Batch::new();
+    batch.fill_circle([0.0, 0.0], 50.0, WHITE);
+    let exact = ::std::f32::consts::PI * 2500.0;
+    assert!(area(&batch) < exact && area(&batch) > exact * 0.99);
+}
+
+#[test]
+fn rounded_rect_radius_is_clamped() {
+    let mut batch = Batch::new();
+    batch.fill_rounded_rect([0.0, 0.0], [20.0, 10.0], 100.0, WHITE);
+    let points = positions(&batch);
+    assert!(points.iter().all(|p| p[0] >= -1e-4 && p[0] <= 20.0 + 1e-4 && p[1] >= -1e-4 && p[1] <= 10.0 + 1e-4));
+    // a stadium: a 10x10 square and a circle of radius 5
+    let exact = 100.0 + ::std::f32::consts::PI * 25.0;
+    assert!(area(&batch) < exact && area(&batch) > exact * 0.97);
+}
+
+/// most triangles covering one of the sample points, spread over the
+/// bounds so they don't fall on the shared edges
+fn coverage(batch: &Batch) -> usize {
+    let points = positions(batch);
+    let (mut min, mut max) = ([::std::f32::MAX; 2], [::std::f32::MIN; 2]);
+    for p in &points {
+        min = [min[0].min(p[0]), min[1].min(p[1])];
+        max = [max[0].max(p[0]), max[1].max(p[1])];
+    }
+    let cross = |a: [f32; 2], b: [f32; 2], p: [f32; 2]| (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0]);
+    let mut most = 0;
+    let mut y = min[1] + 0.013;
+    while y < max[1] {
+        let mut x = min[0] + 0.017;
+        while x < max[0] {
+            let p = [x, y];
+            let inside = points
+                .chunks(3)
+                .filter(|t| {
+                    let sides = [cross(t[0], t[1], p), cross(t[1], t[2], p), cross(t[2], t[0], p)];
+                    sides.iter().all(|&s| s > 1e-3) || sides.iter().all(|&s| s < -1e-3)
+                })
+                .count();
+            most = most.max(inside);
+            x += 0.37;
+        }
+        y += 0.37;
+    }
+    most
+}
+
+#[test]
+fn stroked_circle_is_closed() {
+    let mut batch = Batch::new();
+    batch.stroke_circle([0.0, 0.0], 50.0, 4.0, WHITE);
+    let segments = circle_segments(50.0);
+    // a quad per segment, none of them degenerate
+    assert_eq!(batch.vertices().len(), 6 * segments);
+    // the strip between the offset polygons: width times the middle perimeter
+    let n = segments as f32;
+    let exact = 4.0 * 2.0 * n * 50.0 * (::std::f32::consts::PI / n).sin();
+    assert!((area(&batch) - exact).abs() < exact * 1e-3);
+    assert_eq!(coverage(&batch), 1);
+}
+
+#[test]
+fn bevelled_joins_dont_overlap() {
+    let mut batch = Batch::new();
+    batch.polyline(&[[0.0, 0.0], [100.0, 0.0], [0.0, 20.0]], 2.0, WHITE);
+    batch.polyline(&[[0.0, 50.0], [100.0, 50.0], [0.0, 30.0]], 2.0, WHITE);
+    assert_eq!(coverage(&batch), 1);
+}
@@ -0,0 +1,20 @@
+#version 450
+// in pixels, origin at the top left
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 v_color;
+
+layout(push_constant) uniform CanvasTransform {
+    // column major 2x2 matrix, the pre-rotation of `PreRotation`
+    vec4 matrix;
+    // size of the upright content in pixels
+    vec2 size;
+} transform;
+
+void main() {
+    mat2 m = mat2(transform.matrix.xy, transform.matrix.zw);
+    vec2 clip = position / transform.size * 2.0 - 1.0;
+    gl_Position = vec4(m * clip, 0.0, 1.0);
+    v_color = color;
+}
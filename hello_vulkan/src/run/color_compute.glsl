@@ -0,0 +1,20 @@
+#version 450
+layout(local_size_x = 16, local_size_y = 16) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D source;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D destination;
+
+layout(push_constant) uniform ColorMatrix {
+    mat4 matrix;
+    vec4 offset;
+} color;
+
+void main() {
+    ivec2 size = imageSize(source);
+    ivec2 p = ivec2(gl_GlobalInvocationID.xy);
+    if (p.x >= size.x || p.y >= size.y) {
+        return;
+    }
+
+    imageStore(destination, p, color.matrix * imageLoad(source, p) + color.offset);
+}
@@ -0,0 +1,150 @@
+//! A chain of `filter::Filter`s recorded into one command buffer
+
+use std::sync::Arc;
+
+use image::RgbaImage;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::pipeline_layout::PipelineLayout;
+use vulkano::format::Format;
+use vulkano::image::StorageImage;
+use vulkano::pipeline::ComputePipeline;
+
+use filter::{ColorMatrix, Filter};
+use super::{storage_image, submit, workgroups, GaussianBlur};
+use super::super::VulkanStruct;
+use super::super::renderer::RenderError;
+use super::super::shaders::color_compute_shader;
+use super::super::shaders::convolve_compute_shader;
+use super::super::shaders::resize_compute_shader;
+use super::super::shaders::threshold_compute_shader;
+
+type Pipeline<L> = Arc<ComputePipeline<PipelineLayout<L>>>;
+
+/// runs filters on RGBA8 images, keeping the intermediate results on the
+/// device
+pub struct FilterChain {
+    vulkan_obj: Arc<VulkanStruct>,
+    resize: Pipeline<resize_compute_shader::Layout>,
+    color: Pipeline<color_compute_shader::Layout>,
+    convolve: Pipeline<convolve_compute_shader::Layout>,
+    threshold: Pipeline<threshold_compute_shader::Layout>,
+    blur: GaussianBlur,
+}
+
+impl FilterChain {
+    pub fn new(vulkan_obj: Arc<VulkanStruct>) -> Result<FilterChain, RenderError> {
+        let device = &vulkan_obj.device;
+        let resize = resize_compute_shader::Shader::load(device.clone())?;
+        let color = color_compute_shader::Shader::load(device.clone())?;
+        let convolve = convolve_compute_shader::Shader::load(device.clone())?;
+        let threshold = threshold_compute_shader::Shader::load(device.clone())?;
+        Ok(FilterChain {
+            resize: Arc::new(ComputePipeline::new(device.clone(), &resize.main_entry_point(), &())?),
+            color: Arc::new(ComputePipeline::new(device.clone(), &color.main_entry_point(), &())?),
+            convolve: Arc::new(ComputePipeline::new(device.clone(), &convolve.main_entry_point(), &())?),
+            threshold: Arc::new(ComputePipeline::new(device.clone(), &threshold.main_entry_point(), &())?),
+            blur: GaussianBlur::new(vulkan_obj.clone())?,
+            vulkan_obj: vulkan_obj,
+        })
+    }
+
+    /// `filters` in order; the image is uploaded and read back once
+    pub fn apply(&self, image: &RgbaImage, filters: &[Filter]) -> Result<RgbaImage, RenderError> {
+        let device = &self.vulkan_obj.device;
+        let mut size = [image.width(), image.height()];
+
+        let upload = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::transfer_source(),
+            image.iter().cloned(),
+        )?;
+        let mut current = storage_image(&self.vulkan_obj, size)?;
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            device.clone(),
+            self.vulkan_obj.compute_queue.family(),
+        )?.copy_buffer_to_image(upload, current.clone())?;
+
+        for filter in filters {
+            let output_size = filter.output_size(size);
+            let destination = storage_image(&self.vulkan_obj, output_size)?;
+            builder = self.record(builder, filter, current, destination.clone(), output_size)?;
+            current = destination;
+            size = output_size;
+        }
+
+        let readback = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::transfer_destination(),
+            (0..size[0] * size[1] * 4).map(|_| 0u8),
+        )?;
+        let command_buffer = builder
+            .copy_image_to_buffer(current, readback.clone())?
+            .build()?;
+        submit(&self.vulkan_obj, command_buffer)?;
+
+        let pixels = readback.read().expect("failed to map readback buffer");
+        Ok(RgbaImage::from_raw(size[0], size[1], pixels.to_vec()).expect("readback has the size of the image"))
+    }
+
+    /// `size` is the one of `destination`
+    fn record(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        filter: &Filter,
+        source: Arc<StorageImage<Format>>,
+        destination: Arc<StorageImage<Format>>,
+        size: [u32; 2],
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let groups = workgroups(size);
+        Ok(match *filter {
+            Filter::Resize { .. } => {
+                let set = PersistentDescriptorSet::start(self.resize.clone(), 0)
+                    .add_image(source)?
+                    .add_image(destination)?
+                    .build()?;
+                builder.dispatch(groups, self.resize.clone(), set, ())?
+            }
+            Filter::Convert(ColorMatrix { columns, offset }) => {
+                let set = PersistentDescriptorSet::start(self.color.clone(), 0)
+                    .add_image(source)?
+                    .add_image(destination)?
+                    .build()?;
+                let constants = color_compute_shader::ty::ColorMatrix {
+                    matrix: columns,
+                    offset: offset,
+                };
+                builder.dispatch(groups, self.color.clone(), set, constants)?
+            }
+            Filter::Convolve { size: kernel_size, ref weights } => {
+                let weights = CpuAccessibleBuffer::from_iter(
+                    self.vulkan_obj.device.clone(),
+                    BufferUsage {
+                        storage_buffer: true,
+                        ..BufferUsage::none()
+                    },
+                    weights.iter().cloned(),
+                )?;
+                let set = PersistentDescriptorSet::start(self.convolve.clone(), 0)
+                    .add_image(source)?
+                    .add_image(destination)?
+                    .add_buffer(weights)?
+                    .build()?;
+                let constants = convolve_compute_shader::ty::Convolution {
+                    size: kernel_size as i32,
+                };
+                builder.dispatch(groups, self.convolve.clone(), set, constants)?
+            }
+            Filter::Threshold(level) => {
+                let set = PersistentDescriptorSet::start(self.threshold.clone(), 0)
+                    .add_image(source)?
+                    .add_image(destination)?
+                    .build()?;
+                let constants = threshold_compute_shader::ty::Threshold { level: level };
+                builder.dispatch(groups, self.threshold.clone(), set, constants)?
+            }
+            Filter::Blur(radius) => self.blur.record(builder, source, destination, size, radius)?,
+        })
+    }
+}
@@ -0,0 +1,241 @@
+//! Compute pipelines from SPIR-V read at runtime
+//!
+//! The build time kernels get their pipeline layout from the shader
+//! derive; a module loaded at runtime isn't reflected, its layout is
+//! declared instead: the bindings of set 0, in order, and the size of its
+//! push constants.
+//!
+//! ```ignore
+//! let spirv = fs::read("invert.spv")?;
+//! // the module must be valid and match the layout
+//! let kernel = unsafe { Kernel::from_spirv(vulkan_obj, &spirv, "main", KernelLayout::image_filter())? };
+//! let inverted = kernel.apply(&image::open("in.png")?.to_rgba())?;
+//! ```
+
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use image::RgbaImage;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor::descriptor::{DescriptorBufferDesc, DescriptorDesc, DescriptorDescTy, DescriptorImageDesc,
+                                      DescriptorImageDescArray, DescriptorImageDescDimensions, ShaderStages};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::pipeline_layout::{PipelineLayout, PipelineLayoutDesc, PipelineLayoutDescPcRange};
+use vulkano::pipeline::ComputePipeline;
+use vulkano::pipeline::shader::{ComputeEntryPoint, ShaderModule};
+
+use super::{storage_image, submit, workgroups};
+use super::super::VulkanStruct;
+use super::super::renderer::RenderError;
+
+pub const SPIRV_MAGIC: u32 = 0x0723_0203;
+/// magic, version, generator, bound and schema
+const HEADER_WORDS: usize = 5;
+
+/// a descriptor of set 0
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Binding {
+    StorageImage,
+    StorageBuffer,
+}
+
+/// what a runtime kernel binds, it can't be checked against the module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelLayout {
+    pub bindings: Vec<Binding>,
+    /// bytes, 0 without push constants
+    pub push_constants: usize,
+}
+
+pub type KernelPipeline = ComputePipeline<PipelineLayout<KernelLayout>>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SpirvError {
+    /// not whole 32 bit words, or shorter than the header
+    Length(usize),
+    /// the first word, e.g. the magic number of the other endianness
+    Magic(u32),
+    /// the entry point name holds a nul byte
+    EntryPoint(String),
+}
+
+/// a kernel loaded at runtime
+pub struct Kernel {
+    vulkan_obj: Arc<VulkanStruct>,
+    pipeline: Arc<KernelPipeline>,
+}
+
+impl KernelLayout {
+    /// reads the storage image of binding 0 and writes the one of
+    /// binding 1, both RGBA8 and of the same size, see `Kernel::apply`
+    pub fn image_filter() -> KernelLayout {
+        KernelLayout {
+            bindings: vec![Binding::StorageImage, Binding::StorageImage],
+            push_constants: 0,
+        }
+    }
+}
+
+unsafe impl PipelineLayoutDesc for KernelLayout {
+    fn num_sets(&self) -> usize {
+        if self.bindings.is_empty() {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        match set {
+            0 if !self.bindings.is_empty() => Some(self.bindings.len()),
+            _ => None,
+        }
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        if set != 0 {
+            return None;
+        }
+        let ty = match *self.bindings.get(binding)? {
+            Binding::StorageImage => DescriptorDescTy::Image(DescriptorImageDesc {
+                sampled: false,
+                dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                format: None,
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            }),
+            Binding::StorageBuffer => DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(false),
+                storage: true,
+            }),
+        };
+        Some(DescriptorDesc {
+            ty: ty,
+            array_count: 1,
+            stages: ShaderStages::compute(),
+            // unknown, writes are assumed
+            readonly: false,
+        })
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        if self.push_constants == 0 {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn push_constants_range(&self, num: usize) -> Option<PipelineLayoutDescPcRange> {
+        if num != 0 || self.push_constants == 0 {
+            return None;
+        }
+        Some(PipelineLayoutDescPcRange {
+            offset: 0,
+            size: self.push_constants,
+            stages: ShaderStages::compute(),
+        })
+    }
+}
+
+/// the words of a SPIR-V module in native byte order, once its length
+/// and magic number look right
+pub fn spirv_words(bytes: &[u8]) -> Result<Vec<u32>, SpirvError> {
+    if bytes.len() % 4 != 0 || bytes.len() < HEADER_WORDS * 4 {
+        return Err(SpirvError::Length(bytes.len()));
+    }
+    let words: Vec<u32> = bytes
+        .chunks(4)
+        .map(|word| unsafe { ptr::read_unaligned(word.as_ptr() as *const u32) })
+        .collect();
+    if words[0] != SPIRV_MAGIC {
+        return Err(SpirvError::Magic(words[0]));
+    }
+    Ok(words)
+}
+
+impl Kernel {
+    /// only the header of `spirv` is checked: the module must be valid,
+    /// have a compute `entry_point` and declare what `layout` does
+    pub unsafe fn from_spirv(
+        vulkan_obj: Arc<VulkanStruct>,
+        spirv: &[u8],
+        entry_point: &str,
+        layout: KernelLayout,
+    ) -> Result<Kernel, RenderError> {
+        // copied into words, `spirv` may not be aligned for them
+        let words = spirv_words(spirv)?;
+        let name = CString::new(entry_point).map_err(|_| SpirvError::EntryPoint(entry_point.to_owned()))?;
+        let bytes = slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 4);
+        let module = ShaderModule::new(vulkan_obj.device.clone(), bytes)?;
+        let entry_point: ComputeEntryPoint<(), KernelLayout> = module.compute_entry_point(&name, layout);
+        let pipeline = ComputePipeline::new(vulkan_obj.device.clone(), &entry_point, &())?;
+        Ok(Kernel {
+            vulkan_obj: vulkan_obj,
+            pipeline: Arc::new(pipeline),
+        })
+    }
+
+    /// to bind the descriptor sets of any layout and dispatch it
+    pub fn pipeline(&self) -> &Arc<KernelPipeline> {
+        &self.pipeline
+    }
+
+    /// runs a `KernelLayout::image_filter` kernel over `image`, in
+    /// groups of `LOCAL_SIZE`
+    pub fn apply(&self, image: &RgbaImage) -> Result<RgbaImage, RenderError> {
+        let (width, height) = image.dimensions();
+        let device = &self.vulkan_obj.device;
+
+        let upload = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::transfer_source(),
+            image.iter().cloned(),
+        )?;
+        let readback = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::transfer_destination(),
+            (0..width * height * 4).map(|_| 0u8),
+        )?;
+        let source = storage_image(&self.vulkan_obj, [width, height])?;
+        let destination = storage_image(&self.vulkan_obj, [width, height])?;
+        let set = PersistentDescriptorSet::start(self.pipeline.clone(), 0)
+            .add_image(source.clone())?
+            .add_image(destination.clone())?
+            .build()?;
+
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            device.clone(),
+            self.vulkan_obj.compute_queue.family(),
+        )?.copy_buffer_to_image(upload, source)?;
+        let command_buffer = builder
+            .dispatch(workgroups([width, height]), self.pipeline.clone(), set, ())?
+            .copy_image_to_buffer(destination, readback.clone())?
+            .build()?;
+        submit(&self.vulkan_obj, command_buffer)?;
+
+        let pixels = readback.read().expect("failed to map readback buffer");
+        Ok(RgbaImage::from_raw(width, height, pixels.to_vec()).expect("readback has the size of the image"))
+    }
+}
+
+impl fmt::Display for SpirvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpirvError::Length(len) => write!(f, "{} bytes isn't a SPIR-V module", len),
+            SpirvError::Magic(word) => write!(f, "not SPIR-V, or of another endianness: starts with {:#010x}", word),
+            SpirvError::EntryPoint(ref name) => write!(f, "invalid entry point name {:?}", name),
+        }
+    }
+}
+
+impl Error for SpirvError {
+    fn description(&self) -> &str {
+        "invalid SPIR-V module"
+    }
+}
@@ -0,0 +1,196 @@
+//! Compute pipelines, dispatched on `VulkanStruct::compute_queue`
+//!
+//! Kernels are GLSL compute shaders compiled to SPIR-V at build time, see
+//! `shaders`, or SPIR-V modules loaded at runtime, see `kernel`. Their
+//! inputs and outputs are storage images and storage buffers bound
+//! through descriptor sets; `submit` waits for the kernel, the results
+//! are then read back from host visible buffers:
+//!
+//! ```ignore
+//! let blur = GaussianBlur::new(vulkan_obj)?;
+//! let blurred = blur.apply(&image::open("in.png")?.to_rgba(), 8)?;
+//! blurred.save("out.png")?;
+//! ```
+
+use std::sync::Arc;
+
+use image::RgbaImage;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::pipeline_layout::PipelineLayout;
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageUsage, StorageImage};
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::{now, GpuFuture};
+
+use filter::{gaussian_weights, MAX_BLUR_RADIUS};
+use super::VulkanStruct;
+use super::renderer::RenderError;
+use super::watchdog::{wait_fence, Stage};
+use super::shaders::gaussian_compute_shader::{Layout, Shader};
+use super::shaders::gaussian_compute_shader::ty::BlurPass;
+
+mod filters;
+pub mod kernel;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::filters::FilterChain;
+pub use self::kernel::{Kernel, KernelLayout};
+
+/// `local_size_x` and `local_size_y` of the kernels
+pub const LOCAL_SIZE: [u32; 2] = [16, 16];
+
+type GaussianPipeline = ComputePipeline<PipelineLayout<Layout>>;
+
+/// separable gaussian blur of RGBA8 images
+pub struct GaussianBlur {
+    vulkan_obj: Arc<VulkanStruct>,
+    pipeline: Arc<GaussianPipeline>,
+}
+
+impl GaussianBlur {
+    pub fn new(vulkan_obj: Arc<VulkanStruct>) -> Result<GaussianBlur, RenderError> {
+        let shader = Shader::load(vulkan_obj.device.clone())?;
+        let pipeline = ComputePipeline::new(vulkan_obj.device.clone(), &shader.main_entry_point(), &())?;
+        Ok(GaussianBlur {
+            vulkan_obj: vulkan_obj,
+            pipeline: Arc::new(pipeline),
+        })
+    }
+
+    /// `radius` is where the kernel reaches three standard deviations,
+    /// at most `MAX_BLUR_RADIUS`
+    pub fn apply(&self, image: &RgbaImage, radius: u32) -> Result<RgbaImage, RenderError> {
+        let (width, height) = image.dimensions();
+        let device = &self.vulkan_obj.device;
+
+        let upload = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::transfer_source(),
+            image.iter().cloned(),
+        )?;
+        let readback = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::transfer_destination(),
+            (0..width * height * 4).map(|_| 0u8),
+        )?;
+        let source = storage_image(&self.vulkan_obj, [width, height])?;
+        let destination = storage_image(&self.vulkan_obj, [width, height])?;
+
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            device.clone(),
+            self.vulkan_obj.compute_queue.family(),
+        )?.copy_buffer_to_image(upload, source.clone())?;
+        let command_buffer = self.record(builder, source, destination.clone(), [width, height], radius)?
+            .copy_image_to_buffer(destination, readback.clone())?
+            .build()?;
+        submit(&self.vulkan_obj, command_buffer)?;
+
+        let pixels = readback.read().expect("failed to map readback buffer");
+        Ok(RgbaImage::from_raw(width, height, pixels.to_vec()).expect("readback has the size of the image"))
+    }
+
+    /// adds both passes from `source` to `destination`, both of `size`
+    pub fn record(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        source: Arc<StorageImage<Format>>,
+        destination: Arc<StorageImage<Format>>,
+        size: [u32; 2],
+        radius: u32,
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let radius = radius.min(MAX_BLUR_RADIUS);
+        let weights = CpuAccessibleBuffer::from_iter(
+            self.vulkan_obj.device.clone(),
+            BufferUsage {
+                storage_buffer: true,
+                ..BufferUsage::none()
+            },
+            gaussian_weights(radius).into_iter(),
+        )?;
+        let temporary = storage_image(&self.vulkan_obj, size)?;
+
+        let horizontal = PersistentDescriptorSet::start(self.pipeline.clone(), 0)
+            .add_image(source)?
+            .add_image(temporary.clone())?
+            .add_buffer(weights.clone())?
+            .build()?;
+        let vertical = PersistentDescriptorSet::start(self.pipeline.clone(), 0)
+            .add_image(temporary)?
+            .add_image(destination)?
+            .add_buffer(weights)?
+            .build()?;
+
+        let groups = workgroups(size);
+        Ok(builder
+            .dispatch(
+                groups,
+                self.pipeline.clone(),
+                horizontal,
+                BlurPass {
+                    direction: [1, 0],
+                    radius: radius as i32,
+                },
+            )?
+            .dispatch(
+                groups,
+                self.pipeline.clone(),
+                vertical,
+                BlurPass {
+                    direction: [0, 1],
+                    radius: radius as i32,
+                },
+            )?)
+    }
+}
+
+/// RGBA8 image the kernels can read and write, and that can be copied
+/// from and to buffers
+pub fn storage_image(vulkan_obj: &VulkanStruct, size: [u32; 2]) -> Result<Arc<StorageImage<Format>>, RenderError> {
+    let usage = ImageUsage {
+        storage: true,
+        transfer_source: true,
+        transfer_destination: true,
+        ..ImageUsage::none()
+    };
+    Ok(StorageImage::with_usage(
+        vulkan_obj.device.clone(),
+        Dimensions::Dim2d {
+            width: size[0],
+            height: size[1],
+        },
+        Format::R8G8B8A8Unorm,
+        usage,
+        Some(vulkan_obj.compute_queue.family()),
+    )?)
+}
+
+/// runs `command_buffer` on the compute queue and waits for it, at most
+/// `timeouts.fence`
+pub fn submit(vulkan_obj: &VulkanStruct, command_buffer: AutoCommandBuffer) -> Result<(), RenderError> {
+    // the flush has no timeout, the watchdog reports it if it blocks
+    vulkan_obj.enter_stage(Stage::Compute);
+    let done = execute(vulkan_obj, command_buffer);
+    vulkan_obj.leave_stage();
+    done
+}
+
+fn execute(vulkan_obj: &VulkanStruct, command_buffer: AutoCommandBuffer) -> Result<(), RenderError> {
+    let fence = now(vulkan_obj.device.clone())
+        .then_execute(vulkan_obj.compute_queue.clone(), command_buffer)?
+        .then_signal_fence_and_flush()?;
+    wait_fence(fence, vulkan_obj.timeouts.fence, Stage::Compute)?;
+    Ok(())
+}
+
+/// groups of `LOCAL_SIZE` covering `size` pixels
+pub fn workgroups(size: [u32; 2]) -> [u32; 3] {
+    [
+        (size[0] + LOCAL_SIZE[0] - 1) / LOCAL_SIZE[0],
+        (size[1] + LOCAL_SIZE[1] - 1) / LOCAL_SIZE[1],
+        1,
+    ]
+}
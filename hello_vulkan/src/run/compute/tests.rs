@@ -0,0 +1,51 @@
+use std::slice;
+
+use vulkano::descriptor::pipeline_layout::PipelineLayoutDesc;
+
+use super::kernel::{spirv_words, Binding, KernelLayout, SpirvError, SPIRV_MAGIC};
+use super::workgroups;
+
+/// `words` as they are stored in a module of this machine
+fn module(words: &[u32]) -> Vec<u8> {
+    unsafe { slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 4).to_vec() }
+}
+
+#[test]
+fn workgroups_cover_the_image() {
+    assert_eq!(workgroups([16, 16]), [1, 1, 1]);
+    assert_eq!(workgroups([17, 1]), [2, 1, 1]);
+    assert_eq!(workgroups([1920, 1080]), [120, 68, 1]);
+}
+
+#[test]
+fn spirv_header_is_checked() {
+    let header = [SPIRV_MAGIC, 0x0001_0000, 0, 8, 0];
+    assert_eq!(spirv_words(&module(&header)), Ok(header.to_vec()));
+
+    let mut truncated = module(&header);
+    truncated.push(0);
+    assert_eq!(spirv_words(&truncated), Err(SpirvError::Length(21)));
+    assert_eq!(spirv_words(&module(&header[..4])), Err(SpirvError::Length(16)));
+
+    let swapped = [SPIRV_MAGIC.swap_bytes(), 0, 0, 0, 0];
+    assert_eq!(spirv_words(&module(&swapped)), Err(SpirvError::Magic(0x0302_2307)));
+}
+
+#[test]
+fn kernel_layout_declares_set_0() {
+    let layout = KernelLayout {
+        bindings: vec![Binding::StorageImage, Binding::StorageBuffer],
+        push_constants: 8,
+    };
+    assert_eq!(layout.num_sets(), 1);
+    assert_eq!(layout.num_bindings_in_set(0), Some(2));
+    assert_eq!(layout.num_bindings_in_set(1), None);
+    assert!(layout.descriptor(0, 1).is_some());
+    assert!(layout.descriptor(0, 2).is_none());
+    assert_eq!(layout.num_push_constants_ranges(), 1);
+    assert_eq!(layout.push_constants_range(0).map(|range| range.size), Some(8));
+
+    let filter = KernelLayout::image_filter();
+    assert_eq!(filter.num_push_constants_ranges(), 0);
+    assert!(filter.push_constants_range(0).is_none());
+}
@@ -0,0 +1,32 @@
+#version 450
+layout(local_size_x = 16, local_size_y = 16) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D source;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D destination;
+// size * size weights, row by row
+layout(set = 0, binding = 2) readonly buffer Weights {
+    float weights[];
+} kernel;
+
+layout(push_constant) uniform Convolution {
+    int size;
+} convolution;
+
+// on the colour only, the alpha of the centre is kept
+void main() {
+    ivec2 size = imageSize(source);
+    ivec2 p = ivec2(gl_GlobalInvocationID.xy);
+    if (p.x >= size.x || p.y >= size.y) {
+        return;
+    }
+
+    int half_size = convolution.size / 2;
+    vec3 sum = vec3(0.0);
+    for (int y = 0; y < convolution.size; y++) {
+        for (int x = 0; x < convolution.size; x++) {
+            ivec2 q = clamp(p + ivec2(x, y) - half_size, ivec2(0), size - 1);
+            sum += imageLoad(source, q).rgb * kernel.weights[y * convolution.size + x];
+        }
+    }
+    imageStore(destination, p, vec4(sum, imageLoad(source, p).a));
+}
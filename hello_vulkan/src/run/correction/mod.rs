@@ -0,0 +1,355 @@
+//! Colour correction, the last pass of the render graph
+//!
+//! Maps every pixel of the frame through a 3D LUT. The LUT comes from a
+//! `.cube` file or is built from per-channel gamma exponents. It is stored
+//! as RGBA8, which the hardware filters everywhere, so the output keeps 8
+//! bits per channel like the panels.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::format::Format;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, Dimensions, ImmutableImage};
+use vulkano::pipeline::vertex::{BufferlessDefinition, BufferlessVertices};
+use vulkano::sampler::Sampler;
+use vulkano::sync::GpuFuture;
+
+use config::ColorCorrectionConfig;
+use log_targets::INFO;
+
+use super::VulkanStruct;
+use super::graph::{linear_sampler, Effect};
+use super::pipeline_cache::{Blend, CachedPipeline};
+use super::renderer::{BufferlessPipeline, RenderError};
+use super::upload::UploadManager;
+use super::shaders::correction_fragment_shader::Shader as FragmentShader;
+use super::shaders::correction_fragment_shader::ty::CorrectionParams;
+use super::shaders::fullscreen_vertex_shader::Shader as VertexShader;
+
+#[cfg(test)]
+mod tests;
+
+/// entries per axis of the LUT built from gamma exponents
+const GAMMA_LUT_SIZE: usize = 64;
+const LUT_FORMAT: Format = Format::R8G8B8A8Unorm;
+
+/// a 3D LUT, red varies fastest like in `.cube` files
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeLut {
+    pub size: usize,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    pub table: Vec<[f32; 3]>,
+}
+
+/// maps colours to LUT texture coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LutTransform {
+    pub scale: [f32; 4],
+    pub offset: [f32; 4],
+}
+
+#[derive(Debug)]
+pub enum LutError {
+    Io(PathBuf, io::Error),
+    /// `line` counts from 1
+    Parse { line: usize, message: String },
+}
+
+/// what the LUT is built from
+#[derive(Debug, Clone, PartialEq)]
+pub enum CorrectionSource {
+    Gamma([f32; 3]),
+    Lut(PathBuf),
+}
+
+/// the graph effect
+pub struct ColorCorrection {
+    source: CorrectionSource,
+    pipeline: Option<Arc<BufferlessPipeline>>,
+    frame_sampler: Arc<Sampler>,
+    lut_sampler: Arc<Sampler>,
+    lut: Arc<ImmutableImage<Format>>,
+    transform: LutTransform,
+}
+
+impl CubeLut {
+    pub fn load(path: &Path) -> Result<CubeLut, LutError> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|err| LutError::Io(path.to_path_buf(), err))?;
+        CubeLut::parse(&contents)
+    }
+
+    /// parses the Adobe/Resolve `.cube` format, 3D LUTs only
+    pub fn parse(text: &str) -> Result<CubeLut, LutError> {
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or("");
+            match keyword {
+                "TITLE" => (),
+                "LUT_3D_SIZE" => {
+                    let value = words
+                        .next()
+                        .and_then(|word| word.parse::<usize>().ok())
+                        .and_then(|size| if size >= 2 && size <= 256 { Some(size) } else { None })
+                        .ok_or_else(|| LutError::parse(number, "LUT_3D_SIZE must be between 2 and 256"))?;
+                    size = Some(value);
+                }
+                "LUT_1D_SIZE" => return Err(LutError::parse(number, "1D LUTs are not supported")),
+                "DOMAIN_MIN" => domain_min = parse_triplet(words, number)?,
+                "DOMAIN_MAX" => domain_max = parse_triplet(words, number)?,
+                _ => {
+                    if size.is_none() {
+                        return Err(LutError::parse(number, "table before LUT_3D_SIZE"));
+                    }
+                    table.push(parse_triplet(line.split_whitespace(), number)?);
+                }
+            }
+        }
+
+        let size = size.ok_or_else(|| LutError::parse(0, "missing LUT_3D_SIZE"))?;
+        if table.len() != size * size * size {
+            return Err(LutError::parse(
+                0,
+                format!("expected {} entries, found {}", size * size * size, table.len()),
+            ));
+        }
+        if (0..3).any(|i| domain_max[i] <= domain_min[i]) {
+            return Err(LutError::parse(0, "DOMAIN_MAX must be above DOMAIN_MIN"));
+        }
+
+        Ok(CubeLut {
+            size: size,
+            domain_min: domain_min,
+            domain_max: domain_max,
+            table: table,
+        })
+    }
+
+    /// each channel raised to the power `1 / gamma`
+    pub fn gamma(gamma: [f32; 3], size: usize) -> CubeLut {
+        let step = |i: usize| i as f32 / (size - 1) as f32;
+        let mut table = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    table.push([
+                        step(r).powf(1.0 / gamma[0]),
+                        step(g).powf(1.0 / gamma[1]),
+                        step(b).powf(1.0 / gamma[2]),
+                    ]);
+                }
+            }
+        }
+        CubeLut {
+            size: size,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            table: table,
+        }
+    }
+
+    /// maps colours to texture coordinates: the domain to `[0, 1]`, then
+    /// onto the texel centres
+    fn transform(&self) -> LutTransform {
+        let n = self.size as f32;
+        let mut scale = [1.0; 4];
+        let mut offset = [0.0; 4];
+        for i in 0..3 {
+            let extent = self.domain_max[i] - self.domain_min[i];
+            scale[i] = (n - 1.0) / (n * extent);
+            offset[i] = 0.5 / n - self.domain_min[i] * scale[i];
+        }
+        LutTransform {
+            scale: scale,
+            offset: offset,
+        }
+    }
+}
+
+fn parse_triplet<'a, I: Iterator<Item = &'a str>>(words: I, line: usize) -> Result<[f32; 3], LutError> {
+    let values: Vec<f32> = words
+        .map(|word| word.parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| LutError::parse(line, "expected three numbers"))?;
+    if values.len() != 3 {
+        return Err(LutError::parse(line, "expected three numbers"));
+    }
+    Ok([values[0], values[1], values[2]])
+}
+
+impl CorrectionSource {
+    /// `None` when the configuration disables the correction
+    pub fn from_config(config: &ColorCorrectionConfig) -> Option<CorrectionSource> {
+        match (config.gamma, config.lut.as_ref()) {
+            (_, Some(path)) => Some(CorrectionSource::Lut(path.clone())),
+            (Some(gamma), None) => Some(CorrectionSource::Gamma(gamma)),
+            (None, None) => None,
+        }
+    }
+
+    fn load(&self) -> Result<CubeLut, LutError> {
+        match *self {
+            CorrectionSource::Gamma(gamma) => Ok(CubeLut::gamma(gamma, GAMMA_LUT_SIZE)),
+            CorrectionSource::Lut(ref path) => CubeLut::load(path),
+        }
+    }
+}
+
+impl ColorCorrection {
+    /// the LUT upload must complete before the first frame, the returned
+    /// future is chained by the renderer
+    pub fn new(
+        vulkan_obj: &VulkanStruct,
+        uploads: &mut UploadManager,
+        source: CorrectionSource,
+    ) -> Result<(ColorCorrection, Option<Box<GpuFuture>>), RenderError> {
+        let frame_sampler = linear_sampler(&vulkan_obj.device)?;
+        let lut_sampler = linear_sampler(&vulkan_obj.device)?;
+
+        let table = source.load()?;
+        let (lut, future) = upload_lut(uploads, &table)?;
+        info!(target: INFO, "colour correction from {:?}", source);
+
+        let correction = ColorCorrection {
+            source: source,
+            pipeline: None,
+            frame_sampler: frame_sampler,
+            lut_sampler: lut_sampler,
+            lut: lut,
+            transform: table.transform(),
+        };
+        Ok((correction, future))
+    }
+}
+
+impl Effect for ColorCorrection {
+    fn create_pipeline(
+        &mut self,
+        vulkan_obj: &Arc<VulkanStruct>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Result<(), RenderError> {
+        let vertex_shader = VertexShader::load(vulkan_obj.device.clone())?;
+        let fragment_shader = FragmentShader::load(vulkan_obj.device.clone())?;
+        let pipeline = CachedPipeline::new(
+            vulkan_obj,
+            BufferlessDefinition,
+            vertex_shader.main_entry_point(),
+            fragment_shader.main_entry_point(),
+            Blend::Opaque,
+            subpass,
+        )?;
+        self.pipeline = Some(Arc::new(pipeline));
+        Ok(())
+    }
+
+    fn draw(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        inputs: &[Arc<AttachmentImage>],
+        size: [u32; 2],
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let pipeline = self.pipeline.clone().expect("effect drawn before its pipeline was created");
+        let set = PersistentDescriptorSet::start(pipeline.clone(), 0)
+            .add_sampled_image(inputs[0].clone(), self.frame_sampler.clone())?
+            .add_sampled_image(self.lut.clone(), self.lut_sampler.clone())?
+            .build()?;
+        let params = CorrectionParams {
+            lut_scale: self.transform.scale,
+            lut_offset: self.transform.offset,
+            size: [size[0] as f32, size[1] as f32],
+        };
+        let vertices = BufferlessVertices {
+            vertices: 3,
+            instances: 1,
+        };
+        Ok(builder.draw(pipeline, dynamic_state.clone(), vertices, set, params)?)
+    }
+
+    /// re-reads the LUT, the current one stays on failure
+    fn reload(&mut self, uploads: &mut UploadManager) -> Result<Option<Box<GpuFuture>>, RenderError> {
+        let table = self.source.load()?;
+        let (lut, future) = upload_lut(uploads, &table)?;
+        self.lut = lut;
+        self.transform = table.transform();
+        info!(target: INFO, "colour correction reloaded from {:?}", self.source);
+        Ok(future)
+    }
+}
+
+/// on the transfer queue; the future, unless another upload of the batch
+/// took it, is for the next frame, which is the first to sample the LUT
+fn upload_lut(
+    uploads: &mut UploadManager,
+    table: &CubeLut,
+) -> Result<(Arc<ImmutableImage<Format>>, Option<Box<GpuFuture>>), RenderError> {
+    let size = table.size as u32;
+    let to_unorm = |v: f32| (v.max(0.0).min(1.0) * 255.0 + 0.5) as u8;
+    let texels = table
+        .table
+        .iter()
+        .map(|c| [to_unorm(c[0]), to_unorm(c[1]), to_unorm(c[2]), 255u8])
+        .collect::<Vec<_>>();
+    let upload = uploads.image_from_iter(
+        texels.into_iter(),
+        Dimensions::Dim3d {
+            width: size,
+            height: size,
+            depth: size,
+        },
+        LUT_FORMAT,
+    )?;
+    let future = upload.take_future()?;
+    Ok((upload.resource().clone(), future))
+}
+
+impl LutError {
+    fn parse<S: Into<String>>(line: usize, message: S) -> LutError {
+        LutError::Parse {
+            line: line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LutError::Io(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+            LutError::Parse { line: 0, ref message } => write!(f, "invalid LUT: {}", message),
+            LutError::Parse { line, ref message } => {
+                write!(f, "invalid LUT, line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl Error for LutError {
+    fn description(&self) -> &str {
+        match *self {
+            LutError::Io(..) => "failed to read LUT",
+            LutError::Parse { .. } => "invalid LUT",
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use super::{CubeLut, LutError};
+
+const IDENTITY: &str = "# identity
+TITLE \"identity\"
+LUT_3D_SIZE 2
+
+0 0 0
+1 0 0
+0 1 0
+1 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 1
+";
+
+fn parse_error(text: &str) -> (usize, String) {
+    match CubeLut::parse(text) {
+        Err(LutError::Parse { line, message }) => (line, message),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn parses_red_fastest() {
+    let lut = CubeLut::parse(IDENTITY).unwrap();
+    assert_eq!(lut.size, 2);
+    assert_eq!(lut.domain_min, [0.0; 3]);
+    assert_eq!(lut.domain_max, [1.0; 3]);
+    assert_eq!(lut.table[1], [1.0, 0.0, 0.0]);
+    assert_eq!(lut.table[4], [0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn parses_the_domain() {
+    let text = IDENTITY.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 4");
+    let lut = CubeLut::parse(&text).unwrap();
+    assert_eq!(lut.domain_max, [2.0, 2.0, 4.0]);
+
+    let transform = lut.transform();
+    assert_eq!(transform.scale[2], 0.125);
+    assert_eq!(transform.offset[2], 0.25);
+}
+
+#[test]
+fn rejects_malformed_files() {
+    assert_eq!(parse_error("LUT_1D_SIZE 16\n").0, 1);
+    assert_eq!(parse_error("0 0 0\n").0, 1);
+    assert_eq!(parse_error("LUT_3D_SIZE 2\n0 0\n").0, 2);
+    assert_eq!(parse_error("LUT_3D_SIZE 1\n").0, 1);
+    assert_eq!(
+        parse_error("LUT_3D_SIZE 2\n0 0 0\n"),
+        (0, "expected 8 entries, found 1".to_owned())
+    );
+}
+
+#[test]
+fn gamma_table() {
+    let lut = CubeLut::gamma([2.0, 1.0, 0.5], 3);
+    assert_eq!(lut.table.len(), 27);
+    assert_eq!(lut.table[0], [0.0, 0.0, 0.0]);
+    assert_eq!(lut.table[26], [1.0, 1.0, 1.0]);
+    // r = 0.5, g = 0.5, b = 0.5
+    let middle = lut.table[13];
+    assert!((middle[0] - 0.5f32.sqrt()).abs() < 1e-6);
+    assert_eq!(middle[1], 0.5);
+    assert_eq!(middle[2], 0.25);
+}
@@ -0,0 +1,20 @@
+#version 450
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+layout(set = 0, binding = 1) uniform sampler3D lut;
+
+layout(push_constant) uniform CorrectionParams {
+    // maps a colour to LUT texture coordinates, including the domain and
+    // the half texel at each end
+    vec4 lut_scale;
+    vec4 lut_offset;
+    // output size in pixels
+    vec2 size;
+} params;
+
+void main() {
+    vec4 color = texture(source, gl_FragCoord.xy / params.size);
+    vec3 coord = clamp(color.rgb * params.lut_scale.xyz + params.lut_offset.xyz, 0.0, 1.0);
+    f_color = vec4(texture(lut, coord).rgb, color.a);
+}
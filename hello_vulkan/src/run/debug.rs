@@ -0,0 +1,135 @@
+use std::env;
+use std::sync::Arc;
+
+use vulkano::instance::{self, Instance, InstanceExtensions};
+use vulkano::instance::debug::{DebugCallback, Message, MessageTypes};
+
+use log::Level;
+
+use log_targets::DEBUG_REPORT;
+
+/// set to anything but `0` to enable the validation layers
+pub const VALIDATION_ENV: &str = "SLICER_VALIDATION";
+
+/// layers we ask for when validation is enabled, only the ones
+/// reported by `layers_list()` are actually enabled
+const VALIDATION_LAYERS: &[&str] = &["VK_LAYER_LUNARG_standard_validation"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MessageKind {
+    General,
+    Validation,
+    Performance,
+}
+
+pub fn validation_requested() -> bool {
+    match env::var(VALIDATION_ENV) {
+        Ok(value) => value != "0",
+        Err(_) => false,
+    }
+}
+
+/// returns the validation layers which are both wanted and available
+pub fn validation_layers(enabled: bool) -> Vec<&'static str> {
+    if !enabled {
+        return Vec::new();
+    }
+
+    let available: Vec<String> = match instance::layers_list() {
+        Ok(layers) => layers.map(|l| l.name().to_owned()).collect(),
+        Err(err) => {
+            warn!(target: DEBUG_REPORT, "failed to list layers: {:?}", err);
+            return Vec::new();
+        }
+    };
+
+    VALIDATION_LAYERS
+        .iter()
+        .cloned()
+        .filter(|name| {
+            let found = available.iter().any(|l| l == name);
+            if !found {
+                warn!(target: DEBUG_REPORT, "validation layer {} is not available", name);
+            }
+            found
+        })
+        .collect()
+}
+
+/// `ext_debug_report` if the loader supports it, otherwise nothing
+pub fn debug_extensions() -> InstanceExtensions {
+    let supported = InstanceExtensions::supported_by_core()
+        .map(|exts| exts.ext_debug_report)
+        .unwrap_or(false);
+    InstanceExtensions {
+        ext_debug_report: supported,
+        ..InstanceExtensions::none()
+    }
+}
+
+/// the callback has to outlive every Vulkan call we want reported,
+/// so keep it together with the device in `VulkanStruct`
+pub fn create_debug_callback(instance: &Arc<Instance>, verbose: bool) -> Option<DebugCallback> {
+    let types = MessageTypes {
+        error: true,
+        warning: true,
+        performance_warning: true,
+        information: verbose,
+        debug: verbose,
+    };
+
+    match DebugCallback::new(instance, types, route_message) {
+        Ok(callback) => Some(callback),
+        Err(err) => {
+            warn!(target: DEBUG_REPORT, "failed to create debug callback: {:?}", err);
+            None
+        }
+    }
+}
+
+fn route_message(msg: &Message) {
+    let level = match severity(&msg.ty) {
+        Severity::Error => Level::Error,
+        Severity::Warning => Level::Warn,
+        Severity::Info => Level::Info,
+        Severity::Debug => Level::Debug,
+    };
+    log!(
+        target: DEBUG_REPORT,
+        level,
+        "{:?} [{}] {}",
+        kind(msg),
+        msg.layer_prefix,
+        msg.description
+    );
+}
+
+pub fn severity(ty: &MessageTypes) -> Severity {
+    if ty.error {
+        Severity::Error
+    } else if ty.warning || ty.performance_warning {
+        Severity::Warning
+    } else if ty.information {
+        Severity::Info
+    } else {
+        Severity::Debug
+    }
+}
+
+pub fn kind(msg: &Message) -> MessageKind {
+    if msg.ty.performance_warning {
+        MessageKind::Performance
+    } else if msg.layer_prefix == "Loader Message" {
+        MessageKind::General
+    } else {
+        MessageKind::Validation
+    }
+}
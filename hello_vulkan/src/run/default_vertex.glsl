@@ -1,6 +1,13 @@
- #version 450
+#version 450
 layout(location = 0) in vec2 position;
 
+// column major 2x2 matrix, identity unless the panel orientation
+// can't be applied by the surface
+layout(push_constant) uniform PreRotation {
+    vec4 matrix;
+} pre_rotation;
+
 void main() {
-    gl_Position = vec4(position, 0.0, 1.0);
+    mat2 m = mat2(pre_rotation.matrix.xy, pre_rotation.matrix.zw);
+    gl_Position = vec4(m * position, 0.0, 1.0);
 }
\ No newline at end of file
@@ -1,60 +1,130 @@
-use super::VulkanStruct;
+use super::{choose_format, choose_present_mode, VulkanStruct};
+use super::backend::{BackendEvent, SurfaceBackend};
+use super::orientation::{AppliedOrientation, Orientation};
+use super::renderer::RenderError;
+use super::target::{PresentTarget, SwapchainTarget};
+use config::{Config, ConfigError};
+use input::evdev::{InputDevices, DEFAULT_DIR};
 
 use std::sync::Arc;
-use vulkano::image::swapchain::SwapchainImage;
-use vulkano::instance::InstanceExtensions;
-use vulkano::swapchain::{PresentMode, SurfaceTransform, Swapchain, SwapchainCreationError, Surface};
+use vulkano::instance::{DeviceExtensions, InstanceExtensions};
+use vulkano::swapchain::{Swapchain, Surface};
 
 use vulkano::swapchain::display::{Display, DisplayPlane};
 use vulkano::instance::PhysicalDevice;
 
-use super::info::print_surface_capabilities;
+use super::info::{print_all_display_plane, print_all_displays, print_surface_capabilities};
 
-pub fn required_extensions() -> InstanceExtensions {
-    let extensions = InstanceExtensions {
-        khr_display: true,
-        ..InstanceExtensions::none()
-    };
-    extensions
+/// `VK_KHR_display` surface straight on the panel, no window system,
+/// input comes from the event devices
+pub struct DisplayBackend {
+    input: InputDevices,
+    /// of the mode picked by `create_target`
+    refresh_rate: Option<u32>,
 }
 
-pub fn create_swapchain(
-    vulkan_obj: Arc<VulkanStruct>,
-) -> Result<
-    (
-        Arc<Swapchain<()>>,
-        Vec<Arc<SwapchainImage<()>>>
-    ),
-    SwapchainCreationError
-> {
-    let display = Display::enumerate(vulkan_obj.device.physical_device()).next().expect("There is no display available");
-    let display_mode = display.display_modes().next().unwrap();
-    let region = display_mode.visible_region();
-    let display_plane = DisplayPlane::enumerate(vulkan_obj.device.physical_device()).next().expect("There is no display plane available");
-    let surface = Surface::<()>::from_display_mode(&display_mode, &display_plane).expect("failed to create surface from display mode");
-    let caps = surface.capabilities(vulkan_obj.device.physical_device()).expect("failed to get surface capabilities");
-
-    print_surface_capabilities(caps.clone());
-
-    let format = caps.supported_formats[0].0;
-    let alpha = caps.supported_composite_alpha.iter().next().unwrap();
-    Swapchain::new(
-        vulkan_obj.device.clone(),
-        surface.clone(),
-        caps.min_image_count,
-        format,
-        region,
-        1,
-        caps.supported_usage_flags,
-        &vulkan_obj.queue,
-        SurfaceTransform::Identity,
-        alpha, 
-        PresentMode::Fifo,
-        true,
-        None
-    )
+impl DisplayBackend {
+    pub fn new() -> DisplayBackend {
+        DisplayBackend {
+            input: InputDevices::new(DEFAULT_DIR),
+            refresh_rate: None,
+        }
+    }
 }
 
-pub fn run_loop() {
-    loop {}
+impl SurfaceBackend for DisplayBackend {
+    fn name(&self) -> &'static str {
+        "display"
+    }
+
+    fn required_extensions(&self) -> InstanceExtensions {
+        let extensions = InstanceExtensions {
+            khr_display: true,
+            ..InstanceExtensions::none()
+        };
+        extensions
+    }
+
+    fn required_device_extensions(&self) -> DeviceExtensions {
+        DeviceExtensions {
+            khr_swapchain: true,
+            ..DeviceExtensions::none()
+        }
+    }
+
+    fn create_target(
+        &mut self,
+        vulkan_obj: Arc<VulkanStruct>,
+        config: &Config,
+    ) -> Result<Box<PresentTarget>, RenderError> {
+        print_all_displays(vulkan_obj.device.physical_device());
+        print_all_display_plane(vulkan_obj.device.physical_device());
+
+        let display = Display::enumerate(vulkan_obj.device.physical_device())
+            .nth(config.display.index)
+            .ok_or_else(|| {
+                ConfigError::invalid("display.index", format!("there is no display {}", config.display.index))
+            })?;
+        let display_mode = display
+            .display_modes()
+            .find(|mode| {
+                config.display.mode.map_or(true, |size| mode.visible_region() == size)
+                    && config.display.refresh_rate.map_or(true, |rate| mode.refresh_rate() == rate)
+            })
+            .ok_or_else(|| {
+                ConfigError::invalid(
+                    "display.mode",
+                    "no display mode matches the configured size and refresh rate",
+                )
+            })?;
+        let region = display_mode.visible_region();
+        self.input.set_screen(region);
+        self.refresh_rate = Some(display_mode.refresh_rate());
+        let display_plane = DisplayPlane::enumerate(vulkan_obj.device.physical_device())
+            .nth(config.display.plane)
+            .ok_or_else(|| {
+                ConfigError::invalid("display.plane", format!("there is no display plane {}", config.display.plane))
+            })?;
+        let surface = Surface::<()>::from_display_mode(&display_mode, &display_plane)?;
+        let caps = surface.capabilities(vulkan_obj.device.physical_device())?;
+
+        print_surface_capabilities(caps.clone());
+
+        let orientation = AppliedOrientation::choose(&caps, Orientation::from_config(&config.orientation)?);
+        let region = orientation.image_extent(region);
+        let format = choose_format(&caps, config)?;
+        let present_mode = choose_present_mode(&caps, config);
+        let alpha = caps.supported_composite_alpha.iter().next().unwrap();
+        let (swap_chain, images) = Swapchain::new(
+            vulkan_obj.device.clone(),
+            surface.clone(),
+            caps.min_image_count,
+            format,
+            region,
+            1,
+            caps.supported_usage_flags,
+            &vulkan_obj.queue,
+            orientation.surface_transform,
+            alpha, 
+            present_mode,
+            true,
+            None
+        ).map_err(RenderError::Swapchain)?;
+
+        Ok(Box::new(SwapchainTarget::new(
+            swap_chain,
+            images,
+            vulkan_obj.queue.clone(),
+            orientation,
+            vulkan_obj.timeouts.acquire,
+        )))
+    }
+
+    fn pump_events(&mut self) -> Vec<BackendEvent> {
+        self.input.poll().into_iter().map(BackendEvent::Input).collect()
+    }
+
+    fn refresh_rate(&self) -> Option<u32> {
+        self.refresh_rate
+    }
 }
@@ -0,0 +1,32 @@
+#version 450
+layout(local_size_x = 16, local_size_y = 16) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D source;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D destination;
+// weights[0] is the centre tap, then one per pixel away from it
+layout(set = 0, binding = 2) readonly buffer Weights {
+    float weights[];
+} taps;
+
+// one of the two passes of the separable kernel
+layout(push_constant) uniform BlurPass {
+    ivec2 direction;
+    int radius;
+} pass;
+
+void main() {
+    ivec2 size = imageSize(source);
+    ivec2 p = ivec2(gl_GlobalInvocationID.xy);
+    if (p.x >= size.x || p.y >= size.y) {
+        return;
+    }
+
+    vec4 sum = imageLoad(source, p) * taps.weights[0];
+    for (int i = 1; i <= pass.radius; i++) {
+        ivec2 offset = pass.direction * i;
+        vec4 pair = imageLoad(source, clamp(p + offset, ivec2(0), size - 1))
+            + imageLoad(source, clamp(p - offset, ivec2(0), size - 1));
+        sum += pair * taps.weights[i];
+    }
+    imageStore(destination, p, sum);
+}
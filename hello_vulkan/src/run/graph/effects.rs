@@ -0,0 +1,193 @@
+//! Effects the configuration can chain, each samples one input
+
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::image::AttachmentImage;
+use vulkano::pipeline::vertex::{BufferlessDefinition, BufferlessVertices};
+use vulkano::sampler::Sampler;
+
+use super::{linear_sampler, Effect};
+use super::super::VulkanStruct;
+use super::super::pipeline_cache::{Blend, CachedPipeline};
+use super::super::renderer::{BufferlessPipeline, RenderError};
+use super::super::shaders::blit_fragment_shader;
+use super::super::shaders::blit_fragment_shader::ty::BlitParams;
+use super::super::shaders::blur_fragment_shader;
+use super::super::shaders::blur_fragment_shader::ty::BlurParams;
+use super::super::shaders::fullscreen_vertex_shader::Shader as VertexShader;
+use super::super::shaders::vignette_fragment_shader;
+use super::super::shaders::vignette_fragment_shader::ty::VignetteParams;
+
+/// copies its input, resampling it when the sizes differ
+pub struct Blit {
+    pipeline: Option<Arc<BufferlessPipeline>>,
+    sampler: Option<Arc<Sampler>>,
+}
+
+/// one direction of a gaussian blur, chain a horizontal and a vertical one
+pub struct Blur {
+    /// standard deviation in pixels
+    radius: f32,
+    direction: [f32; 2],
+    pipeline: Option<Arc<BufferlessPipeline>>,
+    sampler: Option<Arc<Sampler>>,
+}
+
+/// darkens the corners
+pub struct Vignette {
+    /// 0 leaves the frame alone, 1 makes the corners black
+    strength: f32,
+    pipeline: Option<Arc<BufferlessPipeline>>,
+    sampler: Option<Arc<Sampler>>,
+}
+
+/// builds the pipeline of a fullscreen effect from its fragment shader
+/// module
+macro_rules! fullscreen_pipeline {
+    ($vulkan_obj:expr, $subpass:expr, $fragment:ident) => {{
+        let vertex_shader = VertexShader::load($vulkan_obj.device.clone())?;
+        let fragment_shader = $fragment::Shader::load($vulkan_obj.device.clone())?;
+        let pipeline = CachedPipeline::new(
+            $vulkan_obj,
+            BufferlessDefinition,
+            vertex_shader.main_entry_point(),
+            fragment_shader.main_entry_point(),
+            Blend::Opaque,
+            $subpass,
+        )?;
+        Arc::new(pipeline)
+    }};
+}
+
+/// draws the fullscreen triangle with `input` at binding 0
+fn draw_sampled<Pc>(
+    builder: AutoCommandBufferBuilder,
+    dynamic_state: &DynamicState,
+    pipeline: &Option<Arc<BufferlessPipeline>>,
+    sampler: &Option<Arc<Sampler>>,
+    input: &Arc<AttachmentImage>,
+    constants: Pc,
+) -> Result<AutoCommandBufferBuilder, RenderError> {
+    let pipeline = pipeline.clone().expect("effect drawn before its pipeline was created");
+    let sampler = sampler.clone().expect("effect drawn before its pipeline was created");
+    let set = PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_sampled_image(input.clone(), sampler)?
+        .build()?;
+    let vertices = BufferlessVertices {
+        vertices: 3,
+        instances: 1,
+    };
+    Ok(builder.draw(pipeline, dynamic_state.clone(), vertices, set, constants)?)
+}
+
+impl Blit {
+    pub fn new() -> Blit {
+        Blit {
+            pipeline: None,
+            sampler: None,
+        }
+    }
+}
+
+impl Effect for Blit {
+    fn create_pipeline(
+        &mut self,
+        vulkan_obj: &Arc<VulkanStruct>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Result<(), RenderError> {
+        self.pipeline = Some(fullscreen_pipeline!(vulkan_obj, subpass, blit_fragment_shader));
+        self.sampler = Some(linear_sampler(&vulkan_obj.device)?);
+        Ok(())
+    }
+
+    fn draw(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        inputs: &[Arc<AttachmentImage>],
+        size: [u32; 2],
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let params = BlitParams {
+            size: [size[0] as f32, size[1] as f32],
+        };
+        draw_sampled(builder, dynamic_state, &self.pipeline, &self.sampler, &inputs[0], params)
+    }
+}
+
+impl Blur {
+    /// `direction` is `[1.0, 0.0]` or `[0.0, 1.0]`
+    pub fn new(radius: f32, direction: [f32; 2]) -> Blur {
+        Blur {
+            radius: radius,
+            direction: direction,
+            pipeline: None,
+            sampler: None,
+        }
+    }
+}
+
+impl Effect for Blur {
+    fn create_pipeline(
+        &mut self,
+        vulkan_obj: &Arc<VulkanStruct>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Result<(), RenderError> {
+        self.pipeline = Some(fullscreen_pipeline!(vulkan_obj, subpass, blur_fragment_shader));
+        self.sampler = Some(linear_sampler(&vulkan_obj.device)?);
+        Ok(())
+    }
+
+    fn draw(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        inputs: &[Arc<AttachmentImage>],
+        size: [u32; 2],
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let params = BlurParams {
+            size: [size[0] as f32, size[1] as f32],
+            direction: self.direction,
+            radius: self.radius,
+        };
+        draw_sampled(builder, dynamic_state, &self.pipeline, &self.sampler, &inputs[0], params)
+    }
+}
+
+impl Vignette {
+    pub fn new(strength: f32) -> Vignette {
+        Vignette {
+            strength: strength,
+            pipeline: None,
+            sampler: None,
+        }
+    }
+}
+
+impl Effect for Vignette {
+    fn create_pipeline(
+        &mut self,
+        vulkan_obj: &Arc<VulkanStruct>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Result<(), RenderError> {
+        self.pipeline = Some(fullscreen_pipeline!(vulkan_obj, subpass, vignette_fragment_shader));
+        self.sampler = Some(linear_sampler(&vulkan_obj.device)?);
+        Ok(())
+    }
+
+    fn draw(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        inputs: &[Arc<AttachmentImage>],
+        size: [u32; 2],
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let params = VignetteParams {
+            size: [size[0] as f32, size[1] as f32],
+            strength: self.strength,
+        };
+        draw_sampled(builder, dynamic_state, &self.pipeline, &self.sampler, &inputs[0], params)
+    }
+}
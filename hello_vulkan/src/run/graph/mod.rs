@@ -0,0 +1,525 @@
+//! Post-processing render graph
+//!
+//! The frame starts with the scene node: the render pass `Renderer::draw`
+//! records into, whose output is the `SCENE` image. Passes declared on the
+//! graph then read images and write one each, the last one writing the
+//! `TARGET` image which is presented. Every pass is a fullscreen draw by an
+//! `Effect`.
+//!
+//! The graph orders the passes by their dependencies, creates a render
+//! pass per pass and the intermediate images, one set per target image so
+//! frames in flight don't share them. The layout transitions and barriers
+//! between a pass writing an image and the next one sampling it are
+//! inserted by vulkano's command buffer builder, which tracks the images
+//! each command uses.
+//!
+//! ```ignore
+//! let mut graph = RenderGraph::new(1.0);
+//! let half = graph.image(ImageDesc::scaled(0.5));
+//! graph.add_pass("downscale", Box::new(Blit::new()), &[SCENE], half);
+//! graph.add_pass("vignette", Box::new(Vignette::new(0.5)), &[half], TARGET);
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::device::Device;
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageViewAccess};
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+use config::{Config, EffectConfig};
+
+use super::VulkanStruct;
+use super::correction::{ColorCorrection, CorrectionSource};
+use super::renderer::RenderError;
+use super::upload::UploadManager;
+
+pub mod effects;
+
+#[cfg(test)]
+mod tests;
+
+use self::effects::{Blit, Blur, Vignette};
+
+/// an image of the graph, from `RenderGraph::image` or one of the two
+/// built-in ones
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ImageId(usize);
+
+/// written by the scene node
+pub const SCENE: ImageId = ImageId(0);
+/// the presented image, written by the last pass
+pub const TARGET: ImageId = ImageId(1);
+
+/// an intermediate image
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImageDesc {
+    /// size relative to the target
+    pub scale: f32,
+    /// the target's when `None`
+    pub format: Option<Format>,
+}
+
+/// what the graph knows of a pass to order it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassDesc {
+    pub name: String,
+    pub inputs: Vec<ImageId>,
+    pub output: ImageId,
+}
+
+/// a fullscreen draw reading the pass' inputs
+pub trait Effect {
+    /// creates the pipeline, called once the pass' render pass exists
+    fn create_pipeline(
+        &mut self,
+        vulkan_obj: &Arc<VulkanStruct>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Result<(), RenderError>;
+
+    /// records the draw inside the pass' render pass, `inputs` in the
+    /// order they were declared and `size` the output's in pixels
+    fn draw(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        dynamic_state: &DynamicState,
+        inputs: &[Arc<AttachmentImage>],
+        size: [u32; 2],
+    ) -> Result<AutoCommandBufferBuilder, RenderError>;
+
+    /// re-reads the files the effect is built from, the returned upload
+    /// must complete before the next frame
+    fn reload(&mut self, _uploads: &mut UploadManager) -> Result<Option<Box<GpuFuture>>, RenderError> {
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphError {
+    /// `pass` reads an image no pass writes
+    NotWritten { pass: String, image: ImageId },
+    /// several passes write `image`
+    WrittenTwice(ImageId),
+    ReadsTarget(String),
+    WritesScene(String),
+    /// the output of `pass` is never read
+    Unused(String),
+    NoTarget,
+    Cycle,
+}
+
+struct Pass {
+    desc: PassDesc,
+    effect: Box<Effect>,
+    render_pass: Option<Arc<RenderPassAbstract + Send + Sync>>,
+}
+
+/// the images and framebuffers of one target image
+struct FrameResources {
+    /// indexed by `ImageId`, `None` for the target
+    images: Vec<Option<Arc<AttachmentImage>>>,
+    /// indexed like `RenderGraph::order`
+    framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+}
+
+pub struct RenderGraph {
+    images: Vec<ImageDesc>,
+    passes: Vec<Pass>,
+    /// pass indices in execution order, set by `compile`
+    order: Vec<usize>,
+    target_format: Option<Format>,
+    target_dimensions: [u32; 2],
+    frames: Vec<FrameResources>,
+}
+
+impl ImageDesc {
+    /// in the target's format
+    pub fn scaled(scale: f32) -> ImageDesc {
+        ImageDesc {
+            scale: scale,
+            format: None,
+        }
+    }
+
+    fn dimensions(&self, target: [u32; 2]) -> [u32; 2] {
+        let scale = |size: u32| ((size as f32 * self.scale).round() as u32).max(1);
+        [scale(target[0]), scale(target[1])]
+    }
+}
+
+impl RenderGraph {
+    /// `scene_scale` is the size of the scene relative to the target
+    pub fn new(scene_scale: f32) -> RenderGraph {
+        RenderGraph {
+            images: vec![ImageDesc::scaled(scene_scale), ImageDesc::scaled(1.0)],
+            passes: Vec::new(),
+            order: Vec::new(),
+            target_format: None,
+            target_dimensions: [0, 0],
+            frames: Vec::new(),
+        }
+    }
+
+    /// the chain `[post_process]` and `[color_correction]` describe, with
+    /// the uploads the effects need
+    pub fn from_config(
+        vulkan_obj: &Arc<VulkanStruct>,
+        uploads: &mut UploadManager,
+        config: &Config,
+    ) -> Result<(RenderGraph, Vec<Box<GpuFuture>>), RenderError> {
+        let mut chain: Vec<(String, Box<Effect>, f32)> = Vec::new();
+        let mut scale = config.post_process.scale;
+        for (index, effect) in config.post_process.effects.iter().enumerate() {
+            match *effect {
+                EffectConfig::Blur { radius } => {
+                    chain.push((
+                        format!("blur-x-{}", index),
+                        Box::new(Blur::new(radius, [1.0, 0.0])),
+                        scale,
+                    ));
+                    chain.push((
+                        format!("blur-y-{}", index),
+                        Box::new(Blur::new(radius, [0.0, 1.0])),
+                        scale,
+                    ));
+                }
+                EffectConfig::Vignette { strength } => {
+                    chain.push((
+                        format!("vignette-{}", index),
+                        Box::new(Vignette::new(strength)),
+                        scale,
+                    ));
+                }
+                EffectConfig::Scale { size } => {
+                    scale = size;
+                    chain.push((
+                        format!("scale-{}", index),
+                        Box::new(Blit::new()),
+                        scale,
+                    ));
+                }
+            }
+        }
+
+        let mut futures = Vec::new();
+        if let Some(source) = CorrectionSource::from_config(&config.color_correction) {
+            let (correction, future) = ColorCorrection::new(vulkan_obj, uploads, source)?;
+            futures.extend(future);
+            chain.push((
+                "color-correction".to_owned(),
+                Box::new(correction),
+                1.0,
+            ));
+        }
+
+        // the scene is rendered at another size but nothing resamples it
+        if chain.is_empty() && config.post_process.scale != 1.0 {
+            chain.push((
+                "scale".to_owned(),
+                Box::new(Blit::new()),
+                1.0,
+            ));
+        }
+
+        let mut graph = RenderGraph::new(config.post_process.scale);
+        let mut input = SCENE;
+        let last = chain.len();
+        for (index, (name, effect, scale)) in chain.into_iter().enumerate() {
+            let output = if index + 1 == last {
+                TARGET
+            } else {
+                graph.image(ImageDesc::scaled(scale))
+            };
+            graph.add_pass(&name, effect, &[input], output);
+            input = output;
+        }
+        Ok((graph, futures))
+    }
+
+    pub fn image(&mut self, desc: ImageDesc) -> ImageId {
+        self.images.push(desc);
+        ImageId(self.images.len() - 1)
+    }
+
+    /// `output` may be any image but the scene, checked by `compile`
+    pub fn add_pass(&mut self, name: &str, effect: Box<Effect>, inputs: &[ImageId], output: ImageId) {
+        self.passes.push(Pass {
+            desc: PassDesc {
+                name: name.to_owned(),
+                inputs: inputs.to_vec(),
+                output: output,
+            },
+            effect: effect,
+            render_pass: None,
+        });
+    }
+
+    /// without passes the scene is rendered straight into the target
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// orders the passes and creates their render passes and pipelines,
+    /// `format` is the target's
+    pub fn compile(&mut self, vulkan_obj: &Arc<VulkanStruct>, format: Format) -> Result<(), RenderError> {
+        let descs: Vec<PassDesc> = self.passes.iter().map(|pass| pass.desc.clone()).collect();
+        self.order = schedule(&descs)?;
+        self.target_format = Some(format);
+
+        for index in 0..self.passes.len() {
+            let output = self.passes[index].desc.output;
+            let format = self.format(output);
+            let render_pass = Arc::new(single_pass_renderpass!(vulkan_obj.device.clone(),
+                attachments: {
+                    color: {
+                        load: DontCare,
+                        store: Store,
+                        format: format,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            )?) as Arc<RenderPassAbstract + Send + Sync>;
+            let subpass = Subpass::from(render_pass.clone(), 0).expect("render pass has no subpass 0");
+            let pass = &mut self.passes[index];
+            pass.effect.create_pipeline(vulkan_obj, subpass)?;
+            pass.render_pass = Some(render_pass);
+        }
+        Ok(())
+    }
+
+    /// size the scene is rendered at
+    pub fn scene_dimensions(&self, target: [u32; 2]) -> [u32; 2] {
+        if self.is_empty() {
+            target
+        } else {
+            self.images[SCENE.0].dimensions(target)
+        }
+    }
+
+    /// creates the intermediate images for new target images, returns the
+    /// images the scene must be rendered into
+    pub fn attach(
+        &mut self,
+        device: &Arc<Device>,
+        targets: Vec<Arc<ImageViewAccess + Send + Sync>>,
+        dimensions: [u32; 2],
+    ) -> Result<Vec<Arc<ImageViewAccess + Send + Sync>>, RenderError> {
+        self.target_dimensions = dimensions;
+        if self.is_empty() {
+            self.frames.clear();
+            return Ok(targets);
+        }
+
+        let mut frames = Vec::with_capacity(targets.len());
+        let mut scenes = Vec::with_capacity(targets.len());
+        for target in targets {
+            let mut images = Vec::with_capacity(self.images.len());
+            for (index, desc) in self.images.iter().enumerate() {
+                images.push(if ImageId(index) == TARGET {
+                    None
+                } else {
+                    Some(AttachmentImage::sampled(
+                        device.clone(),
+                        desc.dimensions(dimensions),
+                        self.format(ImageId(index)),
+                    )?)
+                });
+            }
+
+            let mut framebuffers = Vec::with_capacity(self.order.len());
+            for &index in &self.order {
+                let pass = &self.passes[index];
+                let render_pass = pass.render_pass.clone().expect("graph is not compiled");
+                let framebuffer = match images[pass.desc.output.0] {
+                    Some(ref image) => Arc::new(Framebuffer::start(render_pass).add(image.clone())?.build()?)
+                        as Arc<FramebufferAbstract + Send + Sync>,
+                    None => Arc::new(Framebuffer::start(render_pass).add(target.clone())?.build()?)
+                        as Arc<FramebufferAbstract + Send + Sync>,
+                };
+                framebuffers.push(framebuffer);
+            }
+
+            scenes.push(images[SCENE.0].clone().expect("scene image") as Arc<ImageViewAccess + Send + Sync>);
+            frames.push(FrameResources {
+                images: images,
+                framebuffers: framebuffers,
+            });
+        }
+        self.frames = frames;
+        Ok(scenes)
+    }
+
+    /// records every pass, after the scene's render pass ended
+    pub fn record(
+        &self,
+        mut builder: AutoCommandBufferBuilder,
+        image_index: usize,
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let frame = match self.frames.get(image_index) {
+            Some(frame) => frame,
+            None => return Ok(builder),
+        };
+
+        for (position, &index) in self.order.iter().enumerate() {
+            let pass = &self.passes[index];
+            let inputs: Vec<Arc<AttachmentImage>> = pass.desc
+                .inputs
+                .iter()
+                .map(|input| frame.images[input.0].clone().expect("pass reads the target"))
+                .collect();
+            let size = self.images[pass.desc.output.0].dimensions(self.target_dimensions);
+            let dynamic_state = DynamicState {
+                viewports: Some(vec![Viewport {
+                    origin: [0.0, 0.0],
+                    dimensions: [size[0] as f32, size[1] as f32],
+                    depth_range: 0.0..1.0,
+                }]),
+                ..DynamicState::none()
+            };
+
+            builder = builder.begin_render_pass(
+                frame.framebuffers[position].clone(),
+                false,
+                vec![ClearValue::None],
+            )?;
+            builder = pass.effect.draw(builder, &dynamic_state, &inputs, size)?;
+            builder = builder.end_render_pass()?;
+        }
+        Ok(builder)
+    }
+
+    /// reloads every effect, the current ones stay on error
+    pub fn reload(&mut self, uploads: &mut UploadManager) -> Result<Vec<Box<GpuFuture>>, RenderError> {
+        let mut futures = Vec::new();
+        for pass in &mut self.passes {
+            if let Some(future) = pass.effect.reload(uploads)? {
+                futures.push(future);
+            }
+        }
+        Ok(futures)
+    }
+
+    fn format(&self, image: ImageId) -> Format {
+        let target = self.target_format.expect("graph is not compiled");
+        if image == TARGET || image == SCENE {
+            target
+        } else {
+            self.images[image.0].format.unwrap_or(target)
+        }
+    }
+}
+
+/// orders `passes` so every pass runs after the ones writing its inputs,
+/// keeping the declaration order otherwise
+pub fn schedule(passes: &[PassDesc]) -> Result<Vec<usize>, GraphError> {
+    if passes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let writer = |image: ImageId| passes.iter().position(|pass| pass.output == image);
+    for (index, pass) in passes.iter().enumerate() {
+        if pass.output == SCENE {
+            return Err(GraphError::WritesScene(pass.name.clone()));
+        }
+        if passes[index + 1..].iter().any(|other| other.output == pass.output) {
+            return Err(GraphError::WrittenTwice(pass.output));
+        }
+        for &input in &pass.inputs {
+            if input == TARGET {
+                return Err(GraphError::ReadsTarget(pass.name.clone()));
+            }
+            if input != SCENE && writer(input).is_none() {
+                return Err(GraphError::NotWritten {
+                    pass: pass.name.clone(),
+                    image: input,
+                });
+            }
+        }
+        if pass.output != TARGET && !passes.iter().any(|other| other.inputs.contains(&pass.output)) {
+            return Err(GraphError::Unused(pass.name.clone()));
+        }
+    }
+    if writer(TARGET).is_none() {
+        return Err(GraphError::NoTarget);
+    }
+
+    let mut order = Vec::with_capacity(passes.len());
+    let mut done = vec![false; passes.len()];
+    while order.len() < passes.len() {
+        let ready = (0..passes.len()).find(|&index| {
+            !done[index]
+                && passes[index]
+                    .inputs
+                    .iter()
+                    .all(|&input| input == SCENE || writer(input).map_or(false, |w| done[w]))
+        });
+        match ready {
+            Some(index) => {
+                done[index] = true;
+                order.push(index);
+            }
+            None => return Err(GraphError::Cycle),
+        }
+    }
+    Ok(order)
+}
+
+/// bilinear, clamped at the edges, for effects sampling their inputs with
+/// normalized coordinates
+pub fn linear_sampler(device: &Arc<Device>) -> Result<Arc<Sampler>, RenderError> {
+    Ok(Sampler::new(
+        device.clone(),
+        Filter::Linear,
+        Filter::Linear,
+        MipmapMode::Nearest,
+        SamplerAddressMode::ClampToEdge,
+        SamplerAddressMode::ClampToEdge,
+        SamplerAddressMode::ClampToEdge,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+    )?)
+}
+
+impl fmt::Display for ImageId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SCENE => write!(f, "scene"),
+            TARGET => write!(f, "target"),
+            ImageId(index) => write!(f, "image {}", index),
+        }
+    }
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GraphError::NotWritten { ref pass, image } => {
+                write!(f, "pass '{}' reads {} which no pass writes", pass, image)
+            }
+            GraphError::WrittenTwice(image) => write!(f, "{} is written by several passes", image),
+            GraphError::ReadsTarget(ref pass) => write!(f, "pass '{}' reads the target", pass),
+            GraphError::WritesScene(ref pass) => write!(f, "pass '{}' writes the scene", pass),
+            GraphError::Unused(ref pass) => write!(f, "the output of pass '{}' is never read", pass),
+            GraphError::NoTarget => write!(f, "no pass writes the target"),
+            GraphError::Cycle => write!(f, "the passes depend on each other"),
+        }
+    }
+}
+
+impl Error for GraphError {
+    fn description(&self) -> &str {
+        "invalid render graph"
+    }
+}
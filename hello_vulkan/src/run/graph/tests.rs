@@ -0,0 +1,71 @@
+use super::{schedule, GraphError, ImageDesc, ImageId, PassDesc, SCENE, TARGET};
+
+fn pass(name: &str, inputs: &[ImageId], output: ImageId) -> PassDesc {
+    PassDesc {
+        name: name.to_owned(),
+        inputs: inputs.to_vec(),
+        output: output,
+    }
+}
+
+#[test]
+fn no_passes() {
+    assert_eq!(schedule(&[]), Ok(vec![]));
+}
+
+#[test]
+fn passes_run_after_their_inputs() {
+    let (blurred, dimmed) = (ImageId(2), ImageId(3));
+    let passes = [
+        pass("compose", &[blurred, dimmed], TARGET),
+        pass("dim", &[SCENE], dimmed),
+        pass("blur", &[SCENE], blurred),
+    ];
+    assert_eq!(schedule(&passes), Ok(vec![1, 2, 0]));
+}
+
+#[test]
+fn invalid_graphs() {
+    let image = ImageId(2);
+    assert_eq!(
+        schedule(&[pass("a", &[image], TARGET)]),
+        Err(GraphError::NotWritten {
+            pass: "a".to_owned(),
+            image: image,
+        })
+    );
+    assert_eq!(
+        schedule(&[pass("a", &[SCENE], TARGET), pass("b", &[SCENE], TARGET)]),
+        Err(GraphError::WrittenTwice(TARGET))
+    );
+    assert_eq!(
+        schedule(&[pass("a", &[TARGET], TARGET)]),
+        Err(GraphError::ReadsTarget("a".to_owned()))
+    );
+    assert_eq!(
+        schedule(&[pass("a", &[SCENE], SCENE)]),
+        Err(GraphError::WritesScene("a".to_owned()))
+    );
+    assert_eq!(
+        schedule(&[pass("a", &[SCENE], image), pass("b", &[SCENE], TARGET)]),
+        Err(GraphError::Unused("a".to_owned()))
+    );
+    assert_eq!(
+        schedule(&[pass("a", &[SCENE], image), pass("b", &[image], image)]),
+        Err(GraphError::WrittenTwice(image))
+    );
+    assert_eq!(
+        schedule(&[
+            pass("a", &[ImageId(3)], image),
+            pass("b", &[image], ImageId(3)),
+            pass("c", &[image], TARGET),
+        ]),
+        Err(GraphError::Cycle)
+    );
+}
+
+#[test]
+fn image_sizes_are_never_zero() {
+    assert_eq!(ImageDesc::scaled(0.5).dimensions([1920, 1081]), [960, 541]);
+    assert_eq!(ImageDesc::scaled(0.001).dimensions([100, 100]), [1, 1]);
+}
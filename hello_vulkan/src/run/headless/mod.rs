@@ -0,0 +1,288 @@
+//! Offscreen backend, renders into plain images and reads them back
+//!
+//! Needs no window system nor display, which makes it usable on build
+//! machines and on boards where the display is driven by something else.
+//! The frames read back go to a `FrameSink`: PNG files for the `headless`
+//! backend, a Linux framebuffer device for the `fb` backend.
+
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use image::{ImageBuffer, Rgba};
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::{AttachmentImage, ImageAccess, ImageUsage, ImageViewAccess};
+use vulkano::instance::{DeviceExtensions, InstanceExtensions};
+use vulkano::sync::{now, GpuFuture};
+
+use config::Config;
+#[cfg(target_os = "linux")]
+use input::evdev::{InputDevices, DEFAULT_DIR};
+#[cfg(target_os = "linux")]
+use linuxfb::LinuxFramebuffer;
+use log_targets::INFO;
+
+use super::VulkanStruct;
+use super::backend::{BackendEvent, SurfaceBackend};
+use super::orientation::{AppliedOrientation, Orientation};
+use super::renderer::RenderError;
+use super::target::PresentTarget;
+use super::watchdog::{wait_fence, Stage};
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests;
+
+const FORMAT: Format = Format::R8G8B8A8Unorm;
+/// enough to keep recording a frame while the previous one is read back
+const IMAGE_COUNT: usize = 2;
+
+/// receives every frame read back, as tightly packed RGBA8
+pub trait FrameSink {
+    fn write_frame(&mut self, frame: u32, pixels: &[u8], width: u32, height: u32);
+}
+
+/// owned by the backend and lent to each target, so the targets created
+/// after a device loss still write where the first one did
+pub type SharedSink = Rc<RefCell<Box<FrameSink>>>;
+
+/// writes each frame to `output` with the first `{}` replaced by the
+/// frame number
+pub struct PngSink {
+    output: String,
+}
+
+pub struct HeadlessBackend {
+    name: &'static str,
+    /// lent to every target, `None` until the first one creates a
+    /// `PngSink` from the config
+    sink: Option<SharedSink>,
+    /// `None` uses `headless.size`
+    size: Option<[u32; 2]>,
+    /// stop after that many frames, 0 never stops
+    frames: Option<u32>,
+    frames_written: Rc<Cell<u32>>,
+    /// the `fb` backend reads the event devices
+    #[cfg(target_os = "linux")]
+    input: Option<InputDevices>,
+}
+
+pub struct HeadlessTarget {
+    vulkan_obj: Arc<VulkanStruct>,
+    images: Vec<Arc<AttachmentImage>>,
+    dimensions: [u32; 2],
+    next_image: usize,
+    sink: SharedSink,
+    frames: u32,
+    frames_written: Rc<Cell<u32>>,
+    orientation: AppliedOrientation,
+}
+
+impl HeadlessBackend {
+    pub fn new() -> HeadlessBackend {
+        HeadlessBackend {
+            name: "headless",
+            sink: None,
+            size: None,
+            frames: None,
+            frames_written: Rc::new(Cell::new(0)),
+            #[cfg(target_os = "linux")]
+            input: None,
+        }
+    }
+
+    /// the sink every target writes to, the same one for the life of the
+    /// backend
+    fn sink(&mut self, config: &Config) -> SharedSink {
+        self.sink
+            .get_or_insert_with(|| {
+                Rc::new(RefCell::new(Box::new(PngSink {
+                    output: config.headless.output.clone(),
+                }) as Box<FrameSink>))
+            })
+            .clone()
+    }
+
+    /// renders at the size of `fb` and copies every frame to it
+    #[cfg(target_os = "linux")]
+    pub fn framebuffer(fb: LinuxFramebuffer) -> HeadlessBackend {
+        let mut input = InputDevices::new(DEFAULT_DIR);
+        input.set_screen(fb.dimensions());
+        HeadlessBackend {
+            name: "fb",
+            size: Some(fb.dimensions()),
+            sink: Some(Rc::new(RefCell::new(Box::new(fb) as Box<FrameSink>))),
+            frames: Some(0),
+            frames_written: Rc::new(Cell::new(0)),
+            input: Some(input),
+        }
+    }
+}
+
+impl SurfaceBackend for HeadlessBackend {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn required_extensions(&self) -> InstanceExtensions {
+        InstanceExtensions::none()
+    }
+
+    fn required_device_extensions(&self) -> DeviceExtensions {
+        DeviceExtensions::none()
+    }
+
+    fn create_target(
+        &mut self,
+        vulkan_obj: Arc<VulkanStruct>,
+        config: &Config,
+    ) -> Result<Box<PresentTarget>, RenderError> {
+        let size = self.size.unwrap_or(config.headless.size);
+        let frames = *self.frames.get_or_insert(config.headless.frames);
+        let sink = self.sink(config);
+
+        let orientation = Orientation::from_config(&config.orientation)?;
+        let images = create_images(&vulkan_obj.device, size)?;
+        Ok(Box::new(HeadlessTarget {
+            vulkan_obj: vulkan_obj,
+            images: images,
+            dimensions: size,
+            next_image: 0,
+            sink: sink,
+            frames: frames,
+            frames_written: self.frames_written.clone(),
+            orientation: AppliedOrientation::pre_rotated(orientation),
+        }))
+    }
+
+    fn pump_events(&mut self) -> Vec<BackendEvent> {
+        let mut events = Vec::new();
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(ref mut input) = self.input {
+                events.extend(input.poll().into_iter().map(BackendEvent::Input));
+            }
+        }
+
+        let frames = self.frames.unwrap_or(0);
+        if frames != 0 && self.frames_written.get() >= frames {
+            events.push(BackendEvent::Quit);
+        }
+        events
+    }
+}
+
+fn create_images(
+    device: &Arc<Device>,
+    dimensions: [u32; 2],
+) -> Result<Vec<Arc<AttachmentImage>>, RenderError> {
+    let usage = ImageUsage {
+        color_attachment: true,
+        transfer_source: true,
+        ..ImageUsage::none()
+    };
+    let mut images = Vec::with_capacity(IMAGE_COUNT);
+    for _ in 0..IMAGE_COUNT {
+        images.push(AttachmentImage::with_usage(device.clone(), dimensions, FORMAT, usage)?);
+    }
+    Ok(images)
+}
+
+impl FrameSink for PngSink {
+    fn write_frame(&mut self, frame: u32, pixels: &[u8], width: u32, height: u32) {
+        let path = PathBuf::from(self.output.replacen("{}", &frame.to_string(), 1));
+        match ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, pixels) {
+            Some(image) => match image.save(&path) {
+                Ok(()) => info!(target: INFO, "frame {} written to {}", frame, path.display()),
+                Err(err) => warn!(target: INFO, "failed to write {}: {}", path.display(), err),
+            },
+            None => warn!(target: INFO, "readback of frame {} has the wrong size", frame),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl FrameSink for LinuxFramebuffer {
+    fn write_frame(&mut self, _frame: u32, pixels: &[u8], width: u32, height: u32) {
+        self.blit_rgba(pixels, width, height);
+    }
+}
+
+impl PresentTarget for HeadlessTarget {
+    fn format(&self) -> Format {
+        FORMAT
+    }
+
+    fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// the images stand for the panel, there is no surface to rotate
+    fn orientation(&self) -> AppliedOrientation {
+        self.orientation
+    }
+
+    fn images(&self) -> Vec<Arc<ImageViewAccess + Send + Sync>> {
+        self.images
+            .iter()
+            .map(|image| image.clone() as Arc<ImageViewAccess + Send + Sync>)
+            .collect()
+    }
+
+    fn image(&self, index: usize) -> Arc<ImageAccess + Send + Sync> {
+        self.images[index].clone()
+    }
+
+    fn acquire(&mut self) -> Result<(usize, Box<GpuFuture>), RenderError> {
+        let index = self.next_image;
+        self.next_image = (self.next_image + 1) % self.images.len();
+        Ok((index, Box::new(now(self.vulkan_obj.device.clone())) as Box<GpuFuture>))
+    }
+
+    /// reads the image back and writes it out, blocks until it's done
+    fn present(
+        &mut self,
+        future: Box<GpuFuture>,
+        image_index: usize,
+    ) -> Result<Box<GpuFuture>, RenderError> {
+        let frame = self.frames_written.get();
+        self.frames_written.set(frame + 1);
+        if self.frames != 0 && frame >= self.frames {
+            return Ok(future);
+        }
+
+        let (width, height) = (self.dimensions[0], self.dimensions[1]);
+        let buffer = CpuAccessibleBuffer::from_iter(
+            self.vulkan_obj.device.clone(),
+            BufferUsage::transfer_destination(),
+            (0..width * height * 4).map(|_| 0u8),
+        )?;
+
+        let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(
+            self.vulkan_obj.device.clone(),
+            self.vulkan_obj.queue.family(),
+        )?.copy_image_to_buffer(self.images[image_index].clone(), buffer.clone())?
+            .build()?;
+
+        let fence = future
+            .then_execute(self.vulkan_obj.queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?;
+        wait_fence(fence, self.vulkan_obj.timeouts.fence, Stage::Readback)?;
+
+        let content = buffer.read().expect("failed to map readback buffer");
+        self.sink.borrow_mut().write_frame(frame, &content, width, height);
+
+        Ok(Box::new(now(self.vulkan_obj.device.clone())))
+    }
+
+    fn recreate(&mut self, dimensions: [u32; 2]) -> Result<(), RenderError> {
+        self.images = create_images(&self.vulkan_obj.device, dimensions)?;
+        self.dimensions = dimensions;
+        self.next_image = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use super::HeadlessBackend;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
+
+use config::Config;
+use linuxfb::{Geometry, LinuxFramebuffer};
+
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("slicer-headless-{}-{}", process::id(), name))
+}
+
+#[test]
+fn framebuffer_sink_outlives_its_targets() {
+    let path = temp_path("fb");
+    let config = Config::default();
+    {
+        let fb = LinuxFramebuffer::with_geometry(&path, Geometry::xrgb8888(1, 1)).unwrap();
+        let mut backend = HeadlessBackend::framebuffer(fb);
+
+        // what `create_target` lends, the first target is gone after a
+        // device loss
+        let first = backend.sink(&config);
+        first.borrow_mut().write_frame(0, &[1, 2, 3, 255], 1, 1);
+        drop(first);
+
+        let second = backend.sink(&config);
+        assert!(Rc::ptr_eq(&second, &backend.sink(&config)));
+        second.borrow_mut().write_frame(1, &[4, 5, 6, 255], 1, 1);
+    }
+
+    let mut contents = Vec::new();
+    File::open(&path).unwrap().read_to_end(&mut contents).unwrap();
+    fs::remove_file(&path).unwrap();
+    // still the framebuffer, not a `PngSink`
+    assert_eq!(contents, vec![6, 5, 4, 255]);
+}
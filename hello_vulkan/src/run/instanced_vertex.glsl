@@ -0,0 +1,21 @@
+#version 450
+// per vertex, the shape in clip space around the origin
+layout(location = 0) in vec2 position;
+// per instance
+layout(location = 1) in vec2 offset;
+layout(location = 2) in float scale;
+layout(location = 3) in vec4 color;
+
+layout(location = 0) out vec4 v_color;
+
+// column major 2x2 matrix, identity unless the panel orientation
+// can't be applied by the surface
+layout(push_constant) uniform PreRotation {
+    vec4 matrix;
+} pre_rotation;
+
+void main() {
+    mat2 m = mat2(pre_rotation.matrix.xy, pre_rotation.matrix.zw);
+    gl_Position = vec4(m * (position * scale + offset), 0.0, 1.0);
+    v_color = color;
+}
@@ -1,260 +1,351 @@
+pub mod backend;
+pub mod canvas;
+pub mod compute;
+pub mod correction;
+mod debug;
+pub mod graph;
+pub mod headless;
 mod info;
-mod shaders;
+pub mod orientation;
+pub mod patterns;
+pub mod pipeline_cache;
+pub mod refresh;
+pub mod renderer;
+pub mod scene;
+pub mod shaders;
+pub mod target;
+pub mod text;
+pub mod upload;
+pub mod watchdog;
 
 #[cfg(feature = "win")]
-mod win;
+pub mod win;
 
 #[cfg(feature = "fbdev")]
-mod fbdev;
+pub mod fbdev;
 
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[allow(unused_imports)]
-use vulkano::instance::{DeviceExtensions, Features, Instance, InstanceExtensions, Limits,
-                        PhysicalDevice, QueueFamily};
+use vulkano::instance::{DeviceExtensions, Features, Instance, InstanceCreationError,
+                        InstanceExtensions, Limits, PhysicalDevice, QueueFamily};
 
-use vulkano::device::{Device, Queue};
+use vulkano::OomError;
+use vulkano::device::{Device, DeviceCreationError, Queue};
 
 #[allow(unused_imports)]
 use vulkano::format::{ClearValue, Format};
 
-// use vulkano::image::{Dimensions, StorageImage};
-
-use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
-
-#[allow(unused_imports)]
-use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, ImmutableBuffer};
-use vulkano::framebuffer::{Framebuffer, Subpass};
-
 use vulkano::instance::debug::DebugCallback;
 
-use vulkano::sync::{now, GpuFuture};
+use vulkano::pipeline::cache::PipelineCache;
 
-use vulkano::pipeline::viewport::Viewport;
-use vulkano::pipeline::GraphicsPipeline;
+use vulkano::swapchain::{Capabilities, PresentMode};
 
-use vulkano::swapchain;
-
-use vulkano::image::traits::ImageAccess;
-
-use self::shaders::Vertex;
+use config::{Config, ConfigError, DeviceConfig};
+use log_targets::INFO;
 
 use self::shaders::default_fragment_shader::Shader as FragmentShader;
 use self::shaders::default_vertex_shader::Shader as VertexShader;
 
-#[cfg(feature = "win")]
-use self::win::{create_swapchain, required_extensions, run_loop};
-
-#[cfg(feature = "fbdev")]
-use self::fbdev::{create_swapchain, required_extensions, run_loop};
-
-/*
-use ::image;
-#[allow(unused_imports)]
-use image::{ImageBuffer, Rgba};
-*/
+use self::backend::SurfaceBackend;
+use self::watchdog::{Stage, Timeouts, Watchdog};
 
 pub struct VulkanStruct {
     pub device: Arc<Device>,
     pub queue: Arc<Queue>,
+    /// of a compute-only family when there is one, else `queue`
+    pub compute_queue: Arc<Queue>,
+    /// of a transfer-only family when there is one, else `queue`
+    pub transfer_queue: Arc<Queue>,
     pub vertex_shader: VertexShader,
     pub fragment_shader: FragmentShader,
+    /// passed to every graphics pipeline, see `pipeline_cache::CachedPipeline`
+    pub pipeline_cache: Arc<PipelineCache>,
+    pub pipeline_cache_path: PathBuf,
+    pub debug_callback: Option<DebugCallback>,
+    /// how long to wait for the GPU, from `[watchdog]`
+    pub timeouts: Timeouts,
+    /// `None` when `watchdog.stall` is 0
+    pub watchdog: Option<Watchdog>,
 }
 
-pub fn create_vk_instance() -> Arc<Instance> {
+impl VulkanStruct {
+    /// tells the watchdog the calling thread waits on the GPU for `stage`
+    pub fn enter_stage(&self, stage: Stage) {
+        if let Some(ref watchdog) = self.watchdog {
+            watchdog.enter(stage);
+        }
+    }
+
+    pub fn leave_stage(&self) {
+        if let Some(ref watchdog) = self.watchdog {
+            watchdog.leave();
+        }
+    }
+
+    /// stops the watchdog thread of a lost device, which is leaked with
+    /// what still holds it
+    pub fn stop_watchdog(&self) {
+        if let Some(ref watchdog) = self.watchdog {
+            watchdog.stop();
+        }
+    }
+
+    /// call once the pipelines are created, failures are only logged
+    pub fn save_pipeline_cache(&self) {
+        if let Err(err) = pipeline_cache::save(&self.device, &self.pipeline_cache, &self.pipeline_cache_path) {
+            warn!(target: INFO, "failed to save pipeline cache: {}", err);
+        }
+    }
+}
+
+/// why `create_vk_struct` failed, all of them leave the CPU fallbacks
+#[derive(Debug)]
+pub enum VulkanInitError {
+    /// the loader or the driver is missing
+    Instance(InstanceCreationError),
+    NoDevice,
+    /// `device.index` or `device.name` matches no device
+    Config(ConfigError),
+    /// the device has no queue family for this work
+    NoQueueFamily(&'static str),
+    Device(DeviceCreationError),
+    Shader(OomError),
+    PipelineCache(OomError),
+}
+
+/// fails when the loader or the driver is missing, e.g. without the
+/// `libvulkan.so.1` symlink
+pub fn create_vk_instance(
+    validation: bool,
+    extensions: &InstanceExtensions,
+) -> Result<Arc<Instance>, InstanceCreationError> {
     let app_info = app_info_from_cargo_toml!();
     // println!("Application Info:{:?}", app_info);
-    let extensions = required_extensions();
-    Instance::new(Some(&app_info), &extensions, None).expect("failed to create Vulkan instance")
+    let extensions = extensions.union(&debug::debug_extensions());
+    let layers = debug::validation_layers(validation);
+    Instance::new(Some(&app_info), &extensions, layers.iter())
 }
 
-fn create_vk_struct() -> Arc<VulkanStruct> {
-    let instance = create_vk_instance();
+/// the configured device, else the first one
+fn select_physical_device<'a>(
+    instance: &'a Arc<Instance>,
+    config: &DeviceConfig,
+) -> Result<PhysicalDevice<'a>, VulkanInitError> {
+    if let Some(index) = config.index {
+        return PhysicalDevice::from_index(instance, index).ok_or_else(|| {
+            VulkanInitError::Config(ConfigError::invalid("device.index", format!("there is no device {}", index)))
+        });
+    }
+
+    if let Some(ref name) = config.name {
+        return PhysicalDevice::enumerate(instance)
+            .find(|device| device.name().contains(name.as_str()))
+            .ok_or_else(|| {
+                VulkanInitError::Config(ConfigError::invalid("device.name", format!("no device matches '{}'", name)))
+            });
+    }
+
+    PhysicalDevice::enumerate(instance).next().ok_or(VulkanInitError::NoDevice)
+}
+
+pub fn create_vk_struct(
+    config: &Config,
+    backend: &SurfaceBackend,
+) -> Result<Arc<VulkanStruct>, VulkanInitError> {
+    let validation = config.validation || debug::validation_requested();
+    let instance = create_vk_instance(validation, &backend.required_extensions())?;
 
-    let _callback = DebugCallback::errors_and_warnings(&instance, |msg| {
-        println!("Vulkan Debug: {:?}", msg.description);
-    }).ok();
+    let debug_callback = debug::create_debug_callback(&instance, validation);
 
     info::print_vk_info(&instance);
 
-    let physical_device = PhysicalDevice::enumerate(&instance)
-        .next()
-        .expect("No device available");
+    let physical_device = select_physical_device(&instance, &config.device)?;
 
     let queue_family = physical_device
         .queue_families()
         .find(|&q| q.supports_graphics())
-        .expect("No graphical queue family");
+        .ok_or(VulkanInitError::NoQueueFamily("graphics"))?;
+
+    // a compute-only family may run next to the graphics work
+    let compute_family = physical_device
+        .queue_families()
+        .find(|&q| q.supports_compute() && !q.supports_graphics())
+        .or_else(|| if queue_family.supports_compute() { Some(queue_family) } else { None })
+        .or_else(|| physical_device.queue_families().find(|&q| q.supports_compute()))
+        .ok_or(VulkanInitError::NoQueueFamily("compute"))?;
+    let separate_compute = compute_family.id() != queue_family.id();
+
+    // a DMA engine copying while the graphics queue renders
+    let transfer_family = physical_device
+        .queue_families()
+        .find(|&q| q.explicitly_supports_transfers() && !q.supports_graphics() && !q.supports_compute())
+        .unwrap_or(queue_family);
+    let separate_transfer = transfer_family.id() != queue_family.id();
+    info!(
+        target: INFO,
+        "graphics queue family {}, compute queue family {}, transfer queue family {}",
+        queue_family.id(),
+        compute_family.id(),
+        transfer_family.id()
+    );
 
     let (device, mut queues) = {
-        let ext = DeviceExtensions {
-            khr_swapchain: true,
-            ..DeviceExtensions::none()
+        let ext = backend.required_device_extensions();
+
+        // lets `draw_indirect` take several commands at once
+        let features = Features {
+            multi_draw_indirect: physical_device.supported_features().multi_draw_indirect,
+            ..Features::none()
         };
 
-        Device::new(
-            physical_device,
-            &Features::none(),
-            &ext,
-            [(queue_family, 0.5)].iter().cloned(),
-        ).expect("failed to create device")
+        let mut families = vec![(queue_family, 0.5)];
+        if separate_compute {
+            families.push((compute_family, 0.5));
+        }
+        if separate_transfer {
+            families.push((transfer_family, 0.5));
+        }
+
+        Device::new(physical_device, &features, &ext, families.into_iter())?
     };
 
     let queue = queues.next().expect("No queues are found");
+    let compute_queue = if separate_compute {
+        queues.next().expect("No compute queue is found")
+    } else {
+        queue.clone()
+    };
+    let transfer_queue = if separate_transfer {
+        queues.next().expect("No transfer queue is found")
+    } else {
+        queue.clone()
+    };
 
-    let vertex_shader = shaders::default_vertex_shader::Shader::load(device.clone())
-        .expect("Failed to create vertex shader module");
-    let fragment_shader = shaders::default_fragment_shader::Shader::load(device.clone())
-        .expect("Failed to create fragment shader module");
+    let vertex_shader = shaders::default_vertex_shader::Shader::load(device.clone())?;
+    let fragment_shader = shaders::default_fragment_shader::Shader::load(device.clone())?;
+
+    let pipeline_cache_path = config
+        .pipeline_cache
+        .clone()
+        .unwrap_or_else(pipeline_cache::default_path);
+    let pipeline_cache =
+        pipeline_cache::load(&device, &pipeline_cache_path).map_err(VulkanInitError::PipelineCache)?;
+
+    let watchdog = match config.watchdog.stall {
+        0 => None,
+        stall => match Watchdog::start(Duration::from_millis(stall)) {
+            Ok(watchdog) => Some(watchdog),
+            Err(err) => {
+                warn!(target: INFO, "no watchdog, GPU stalls won't be reported: {}", err);
+                None
+            }
+        },
+    };
 
     let vs = VulkanStruct {
         device: device,
         queue: queue,
+        compute_queue: compute_queue,
+        transfer_queue: transfer_queue,
         vertex_shader: vertex_shader,
         fragment_shader: fragment_shader,
+        pipeline_cache: pipeline_cache,
+        pipeline_cache_path: pipeline_cache_path,
+        debug_callback: debug_callback,
+        timeouts: Timeouts::from_config(&config.watchdog),
+        watchdog: watchdog,
     };
 
-    return Arc::<_>::new(vs);
+    Ok(Arc::<_>::new(vs))
 }
 
-pub fn run() {
-    let vulkan_obj = create_vk_struct();
+/// the configured `swapchain.format`, or the first one the surface supports
+fn choose_format(caps: &Capabilities, config: &Config) -> Result<Format, ConfigError> {
+    match config.swapchain.format() {
+        Some(format) => {
+            if !caps.supported_formats.iter().any(|&(f, _)| f == format) {
+                return Err(ConfigError::invalid(
+                    "swapchain.format",
+                    format!("{:?} is not supported by the surface", format),
+                ));
+            }
+            Ok(format)
+        }
+        None => Ok(caps.supported_formats[0].0),
+    }
+}
 
-#[cfg(feature="fbdev")]
-    info::print_all_displays(vulkan_obj.device.physical_device());
-#[cfg(feature="fbdev")]
-    info::print_all_display_plane(vulkan_obj.device.physical_device());
+/// the configured `swapchain.present_mode`, falling back to fifo which
+/// every driver has to support
+fn choose_present_mode(caps: &Capabilities, config: &Config) -> PresentMode {
+    let mode = config.swapchain.present_mode.to_present_mode();
+    let supported = match mode {
+        PresentMode::Immediate => caps.present_modes.immediate,
+        PresentMode::Mailbox => caps.present_modes.mailbox,
+        PresentMode::Fifo => caps.present_modes.fifo,
+        PresentMode::Relaxed => caps.present_modes.relaxed,
+    };
 
-#[cfg(feature = "win")]
-    let (swap_chain, images, mut events_loop) = create_swapchain(vulkan_obj.clone()).unwrap();
+    if supported {
+        mode
+    } else {
+        warn!(
+            target: INFO,
+            "`swapchain.present_mode`: {:?} is not supported, using fifo",
+            mode
+        );
+        PresentMode::Fifo
+    }
+}
 
-#[cfg(feature = "fbdev")]
-    let (swap_chain, images) = create_swapchain(vulkan_obj.clone()).unwrap();
-
-    /*
-    let image = StorageImage::new(
-        device.clone(),
-        Dimensions::Dim2d {
-            width: 1024,
-            height: 1024,
-        },
-        Format::R8G8B8A8Unorm,
-        Some(queue_family),
-    ).unwrap();
-    */
+impl From<InstanceCreationError> for VulkanInitError {
+    fn from(err: InstanceCreationError) -> VulkanInitError {
+        VulkanInitError::Instance(err)
+    }
+}
 
-    let (image_index, swapchain_acquire_future) =
-        swapchain::acquire_next_image(swap_chain.clone(), None).unwrap();
-    let image = images[image_index].clone();
+impl From<DeviceCreationError> for VulkanInitError {
+    fn from(err: DeviceCreationError) -> VulkanInitError {
+        VulkanInitError::Device(err)
+    }
+}
 
-    let vertex1 = Vertex {
-        position: [-0.5, -0.5],
-    };
-    let vertex2 = Vertex {
-        position: [0.0, 0.5],
-    };
-    let vertex3 = Vertex {
-        position: [0.5, -0.25],
-    };
+impl From<OomError> for VulkanInitError {
+    fn from(err: OomError) -> VulkanInitError {
+        VulkanInitError::Shader(err)
+    }
+}
 
-    let render_pass = Arc::new(
-        single_pass_renderpass!(vulkan_obj.device.clone(),
-    attachments: {
-        color: {
-            load: Clear,
-            store: Store,
-            format: image.format(),
-            // format: Format::B8G8R8A8Srgb, // for Linux Intel GPU
-            // R8G8B8A8Unorm is not supported under Linux Intel driver
-            // format: Format::R8G8B8A8Unorm, // for Windows Intel GPU
-            // format: Format::B8G8R8A8Unorm, // for rk3288 ARM Mali T-76x fbdev
-            samples: 1,
+impl fmt::Display for VulkanInitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VulkanInitError::Instance(ref err) => write!(f, "failed to create the instance: {}", err),
+            VulkanInitError::NoDevice => write!(f, "no Vulkan device available"),
+            VulkanInitError::Config(ref err) => write!(f, "{}", err),
+            VulkanInitError::NoQueueFamily(kind) => write!(f, "the device has no {} queue family", kind),
+            VulkanInitError::Device(ref err) => write!(f, "failed to create the device: {}", err),
+            VulkanInitError::Shader(ref err) => write!(f, "failed to load the shaders: {}", err),
+            VulkanInitError::PipelineCache(ref err) => write!(f, "failed to create the pipeline cache: {}", err),
         }
-    },
-    pass: {
-        color: [color],
-        depth_stencil: {}
-    }).unwrap(),
-    );
-
-    let framebuffer = Arc::new(
-        Framebuffer::start(render_pass.clone())
-            .add(image.clone())
-            .unwrap()
-            .build()
-            .unwrap(),
-    );
-
-    let pipeline = Arc::new(
-        GraphicsPipeline::start()
-            .vertex_input_single_buffer::<Vertex>()
-            .vertex_shader(vulkan_obj.vertex_shader.main_entry_point(), ())
-            .viewports_dynamic_scissors_irrelevant(1)
-            .fragment_shader(vulkan_obj.fragment_shader.main_entry_point(), ())
-            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
-            .build(vulkan_obj.device.clone())
-            .unwrap(),
-    );
-
-    let dynamic_state = DynamicState {
-        viewports: Some(vec![Viewport {
-            origin: [0.0, 0.0],
-            dimensions: [1024.0, 1024.0],
-            depth_range: 0.0..1.0,
-        }]),
-        ..DynamicState::none()
-    };
+    }
+}
 
-    let vertices = vec![vertex1, vertex2, vertex3];
-    let (vertex_buffer, vertex_buffer_future) = ImmutableBuffer::from_iter(
-        vertices.into_iter(),
-        BufferUsage::all(),
-        vulkan_obj.queue.clone(),
-    ).unwrap();
-
-    let f = vertex_buffer_future.then_signal_fence_and_flush().unwrap();
-    f.wait(None).unwrap();
-
-    let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(vulkan_obj.device.clone(), vulkan_obj.queue.family())
-            .unwrap()
-            .begin_render_pass(
-                framebuffer.clone(),
-                false,
-                vec![[0.0, 0.0, 1.0, 1.0].into()],
-            )
-            .unwrap()
-            .draw(
-                pipeline.clone(),
-                dynamic_state,
-                vertex_buffer.clone(),
-                (),
-                (),
-            )
-            .unwrap()
-            .end_render_pass()
-            .unwrap()
-            // .copy_image_to_buffer(image.clone(), buffer.clone())
-            // .unwrap()
-            .build()
-            .unwrap();
-
-    let mut previous_frame_end = Box::new(now(vulkan_obj.device.clone())) as Box<GpuFuture>;
-
-    previous_frame_end.cleanup_finished();
-
-    let future = previous_frame_end
-        .join(swapchain_acquire_future)
-        .then_execute(vulkan_obj.queue.clone(), command_buffer)
-        .unwrap()
-        .then_swapchain_present(vulkan_obj.queue.clone(), swap_chain.clone(), image_index)
-        .then_signal_fence_and_flush();
-
-    let _future = future.unwrap();
-    // swapchain::present(swap_chain, finished, queue.clone(), image_index);
-
-    #[cfg(feature = "win")]
-    run_loop(&mut events_loop);
-
-    #[cfg(feature = "fbdev")]
-    run_loop();
+impl Error for VulkanInitError {
+    fn description(&self) -> &str {
+        match *self {
+            VulkanInitError::Instance(_) => "failed to create the instance",
+            VulkanInitError::NoDevice => "no Vulkan device available",
+            VulkanInitError::Config(ref err) => err.description(),
+            VulkanInitError::NoQueueFamily(_) => "missing queue family",
+            VulkanInitError::Device(_) => "failed to create the device",
+            VulkanInitError::Shader(_) => "failed to load the shaders",
+            VulkanInitError::PipelineCache(_) => "failed to create the pipeline cache",
+        }
+    }
 }
@@ -0,0 +1,218 @@
+//! Panel rotation and mirroring
+//!
+//! The configured orientation is applied by the presentation engine when
+//! the surface supports the matching `SurfaceTransform`; the swapchain then
+//! gets the extent of the upright content. Otherwise the swapchain keeps the
+//! panel's extent and the default vertex shader pre-rotates with the matrix
+//! returned by `Renderer::pre_rotation()`.
+//!
+//! Positions are clip space with y pointing down, rotations are clockwise.
+
+use vulkano::swapchain::{Capabilities, SurfaceTransform};
+
+use config::{ConfigError, OrientationConfig};
+use log_targets::INFO;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+/// mirrors horizontally first, then rotates, like `SurfaceTransform`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    pub mirror: bool,
+}
+
+/// how an `Orientation` is split between the surface and the shader
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AppliedOrientation {
+    pub orientation: Orientation,
+    pub surface_transform: SurfaceTransform,
+    /// the presentation engine does the whole transform
+    pub by_surface: bool,
+}
+
+impl Rotation {
+    /// `(cos, sin)`
+    fn cos_sin(self) -> (f32, f32) {
+        match self {
+            Rotation::Deg0 => (1.0, 0.0),
+            Rotation::Deg90 => (0.0, 1.0),
+            Rotation::Deg180 => (-1.0, 0.0),
+            Rotation::Deg270 => (0.0, -1.0),
+        }
+    }
+}
+
+impl Orientation {
+    pub fn identity() -> Orientation {
+        Orientation {
+            rotation: Rotation::Deg0,
+            mirror: false,
+        }
+    }
+
+    /// also what `Config::validate` checks `[orientation]` with
+    pub fn from_config(config: &OrientationConfig) -> Result<Orientation, ConfigError> {
+        let rotation = match config.rotation {
+            0 => Rotation::Deg0,
+            90 => Rotation::Deg90,
+            180 => Rotation::Deg180,
+            270 => Rotation::Deg270,
+            _ => return Err(ConfigError::invalid("orientation.rotation", "must be 0, 90, 180 or 270")),
+        };
+        Ok(Orientation {
+            rotation: rotation,
+            mirror: config.mirror,
+        })
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Orientation::identity()
+    }
+
+    /// true when width and height trade places
+    pub fn swaps_extent(&self) -> bool {
+        self.rotation == Rotation::Deg90 || self.rotation == Rotation::Deg270
+    }
+
+    /// the extent of `extent` once transformed
+    pub fn transform_extent(&self, extent: [u32; 2]) -> [u32; 2] {
+        if self.swaps_extent() {
+            [extent[1], extent[0]]
+        } else {
+            extent
+        }
+    }
+
+    /// the transform undoing this one
+    pub fn inverse(&self) -> Orientation {
+        // a mirrored rotation is its own inverse
+        let rotation = match (self.rotation, self.mirror) {
+            (Rotation::Deg90, false) => Rotation::Deg270,
+            (Rotation::Deg270, false) => Rotation::Deg90,
+            (rotation, _) => rotation,
+        };
+        Orientation {
+            rotation: rotation,
+            mirror: self.mirror,
+        }
+    }
+
+    pub fn surface_transform(&self) -> SurfaceTransform {
+        match (self.rotation, self.mirror) {
+            (Rotation::Deg0, false) => SurfaceTransform::Identity,
+            (Rotation::Deg90, false) => SurfaceTransform::Rotate90,
+            (Rotation::Deg180, false) => SurfaceTransform::Rotate180,
+            (Rotation::Deg270, false) => SurfaceTransform::Rotate270,
+            (Rotation::Deg0, true) => SurfaceTransform::HorizontalMirror,
+            (Rotation::Deg90, true) => SurfaceTransform::HorizontalMirrorRotate90,
+            (Rotation::Deg180, true) => SurfaceTransform::HorizontalMirrorRotate180,
+            (Rotation::Deg270, true) => SurfaceTransform::HorizontalMirrorRotate270,
+        }
+    }
+
+    /// column major 2x2 matrix, as a `vec4` for the vertex shader
+    pub fn matrix(&self) -> [f32; 4] {
+        let (cos, sin) = self.rotation.cos_sin();
+        let flip = if self.mirror { -1.0 } else { 1.0 };
+        [cos * flip, sin * flip, -sin, cos]
+    }
+
+    /// transforms a clip space position
+    pub fn apply(&self, position: [f32; 2]) -> [f32; 2] {
+        let m = self.matrix();
+        [
+            m[0] * position[0] + m[2] * position[1],
+            m[1] * position[0] + m[3] * position[1],
+        ]
+    }
+
+    /// maps a position in pixels on the panel, of size `panel`, back to
+    /// pixels in the upright content
+    pub fn map_input(&self, position: [f32; 2], panel: [u32; 2]) -> [f32; 2] {
+        let clip = [
+            position[0] / panel[0] as f32 * 2.0 - 1.0,
+            position[1] / panel[1] as f32 * 2.0 - 1.0,
+        ];
+        // the matrix is orthogonal, its transpose is its inverse
+        let m = self.matrix();
+        let upright = [
+            m[0] * clip[0] + m[1] * clip[1],
+            m[2] * clip[0] + m[3] * clip[1],
+        ];
+        let size = self.transform_extent(panel);
+        [
+            (upright[0] + 1.0) * 0.5 * size[0] as f32,
+            (upright[1] + 1.0) * 0.5 * size[1] as f32,
+        ]
+    }
+}
+
+impl AppliedOrientation {
+    /// everything done by the vertex shader, for targets without a surface
+    pub fn pre_rotated(orientation: Orientation) -> AppliedOrientation {
+        AppliedOrientation {
+            orientation: orientation,
+            surface_transform: SurfaceTransform::Identity,
+            by_surface: false,
+        }
+    }
+
+    /// uses the surface when it supports the transform
+    pub fn choose(caps: &Capabilities, orientation: Orientation) -> AppliedOrientation {
+        if orientation.is_identity() {
+            return AppliedOrientation::pre_rotated(orientation);
+        }
+
+        // the presentation engine undoes `preTransform`, ask for the inverse
+        let transform = orientation.inverse().surface_transform();
+        if caps.supported_transforms.supports(transform) {
+            info!(target: INFO, "orientation {:?} applied by the surface", orientation);
+            AppliedOrientation {
+                orientation: orientation,
+                surface_transform: transform,
+                by_surface: true,
+            }
+        } else {
+            info!(
+                target: INFO,
+                "surface doesn't support {:?}, pre-rotating in the vertex shader",
+                transform
+            );
+            AppliedOrientation::pre_rotated(orientation)
+        }
+    }
+
+    /// what the vertex shader still has to do
+    pub fn pre_rotation(&self) -> Orientation {
+        if self.by_surface {
+            Orientation::identity()
+        } else {
+            self.orientation
+        }
+    }
+
+    /// swapchain extent for a surface of `surface_extent`
+    pub fn image_extent(&self, surface_extent: [u32; 2]) -> [u32; 2] {
+        if self.by_surface {
+            self.orientation.transform_extent(surface_extent)
+        } else {
+            surface_extent
+        }
+    }
+
+    /// panel extent for images of `image_extent`
+    pub fn panel_extent(&self, image_extent: [u32; 2]) -> [u32; 2] {
+        // rotating by 90 or 270 swaps the extent back as well
+        self.image_extent(image_extent)
+    }
+}
@@ -0,0 +1,124 @@
+use super::{AppliedOrientation, Orientation, Rotation};
+
+use config::{Config, ConfigError, OrientationConfig};
+
+const PANEL: [u32; 2] = [640, 480];
+
+fn all_orientations() -> Vec<Orientation> {
+    let mut orientations = Vec::new();
+    for &mirror in &[false, true] {
+        for &rotation in &[Rotation::Deg0, Rotation::Deg90, Rotation::Deg180, Rotation::Deg270] {
+            orientations.push(Orientation {
+                rotation: rotation,
+                mirror: mirror,
+            });
+        }
+    }
+    orientations
+}
+
+fn assert_near(actual: [f32; 2], expected: [f32; 2], orientation: Orientation) {
+    assert!(
+        (actual[0] - expected[0]).abs() < 1e-3 && (actual[1] - expected[1]).abs() < 1e-3,
+        "{:?} != {:?} for {:?}",
+        actual,
+        expected,
+        orientation
+    );
+}
+
+fn to_clip(position: [f32; 2], size: [u32; 2]) -> [f32; 2] {
+    [
+        position[0] / size[0] as f32 * 2.0 - 1.0,
+        position[1] / size[1] as f32 * 2.0 - 1.0,
+    ]
+}
+
+fn to_pixels(clip: [f32; 2], size: [u32; 2]) -> [f32; 2] {
+    [
+        (clip[0] + 1.0) * 0.5 * size[0] as f32,
+        (clip[1] + 1.0) * 0.5 * size[1] as f32,
+    ]
+}
+
+#[test]
+fn rotations_are_clockwise_with_y_down() {
+    let quarter = Orientation {
+        rotation: Rotation::Deg90,
+        mirror: false,
+    };
+    // the top left corner of the content ends up top right
+    assert_near(quarter.apply([-1.0, -1.0]), [1.0, -1.0], quarter);
+
+    let mirrored = Orientation {
+        rotation: Rotation::Deg0,
+        mirror: true,
+    };
+    assert_near(mirrored.apply([-1.0, -0.5]), [1.0, -0.5], mirrored);
+}
+
+#[test]
+fn input_maps_back_to_what_was_drawn() {
+    for orientation in all_orientations() {
+        let content = orientation.transform_extent(PANEL);
+        for &position in &[[0.0, 0.0], [100.0, 50.0], [content[0] as f32, content[1] as f32], [10.0, 300.0]] {
+            let on_panel = to_pixels(orientation.apply(to_clip(position, content)), PANEL);
+            assert_near(orientation.map_input(on_panel, PANEL), position, orientation);
+        }
+    }
+}
+
+#[test]
+fn inverse_undoes_the_orientation() {
+    for orientation in all_orientations() {
+        let inverse = orientation.inverse();
+        for &position in &[[0.0, 0.0], [1.0, -1.0], [0.25, 0.75], [-0.5, 0.1]] {
+            assert_near(inverse.apply(orientation.apply(position)), position, orientation);
+            assert_near(orientation.apply(inverse.apply(position)), position, orientation);
+        }
+        assert_eq!(inverse.inverse(), orientation);
+    }
+}
+
+#[test]
+fn quarter_turns_swap_the_extent() {
+    for orientation in all_orientations() {
+        let swapped = orientation.rotation == Rotation::Deg90 || orientation.rotation == Rotation::Deg270;
+        assert_eq!(orientation.swaps_extent(), swapped);
+        assert_eq!(orientation.inverse().swaps_extent(), swapped);
+
+        let extent = orientation.transform_extent(PANEL);
+        assert_eq!(extent, if swapped { [480, 640] } else { PANEL });
+        assert_eq!(orientation.transform_extent(extent), PANEL);
+
+        // the vertex shader rotates inside images of the panel's extent
+        let pre_rotated = AppliedOrientation::pre_rotated(orientation);
+        assert_eq!(pre_rotated.image_extent(PANEL), PANEL);
+        assert_eq!(pre_rotated.panel_extent(PANEL), PANEL);
+    }
+}
+
+#[test]
+fn only_quarter_turns_are_accepted() {
+    for &degrees in &[0, 90, 180, 270] {
+        let config = OrientationConfig {
+            rotation: degrees,
+            mirror: true,
+        };
+        let orientation = Orientation::from_config(&config).unwrap();
+        assert!(orientation.mirror);
+    }
+
+    for &degrees in &[45, 360, 450] {
+        let mut config = Config::default();
+        config.orientation.rotation = degrees;
+        match Orientation::from_config(&config.orientation) {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "orientation.rotation"),
+            other => panic!("{} degrees: {:?}", degrees, other),
+        }
+        match config.validate() {
+            Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "orientation.rotation"),
+            other => panic!("{} degrees: {:?}", degrees, other),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+#version 450
+layout(location = 0) out vec4 f_color;
+
+// keep in sync with `Pattern::index`
+const int COLOR_BARS = 0;
+const int GREY_RAMP = 1;
+const int CHECKERBOARD = 2;
+const int PIXEL_GRID = 3;
+const int SOLID = 4;
+const int MOVING_BAR = 5;
+
+layout(push_constant) uniform PatternParams {
+    int pattern;
+    // seconds since the pattern mode started
+    float time;
+    // framebuffer size in pixels
+    vec2 size;
+    vec4 color;
+} params;
+
+vec3 color_bars(vec2 uv) {
+    // 75% bars: white, yellow, cyan, green, magenta, red, blue
+    const vec3 bars[7] = vec3[7](
+        vec3(0.75, 0.75, 0.75), vec3(0.75, 0.75, 0.0), vec3(0.0, 0.75, 0.75),
+        vec3(0.0, 0.75, 0.0), vec3(0.75, 0.0, 0.75), vec3(0.75, 0.0, 0.0),
+        vec3(0.0, 0.0, 0.75));
+    // castellations: blue, black, magenta, black, cyan, black, grey
+    const vec3 reverse[7] = vec3[7](
+        vec3(0.0, 0.0, 0.75), vec3(0.075), vec3(0.75, 0.0, 0.75), vec3(0.075),
+        vec3(0.0, 0.75, 0.75), vec3(0.075), vec3(0.75, 0.75, 0.75));
+
+    int bar = min(int(uv.x * 7.0), 6);
+    if (uv.y < 0.67) {
+        return bars[bar];
+    }
+    if (uv.y < 0.75) {
+        return reverse[bar];
+    }
+
+    // -I, white, +Q, black, then the pluge below, at and above black
+    float x = uv.x * 7.0;
+    if (x < 1.25) {
+        return vec3(0.0, 0.13, 0.30);
+    }
+    if (x < 2.5) {
+        return vec3(1.0);
+    }
+    if (x < 3.75) {
+        return vec3(0.20, 0.0, 0.42);
+    }
+    if (x < 5.0) {
+        return vec3(0.075);
+    }
+    if (x < 5.33) {
+        return vec3(0.035);
+    }
+    if (x < 5.67) {
+        return vec3(0.075);
+    }
+    if (x < 6.0) {
+        return vec3(0.115);
+    }
+    return vec3(0.075);
+}
+
+vec3 grey_ramp(vec2 uv) {
+    // smooth on the top half, 16 steps on the bottom half
+    if (uv.y < 0.5) {
+        return vec3(uv.x);
+    }
+    return vec3(floor(uv.x * 16.0) / 15.0);
+}
+
+vec3 checkerboard(vec2 pixel) {
+    ivec2 cell = ivec2(pixel) / 32;
+    return vec3(float((cell.x + cell.y) & 1));
+}
+
+vec3 pixel_grid(vec2 pixel) {
+    ivec2 p = ivec2(pixel);
+    ivec2 last = ivec2(params.size) - 1;
+    bool border = p.x == 0 || p.y == 0 || p.x == last.x || p.y == last.y;
+    bool line = p.x % 8 == 0 || p.y % 8 == 0;
+    if (border) {
+        return vec3(1.0, 0.0, 0.0);
+    }
+    return vec3(line ? 1.0 : 0.0);
+}
+
+vec3 moving_bar(vec2 pixel) {
+    // crosses the screen every two seconds, tearing shows as a break
+    float width = 64.0;
+    float x = mod(params.time * (params.size.x + width) / 2.0, params.size.x + width) - width;
+    return vec3(pixel.x >= x && pixel.x < x + width ? 1.0 : 0.0);
+}
+
+void main() {
+    vec2 pixel = gl_FragCoord.xy;
+    vec2 uv = pixel / params.size;
+    vec3 color;
+    if (params.pattern == COLOR_BARS) {
+        color = color_bars(uv);
+    } else if (params.pattern == GREY_RAMP) {
+        color = grey_ramp(uv);
+    } else if (params.pattern == CHECKERBOARD) {
+        color = checkerboard(pixel);
+    } else if (params.pattern == PIXEL_GRID) {
+        color = pixel_grid(pixel);
+    } else if (params.pattern == SOLID) {
+        color = params.color.rgb;
+    } else {
+        color = moving_bar(pixel);
+    }
+    f_color = vec4(color, 1.0);
+}
@@ -0,0 +1,140 @@
+//! Test patterns for panel bring-up
+//!
+//! Drawn by a fragment shader over one triangle covering the viewport, in
+//! framebuffer pixels: the patterns show what the panel receives, the
+//! configured orientation is not applied.
+
+use std::sync::Arc;
+
+use vulkano::pipeline::vertex::{BufferlessDefinition, BufferlessVertices};
+
+use super::pipeline_cache::{Blend, CachedPipeline};
+use super::renderer::{BufferlessPipeline, Frame, RenderError, Renderer};
+use super::shaders::fullscreen_vertex_shader::Shader as VertexShader;
+use super::shaders::pattern_fragment_shader::Shader as FragmentShader;
+use super::shaders::pattern_fragment_shader::ty::PatternParams;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Pattern {
+    /// SMPTE colour bars
+    ColorBars,
+    /// smooth and 16 step greyscale
+    GreyRamp,
+    /// 32 pixel squares
+    Checkerboard,
+    /// one pixel lines every 8 pixels, with a red border on the outermost
+    /// pixels to check for overscan
+    PixelGrid,
+    Solid([f32; 4]),
+    /// a vertical bar crossing the screen, reveals tearing
+    MovingBar,
+}
+
+pub struct PatternRenderer {
+    pipeline: Arc<BufferlessPipeline>,
+}
+
+/// in cycling order
+const PATTERNS: &[(&str, Pattern)] = &[
+    ("bars", Pattern::ColorBars),
+    ("ramp", Pattern::GreyRamp),
+    ("checkerboard", Pattern::Checkerboard),
+    ("grid", Pattern::PixelGrid),
+    ("white", Pattern::Solid([1.0, 1.0, 1.0, 1.0])),
+    ("red", Pattern::Solid([1.0, 0.0, 0.0, 1.0])),
+    ("green", Pattern::Solid([0.0, 1.0, 0.0, 1.0])),
+    ("blue", Pattern::Solid([0.0, 0.0, 1.0, 1.0])),
+    ("black", Pattern::Solid([0.0, 0.0, 0.0, 1.0])),
+    ("moving-bar", Pattern::MovingBar),
+];
+
+impl Pattern {
+    pub fn names() -> Vec<&'static str> {
+        PATTERNS.iter().map(|&(name, _)| name).collect()
+    }
+
+    pub fn from_name(name: &str) -> Option<Pattern> {
+        PATTERNS
+            .iter()
+            .find(|&&(n, _)| n == name)
+            .map(|&(_, pattern)| pattern)
+    }
+
+    pub fn name(&self) -> &'static str {
+        PATTERNS[self.position()].0
+    }
+
+    /// the following pattern, wrapping around
+    pub fn next(&self) -> Pattern {
+        PATTERNS[(self.position() + 1) % PATTERNS.len()].1
+    }
+
+    pub fn previous(&self) -> Pattern {
+        PATTERNS[(self.position() + PATTERNS.len() - 1) % PATTERNS.len()].1
+    }
+
+    fn position(&self) -> usize {
+        PATTERNS
+            .iter()
+            .position(|&(_, pattern)| pattern == *self)
+            .unwrap_or(0)
+    }
+
+    /// the constants in `pattern_fragment.glsl`
+    fn index(&self) -> i32 {
+        match *self {
+            Pattern::ColorBars => 0,
+            Pattern::GreyRamp => 1,
+            Pattern::Checkerboard => 2,
+            Pattern::PixelGrid => 3,
+            Pattern::Solid(_) => 4,
+            Pattern::MovingBar => 5,
+        }
+    }
+}
+
+impl PatternRenderer {
+    pub fn new(renderer: &Renderer) -> Result<PatternRenderer, RenderError> {
+        let device = renderer.vulkan_obj().device.clone();
+        let vertex_shader = VertexShader::load(device.clone())?;
+        let fragment_shader = FragmentShader::load(device)?;
+
+        let pipeline = CachedPipeline::new(
+            renderer.vulkan_obj(),
+            BufferlessDefinition,
+            vertex_shader.main_entry_point(),
+            fragment_shader.main_entry_point(),
+            Blend::Opaque,
+            renderer.subpass(),
+        )?;
+
+        Ok(PatternRenderer {
+            pipeline: Arc::new(pipeline),
+        })
+    }
+
+    /// `time` in seconds animates the moving bar
+    pub fn draw(
+        &self,
+        renderer: &Renderer,
+        frame: &mut Frame,
+        pattern: Pattern,
+        time: f32,
+    ) -> Result<(), RenderError> {
+        let dimensions = renderer.scene_dimensions();
+        let params = PatternParams {
+            pattern: pattern.index(),
+            time: time,
+            size: [dimensions[0] as f32, dimensions[1] as f32],
+            color: match pattern {
+                Pattern::Solid(color) => color,
+                _ => [0.0; 4],
+            },
+        };
+        let vertices = BufferlessVertices {
+            vertices: 3,
+            instances: 1,
+        };
+        renderer.draw(frame, self.pipeline.clone(), vertices, (), params)
+    }
+}
@@ -0,0 +1,215 @@
+//! Pipeline cache persisted between runs
+//!
+//! The driver's cache data is stored behind a small header with the
+//! physical device's pipeline cache UUID, vendor/device id and driver
+//! version. A file written by another device or driver is discarded and
+//! the cache starts empty, the Mali driver doesn't always reject stale
+//! data by itself.
+//!
+//! The pipeline builders of vulkano 0.9 pass a null cache handle, the
+//! graphics pipelines are created with `CachedPipeline` instead, which
+//! hands the cache to `vkCreateGraphicsPipelines`. The cache is saved once
+//! they are created, see `VulkanStruct::save_pipeline_cache`.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use vulkano::OomError;
+use vulkano::device::Device;
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::cache::PipelineCache;
+
+use log_targets::INFO;
+
+mod pipeline;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::pipeline::{Blend, CachedPipeline, PipelineError};
+
+/// overrides the location of the cache file
+pub const PATH_ENV: &str = "SLICER_PIPELINE_CACHE";
+
+const MAGIC: &[u8; 4] = b"SLPC";
+const FORMAT_VERSION: u32 = 1;
+/// magic + format version + uuid + vendor id + device id + driver version + data length
+const HEADER_LEN: usize = 4 + 4 + 16 + 4 + 4 + 4 + 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub uuid: [u8; 16],
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub driver_version: u32,
+}
+
+impl CacheKey {
+    pub fn from_physical_device(physical_device: &PhysicalDevice) -> CacheKey {
+        CacheKey {
+            uuid: *physical_device.uuid(),
+            vendor_id: physical_device.pci_vendor_id(),
+            device_id: physical_device.pci_device_id(),
+            driver_version: physical_device.driver_version(),
+        }
+    }
+}
+
+/// `$SLICER_PIPELINE_CACHE`, else `$XDG_CACHE_HOME/slicer/pipeline.cache`,
+/// else `$HOME/.cache/slicer/pipeline.cache`
+pub fn default_path() -> PathBuf {
+    if let Some(path) = env::var_os(PATH_ENV) {
+        return PathBuf::from(path);
+    }
+
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("slicer").join("pipeline.cache")
+}
+
+/// loads the cache stored at `path`, or creates an empty one when the
+/// file is missing, corrupted or written for another device/driver
+pub fn load(device: &Arc<Device>, path: &Path) -> Result<Arc<PipelineCache>, OomError> {
+    let key = CacheKey::from_physical_device(&device.physical_device());
+
+    match read_cache_file(path, &key) {
+        Ok(Some(data)) => {
+            // the header check above makes sure the data was produced
+            // by this device and driver
+            match unsafe { PipelineCache::with_data(device.clone(), &data) } {
+                Ok(cache) => {
+                    info!(
+                        target: INFO,
+                        "pipeline cache loaded from {} ({} bytes)",
+                        path.display(),
+                        data.len()
+                    );
+                    return Ok(cache);
+                }
+                Err(err) => warn!(
+                    target: INFO,
+                    "pipeline cache {} rejected by the driver: {:?}",
+                    path.display(),
+                    err
+                ),
+            }
+        }
+        Ok(None) => info!(
+            target: INFO,
+            "pipeline cache {} does not match the device or driver, invalidated",
+            path.display()
+        ),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            info!(target: INFO, "no pipeline cache at {}", path.display())
+        }
+        Err(err) => warn!(
+            target: INFO,
+            "failed to read pipeline cache {}: {}",
+            path.display(),
+            err
+        ),
+    }
+
+    PipelineCache::empty(device.clone())
+}
+
+/// writes the driver's data of `cache` to `path`
+pub fn save(device: &Arc<Device>, cache: &PipelineCache, path: &Path) -> io::Result<()> {
+    let data = cache
+        .get_data()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?;
+    let key = CacheKey::from_physical_device(&device.physical_device());
+    write_cache_file(path, &key, &data)?;
+
+    info!(
+        target: INFO,
+        "pipeline cache saved to {} ({} bytes)",
+        path.display(),
+        data.len()
+    );
+    Ok(())
+}
+
+/// writes next to `path` first and renames it over, so a power cut on
+/// the board never leaves a truncated cache behind
+pub fn write_cache_file(path: &Path, key: &CacheKey, data: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&encode_header(key, data.len()))?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+/// `Ok(None)` when the file exists but doesn't belong to `key`, or is
+/// truncated
+pub fn read_cache_file(path: &Path, key: &CacheKey) -> io::Result<Option<Vec<u8>>> {
+    let mut contents = Vec::new();
+    File::open(path)?.read_to_end(&mut contents)?;
+
+    if contents.len() < HEADER_LEN {
+        return Ok(None);
+    }
+
+    let (header, data) = contents.split_at(HEADER_LEN);
+    match decode_header(header) {
+        Some((ref stored, len)) if stored == key && len == data.len() as u64 => {
+            Ok(Some(data.to_vec()))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn encode_header(key: &CacheKey, data_len: usize) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&u32_to_le(FORMAT_VERSION));
+    header.extend_from_slice(&key.uuid);
+    header.extend_from_slice(&u32_to_le(key.vendor_id));
+    header.extend_from_slice(&u32_to_le(key.device_id));
+    header.extend_from_slice(&u32_to_le(key.driver_version));
+    header.extend_from_slice(&u32_to_le(data_len as u32));
+    header.extend_from_slice(&u32_to_le((data_len as u64 >> 32) as u32));
+    header
+}
+
+fn decode_header(header: &[u8]) -> Option<(CacheKey, u64)> {
+    if &header[0..4] != MAGIC || u32_from_le(&header[4..8]) != FORMAT_VERSION {
+        return None;
+    }
+
+    let mut uuid = [0u8; 16];
+    uuid.copy_from_slice(&header[8..24]);
+    let key = CacheKey {
+        uuid: uuid,
+        vendor_id: u32_from_le(&header[24..28]),
+        device_id: u32_from_le(&header[28..32]),
+        driver_version: u32_from_le(&header[32..36]),
+    };
+    let len = u32_from_le(&header[36..40]) as u64 | (u32_from_le(&header[40..44]) as u64) << 32;
+    Some((key, len))
+}
+
+fn u32_to_le(value: u32) -> [u8; 4] {
+    [
+        value as u8,
+        (value >> 8) as u8,
+        (value >> 16) as u8,
+        (value >> 24) as u8,
+    ]
+}
+
+fn u32_from_le(bytes: &[u8]) -> u32 {
+    bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24
+}
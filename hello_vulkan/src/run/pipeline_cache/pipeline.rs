@@ -0,0 +1,451 @@
+//! Graphics pipelines created with the pipeline cache
+//!
+//! Every pipeline of slicer has the same fixed state: triangle lists, no
+//! culling, one dynamic viewport, no depth, and opaque or alpha blended
+//! colour attachments. `CachedPipeline::new` fills that in and calls
+//! `vkCreateGraphicsPipelines` itself with the cache handle, then stands
+//! in for vulkano's `GraphicsPipeline` wherever one is drawn with.
+
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+
+use vk_sys as vk;
+
+use vulkano::VulkanObject;
+use vulkano::buffer::BufferAccess;
+use vulkano::descriptor::descriptor::DescriptorDesc;
+use vulkano::descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+use vulkano::descriptor::pipeline_layout::{PipelineLayout, PipelineLayoutAbstract, PipelineLayoutCreationError,
+                                           PipelineLayoutDesc, PipelineLayoutDescPcRange,
+                                           PipelineLayoutDescUnion, PipelineLayoutSys};
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::format::ClearValue;
+use vulkano::framebuffer::{LayoutAttachmentDescription, LayoutPassDependencyDescription, LayoutPassDescription,
+                           RenderPassAbstract, RenderPassDesc, RenderPassDescClearValues, RenderPassSys,
+                           Subpass};
+use vulkano::pipeline::{GraphicsPipelineAbstract, GraphicsPipelineSys};
+use vulkano::pipeline::shader::{EntryPointAbstract, GraphicsEntryPointAbstract};
+use vulkano::pipeline::vertex::{IncompatibleVertexDefinitionError, InputRate, VertexDefinition, VertexSource};
+
+use super::super::VulkanStruct;
+
+/// how the colour attachments are written
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Blend {
+    Opaque,
+    /// over what is there, by the source alpha
+    Alpha,
+}
+
+#[derive(Debug)]
+pub enum PipelineError {
+    Layout(PipelineLayoutCreationError),
+    /// the vertex definition doesn't match the vertex shader's input
+    VertexInput(IncompatibleVertexDefinitionError),
+    /// the `VkResult` of `vkCreateGraphicsPipelines`
+    Creation(vk::Result),
+}
+
+/// a graphics pipeline for `subpass`, taking vertices as `V` describes
+pub struct CachedPipeline<V> {
+    pipeline: vk::Pipeline,
+    device: Arc<Device>,
+    layout: Box<PipelineLayoutAbstract + Send + Sync>,
+    subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    vertex_input: V,
+}
+
+impl<V> CachedPipeline<V> {
+    pub fn new<Vs, Fs>(
+        vulkan_obj: &VulkanStruct,
+        vertex_input: V,
+        vertex_shader: Vs,
+        fragment_shader: Fs,
+        blend: Blend,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Result<CachedPipeline<V>, PipelineError>
+    where
+        V: VertexDefinition<Vs::InputDefinition>,
+        Vs: GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+        Fs: GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+        Vs::PipelineLayout: Clone + Send + Sync + 'static,
+        Fs::PipelineLayout: Clone + Send + Sync + 'static,
+    {
+        let device = vulkan_obj.device.clone();
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineLayoutDescUnion::new(vertex_shader.layout().clone(), fragment_shader.layout().clone()),
+        )?;
+
+        let stages = [
+            vk::PipelineShaderStageCreateInfo {
+                sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
+                pNext: ptr::null(),
+                flags: 0,
+                stage: vk::SHADER_STAGE_VERTEX_BIT,
+                module: vertex_shader.module().internal_object(),
+                pName: vertex_shader.name().as_ptr(),
+                pSpecializationInfo: ptr::null(),
+            },
+            vk::PipelineShaderStageCreateInfo {
+                sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
+                pNext: ptr::null(),
+                flags: 0,
+                stage: vk::SHADER_STAGE_FRAGMENT_BIT,
+                module: fragment_shader.module().internal_object(),
+                pName: fragment_shader.name().as_ptr(),
+                pSpecializationInfo: ptr::null(),
+            },
+        ];
+
+        let (buffers, attributes) = vertex_input.definition(vertex_shader.input())?;
+        let bindings: Vec<vk::VertexInputBindingDescription> = buffers
+            .map(|(binding, stride, rate)| vk::VertexInputBindingDescription {
+                binding: binding,
+                stride: stride as u32,
+                inputRate: match rate {
+                    InputRate::Vertex => vk::VERTEX_INPUT_RATE_VERTEX,
+                    InputRate::Instance => vk::VERTEX_INPUT_RATE_INSTANCE,
+                },
+            })
+            .collect();
+        let attributes: Vec<vk::VertexInputAttributeDescription> = attributes
+            .map(|(location, binding, info)| vk::VertexInputAttributeDescription {
+                location: location,
+                binding: binding,
+                format: info.format as u32,
+                offset: info.offset as u32,
+            })
+            .collect();
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_VERTEX_INPUT_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            vertexBindingDescriptionCount: bindings.len() as u32,
+            pVertexBindingDescriptions: bindings.as_ptr(),
+            vertexAttributeDescriptionCount: attributes.len() as u32,
+            pVertexAttributeDescriptions: attributes.as_ptr(),
+        };
+
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            topology: vk::PRIMITIVE_TOPOLOGY_TRIANGLE_LIST,
+            primitiveRestartEnable: vk::FALSE,
+        };
+
+        // the viewport is dynamic, the scissor covers anything
+        let scissor = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: vk::Extent2D {
+                width: 0x7fff_ffff,
+                height: 0x7fff_ffff,
+            },
+        };
+        let viewport = vk::PipelineViewportStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_VIEWPORT_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            viewportCount: 1,
+            pViewports: ptr::null(),
+            scissorCount: 1,
+            pScissors: &scissor,
+        };
+
+        let rasterization = vk::PipelineRasterizationStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            depthClampEnable: vk::FALSE,
+            rasterizerDiscardEnable: vk::FALSE,
+            polygonMode: vk::POLYGON_MODE_FILL,
+            cullMode: vk::CULL_MODE_NONE,
+            frontFace: vk::FRONT_FACE_COUNTER_CLOCKWISE,
+            depthBiasEnable: vk::FALSE,
+            depthBiasConstantFactor: 0.0,
+            depthBiasClamp: 0.0,
+            depthBiasSlopeFactor: 0.0,
+            lineWidth: 1.0,
+        };
+
+        // a sample count flag is the count itself
+        let multisample = vk::PipelineMultisampleStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            rasterizationSamples: subpass.num_samples().unwrap_or(1),
+            sampleShadingEnable: vk::FALSE,
+            minSampleShading: 1.0,
+            pSampleMask: ptr::null(),
+            alphaToCoverageEnable: vk::FALSE,
+            alphaToOneEnable: vk::FALSE,
+        };
+
+        let attachment = match blend {
+            Blend::Opaque => vk::PipelineColorBlendAttachmentState {
+                blendEnable: vk::FALSE,
+                srcColorBlendFactor: vk::BLEND_FACTOR_ONE,
+                dstColorBlendFactor: vk::BLEND_FACTOR_ZERO,
+                colorBlendOp: vk::BLEND_OP_ADD,
+                srcAlphaBlendFactor: vk::BLEND_FACTOR_ONE,
+                dstAlphaBlendFactor: vk::BLEND_FACTOR_ZERO,
+                alphaBlendOp: vk::BLEND_OP_ADD,
+                colorWriteMask: color_components(),
+            },
+            Blend::Alpha => vk::PipelineColorBlendAttachmentState {
+                blendEnable: vk::TRUE,
+                srcColorBlendFactor: vk::BLEND_FACTOR_SRC_ALPHA,
+                dstColorBlendFactor: vk::BLEND_FACTOR_ONE_MINUS_SRC_ALPHA,
+                colorBlendOp: vk::BLEND_OP_ADD,
+                srcAlphaBlendFactor: vk::BLEND_FACTOR_SRC_ALPHA,
+                dstAlphaBlendFactor: vk::BLEND_FACTOR_ONE_MINUS_SRC_ALPHA,
+                alphaBlendOp: vk::BLEND_OP_ADD,
+                colorWriteMask: color_components(),
+            },
+        };
+        let attachments = vec![attachment; subpass.num_color_attachments() as usize];
+        let color_blend = vk::PipelineColorBlendStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            logicOpEnable: vk::FALSE,
+            logicOp: vk::LOGIC_OP_CLEAR,
+            attachmentCount: attachments.len() as u32,
+            pAttachments: attachments.as_ptr(),
+            blendConstants: [0.0; 4],
+        };
+
+        let dynamic_states = [vk::DYNAMIC_STATE_VIEWPORT];
+        let dynamic = vk::PipelineDynamicStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_DYNAMIC_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            dynamicStateCount: dynamic_states.len() as u32,
+            pDynamicStates: dynamic_states.as_ptr(),
+        };
+
+        let info = vk::GraphicsPipelineCreateInfo {
+            sType: vk::STRUCTURE_TYPE_GRAPHICS_PIPELINE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            stageCount: stages.len() as u32,
+            pStages: stages.as_ptr(),
+            pVertexInputState: &vertex_input_state,
+            pInputAssemblyState: &input_assembly,
+            pTessellationState: ptr::null(),
+            pViewportState: &viewport,
+            pRasterizationState: &rasterization,
+            pMultisampleState: &multisample,
+            // the render passes have no depth attachment
+            pDepthStencilState: ptr::null(),
+            pColorBlendState: &color_blend,
+            pDynamicState: &dynamic,
+            layout: PipelineLayoutAbstract::sys(&layout).internal_object(),
+            renderPass: RenderPassAbstract::inner(subpass.render_pass()).internal_object(),
+            subpass: subpass.index(),
+            basePipelineHandle: 0,
+            basePipelineIndex: -1,
+        };
+
+        let mut pipeline = 0;
+        let result = unsafe {
+            device.pointers().CreateGraphicsPipelines(
+                device.internal_object(),
+                vulkan_obj.pipeline_cache.internal_object(),
+                1,
+                &info,
+                ptr::null(),
+                &mut pipeline,
+            )
+        };
+        if result != vk::SUCCESS {
+            return Err(PipelineError::Creation(result));
+        }
+
+        Ok(CachedPipeline {
+            pipeline: pipeline,
+            device: device,
+            layout: Box::new(layout) as Box<PipelineLayoutAbstract + Send + Sync>,
+            subpass: subpass,
+            vertex_input: vertex_input,
+        })
+    }
+}
+
+fn color_components() -> vk::ColorComponentFlags {
+    vk::COLOR_COMPONENT_R_BIT | vk::COLOR_COMPONENT_G_BIT | vk::COLOR_COMPONENT_B_BIT | vk::COLOR_COMPONENT_A_BIT
+}
+
+impl<V> Drop for CachedPipeline<V> {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .pointers()
+                .DestroyPipeline(self.device.internal_object(), self.pipeline, ptr::null());
+        }
+    }
+}
+
+unsafe impl<V> GraphicsPipelineAbstract for CachedPipeline<V>
+where
+    V: VertexSource<Vec<Arc<BufferAccess + Send + Sync>>>,
+{
+    fn inner(&self) -> GraphicsPipelineSys {
+        // vulkano only builds it from a handle of its own pipelines, it
+        // holds nothing but the handle
+        unsafe { mem::transmute::<vk::Pipeline, GraphicsPipelineSys>(self.pipeline) }
+    }
+
+    fn subpass_index(&self) -> u32 {
+        self.subpass.index()
+    }
+
+    fn has_dynamic_line_width(&self) -> bool {
+        false
+    }
+
+    fn num_viewports(&self) -> u32 {
+        1
+    }
+
+    fn has_dynamic_viewports(&self) -> bool {
+        true
+    }
+
+    fn has_dynamic_scissors(&self) -> bool {
+        false
+    }
+
+    fn has_dynamic_depth_bounds(&self) -> bool {
+        false
+    }
+
+    fn has_dynamic_stencil_compare_mask(&self) -> bool {
+        false
+    }
+
+    fn has_dynamic_stencil_write_mask(&self) -> bool {
+        false
+    }
+
+    fn has_dynamic_stencil_reference(&self) -> bool {
+        false
+    }
+}
+
+unsafe impl<V> PipelineLayoutAbstract for CachedPipeline<V> {
+    fn sys(&self) -> PipelineLayoutSys {
+        self.layout.sys()
+    }
+
+    fn descriptor_set_layout(&self, index: usize) -> Option<&Arc<UnsafeDescriptorSetLayout>> {
+        self.layout.descriptor_set_layout(index)
+    }
+}
+
+unsafe impl<V> PipelineLayoutDesc for CachedPipeline<V> {
+    fn num_sets(&self) -> usize {
+        self.layout.num_sets()
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        self.layout.num_bindings_in_set(set)
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.layout.descriptor(set, binding)
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        self.layout.num_push_constants_ranges()
+    }
+
+    fn push_constants_range(&self, num: usize) -> Option<PipelineLayoutDescPcRange> {
+        self.layout.push_constants_range(num)
+    }
+}
+
+unsafe impl<V> RenderPassAbstract for CachedPipeline<V> {
+    fn inner(&self) -> RenderPassSys {
+        RenderPassAbstract::inner(self.subpass.render_pass())
+    }
+}
+
+unsafe impl<V> RenderPassDesc for CachedPipeline<V> {
+    fn num_attachments(&self) -> usize {
+        self.subpass.render_pass().num_attachments()
+    }
+
+    fn attachment_desc(&self, num: usize) -> Option<LayoutAttachmentDescription> {
+        self.subpass.render_pass().attachment_desc(num)
+    }
+
+    fn num_subpasses(&self) -> usize {
+        self.subpass.render_pass().num_subpasses()
+    }
+
+    fn subpass_desc(&self, num: usize) -> Option<LayoutPassDescription> {
+        self.subpass.render_pass().subpass_desc(num)
+    }
+
+    fn num_dependencies(&self) -> usize {
+        self.subpass.render_pass().num_dependencies()
+    }
+
+    fn dependency_desc(&self, num: usize) -> Option<LayoutPassDependencyDescription> {
+        self.subpass.render_pass().dependency_desc(num)
+    }
+}
+
+unsafe impl<V> RenderPassDescClearValues<Vec<ClearValue>> for CachedPipeline<V> {
+    fn convert_clear_values(&self, values: Vec<ClearValue>) -> Box<Iterator<Item = ClearValue>> {
+        self.subpass.render_pass().convert_clear_values(values)
+    }
+}
+
+unsafe impl<V> DeviceOwned for CachedPipeline<V> {
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+unsafe impl<V, L> VertexSource<L> for CachedPipeline<V>
+where
+    V: VertexSource<L>,
+{
+    fn decode(&self, source: L) -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
+        self.vertex_input.decode(source)
+    }
+}
+
+impl From<PipelineLayoutCreationError> for PipelineError {
+    fn from(err: PipelineLayoutCreationError) -> PipelineError {
+        PipelineError::Layout(err)
+    }
+}
+
+impl From<IncompatibleVertexDefinitionError> for PipelineError {
+    fn from(err: IncompatibleVertexDefinitionError) -> PipelineError {
+        PipelineError::VertexInput(err)
+    }
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PipelineError::Layout(ref err) => write!(f, "failed to create the pipeline layout: {}", err),
+            PipelineError::VertexInput(ref err) => write!(f, "vertex input: {}", err),
+            PipelineError::Creation(result) => write!(f, "vkCreateGraphicsPipelines failed with {}", result),
+        }
+    }
+}
+
+impl Error for PipelineError {
+    fn description(&self) -> &str {
+        "failed to create a graphics pipeline"
+    }
+}
@@ -0,0 +1,65 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process;
+
+use super::{read_cache_file, write_cache_file, CacheKey};
+
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("slicer-pipeline-cache-{}-{}", process::id(), name))
+}
+
+fn key() -> CacheKey {
+    CacheKey {
+        uuid: [7; 16],
+        vendor_id: 0x13b5,
+        device_id: 0x0760,
+        driver_version: 12,
+    }
+}
+
+#[test]
+fn saved_data_is_loaded_back() {
+    let path = temp_path("round-trip").join("pipeline.cache");
+    write_cache_file(&path, &key(), b"driver data").unwrap();
+    assert_eq!(read_cache_file(&path, &key()).unwrap(), Some(b"driver data".to_vec()));
+    // written over the previous one
+    write_cache_file(&path, &key(), b"more driver data").unwrap();
+    assert_eq!(read_cache_file(&path, &key()).unwrap(), Some(b"more driver data".to_vec()));
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn another_device_or_driver_invalidates() {
+    let path = temp_path("invalidated");
+    write_cache_file(&path, &key(), b"driver data").unwrap();
+
+    let updated = CacheKey {
+        driver_version: 13,
+        ..key()
+    };
+    assert_eq!(read_cache_file(&path, &updated).unwrap(), None);
+    let other = CacheKey {
+        uuid: [8; 16],
+        ..key()
+    };
+    assert_eq!(read_cache_file(&path, &other).unwrap(), None);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn truncated_or_foreign_files_invalidate() {
+    let path = temp_path("truncated");
+    write_cache_file(&path, &key(), b"driver data").unwrap();
+    let len = fs::metadata(&path).unwrap().len();
+    fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 1).unwrap();
+    assert_eq!(read_cache_file(&path, &key()).unwrap(), None);
+
+    File::create(&path).unwrap().write_all(b"not a cache").unwrap();
+    assert_eq!(read_cache_file(&path, &key()).unwrap(), None);
+    fs::remove_file(&path).unwrap();
+
+    let err = read_cache_file(&path, &key()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
@@ -0,0 +1,172 @@
+//! Refresh rate measurement
+//!
+//! In FIFO mode `acquire` returns once the presentation engine released an
+//! image, at most once per vertical blank. The time between acquisitions
+//! therefore follows the refresh the panel really runs at, whatever the
+//! display mode claims; a missed blank shows up as an interval about twice
+//! as long. The timestamps are taken on the CPU, the jitter reported
+//! includes the scheduling of this process.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests;
+
+/// ignored at the start, while the swapchain fills up
+const WARM_UP_MS: u64 = 500;
+
+/// collects acquire timestamps for a fixed duration
+pub struct RefreshMeter {
+    duration: Duration,
+    /// display mode refresh in mHz, `None` when the backend doesn't know
+    nominal: Option<u32>,
+    started: Option<Instant>,
+    timestamps: Vec<Instant>,
+}
+
+/// intervals are in milliseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshReport {
+    pub intervals: usize,
+    /// from the display mode, else the median interval
+    pub nominal_interval: f64,
+    pub nominal_is_measured: bool,
+    pub mean_interval: f64,
+    pub median_interval: f64,
+    /// standard deviation of the intervals
+    pub jitter: f64,
+    /// largest distance of an interval to the nominal one
+    pub max_deviation: f64,
+    /// blanks without a new image
+    pub dropped: usize,
+    /// intervals below half the nominal one, FIFO isn't honoured
+    pub early: usize,
+}
+
+impl RefreshMeter {
+    pub fn new(duration: Duration, nominal: Option<u32>) -> RefreshMeter {
+        RefreshMeter {
+            duration: duration,
+            nominal: nominal,
+            started: None,
+            timestamps: Vec::new(),
+        }
+    }
+
+    /// call when `begin_frame` returns
+    pub fn record(&mut self, now: Instant) {
+        let started = *self.started.get_or_insert(now);
+        if now - started >= Duration::from_millis(WARM_UP_MS) {
+            self.timestamps.push(now);
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        let end = Duration::from_millis(WARM_UP_MS) + self.duration;
+        match (self.started, self.timestamps.last()) {
+            (Some(started), Some(&last)) => last - started >= end,
+            _ => false,
+        }
+    }
+
+    /// `None` until two frames were recorded
+    pub fn report(&self) -> Option<RefreshReport> {
+        let intervals: Vec<f64> = self.timestamps
+            .windows(2)
+            .map(|pair| millis(pair[1] - pair[0]))
+            .collect();
+        let nominal = self.nominal.map(|rate| 1_000_000.0 / rate as f64);
+        RefreshReport::from_intervals(&intervals, nominal)
+    }
+}
+
+impl RefreshReport {
+    /// `nominal` interval in milliseconds
+    pub fn from_intervals(intervals: &[f64], nominal: Option<f64>) -> Option<RefreshReport> {
+        if intervals.is_empty() {
+            return None;
+        }
+
+        let count = intervals.len() as f64;
+        let mean = intervals.iter().sum::<f64>() / count;
+        let variance = intervals.iter().map(|i| (i - mean) * (i - mean)).sum::<f64>() / count;
+        let mut sorted = intervals.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).expect("interval is NaN"));
+        let median = sorted[sorted.len() / 2];
+
+        let nominal_interval = nominal.unwrap_or(median);
+        let mut dropped = 0;
+        let mut early = 0;
+        let mut max_deviation = 0.0f64;
+        for &interval in intervals {
+            let blanks = (interval / nominal_interval).round() as usize;
+            if blanks == 0 {
+                early += 1;
+            } else {
+                dropped += blanks - 1;
+            }
+            max_deviation = max_deviation.max((interval - nominal_interval).abs());
+        }
+
+        Some(RefreshReport {
+            intervals: intervals.len(),
+            nominal_interval: nominal_interval,
+            nominal_is_measured: nominal.is_none(),
+            mean_interval: mean,
+            median_interval: median,
+            jitter: variance.sqrt(),
+            max_deviation: max_deviation,
+            dropped: dropped,
+            early: early,
+        })
+    }
+
+    /// refresh rate in Hz from the mean interval, drops included
+    pub fn measured_rate(&self) -> f64 {
+        1000.0 / self.mean_interval
+    }
+
+    pub fn nominal_rate(&self) -> f64 {
+        1000.0 / self.nominal_interval
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 * 1e-6
+}
+
+impl fmt::Display for RefreshReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "nominal:  {:.3} Hz, {:.3} ms{}",
+            self.nominal_rate(),
+            self.nominal_interval,
+            if self.nominal_is_measured {
+                " (unknown mode, median interval)"
+            } else {
+                ""
+            }
+        )?;
+        writeln!(
+            f,
+            "measured: {:.3} Hz, mean {:.3} ms, median {:.3} ms over {} intervals",
+            self.measured_rate(),
+            self.mean_interval,
+            self.median_interval,
+            self.intervals
+        )?;
+        writeln!(
+            f,
+            "jitter:   {:.3} ms standard deviation, {:.3} ms at most",
+            self.jitter,
+            self.max_deviation
+        )?;
+        write!(f, "dropped:  {} frames", self.dropped)?;
+        if self.early > 0 {
+            write!(f, ", {} early: the present mode doesn't wait for vblank", self.early)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use super::{RefreshMeter, RefreshReport};
+
+use std::time::{Duration, Instant};
+
+#[test]
+fn steady_refresh() {
+    let report = RefreshReport::from_intervals(&[16.0, 17.0, 16.0, 17.0], Some(16.5)).unwrap();
+    assert_eq!(report.mean_interval, 16.5);
+    assert_eq!(report.jitter, 0.5);
+    assert_eq!(report.max_deviation, 0.5);
+    assert_eq!(report.dropped, 0);
+    assert_eq!(report.early, 0);
+    assert!(!report.nominal_is_measured);
+}
+
+#[test]
+fn dropped_and_early_frames() {
+    let report = RefreshReport::from_intervals(&[20.0, 40.0, 60.0, 5.0, 20.0], Some(20.0)).unwrap();
+    assert_eq!(report.dropped, 3);
+    assert_eq!(report.early, 1);
+    assert_eq!(report.max_deviation, 40.0);
+}
+
+#[test]
+fn unknown_mode_uses_the_median() {
+    let report = RefreshReport::from_intervals(&[10.0, 10.0, 20.0], None).unwrap();
+    assert!(report.nominal_is_measured);
+    assert_eq!(report.nominal_interval, 10.0);
+    assert_eq!(report.nominal_rate(), 100.0);
+    assert_eq!(report.dropped, 1);
+    assert!(RefreshReport::from_intervals(&[], None).is_none());
+}
+
+#[test]
+fn meter_skips_the_warm_up() {
+    let start = Instant::now();
+    let mut meter = RefreshMeter::new(Duration::from_secs(1), Some(50_000));
+    let mut frame = 0;
+    while !meter.is_done() {
+        meter.record(start + Duration::from_millis(frame * 20));
+        frame += 1;
+    }
+    // 0.5 s warm up, then one second of samples
+    assert_eq!(frame, 76);
+    let report = meter.report().unwrap();
+    assert_eq!(report.intervals, 50);
+    assert_eq!(report.dropped, 0);
+    assert!((report.measured_rate() - 50.0).abs() < 1e-6);
+}
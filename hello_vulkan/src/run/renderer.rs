@@ -0,0 +1,1033 @@
+//! Frame loop on top of a `VulkanStruct` and a `PresentTarget`
+//!
+//! ```ignore
+//! let target = backend.create_target(vulkan_obj.clone(), &config)?;
+//! let mut renderer = Renderer::new(vulkan_obj, target, &config)?;
+//! let pipeline = renderer.create_default_pipeline()?;
+//! let triangle = renderer.create_vertex_buffer(triangle.into_iter())?;
+//! loop {
+//!     let mut frame = match renderer.begin_frame() {
+//!         Ok(frame) => frame,
+//!         Err(RenderError::OutOfDate) => continue,
+//!         Err(err) => return Err(err),
+//!     };
+//!     let vertices = renderer.use_upload(&triangle)?;
+//!     let pre_rotation = renderer.pre_rotation();
+//!     renderer.draw(&mut frame, pipeline.clone(), vec![vertices], (), pre_rotation)?;
+//!     // UI after this isn't post-processed
+//!     renderer.begin_overlay(&mut frame)?;
+//!     canvas.draw(&renderer, &mut frame, &batch)?;
+//!     renderer.end_frame(frame)?;
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+
+use image::{ImageBuffer, Rgba};
+
+use vulkano::OomError;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, CpuBufferPool, ImmutableBuffer,
+                      TypedBufferAccess};
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder,
+                              AutoCommandBufferBuilderContextError, BeginRenderPassError, BuildError,
+                              CommandBufferExecError, CopyBufferError, CopyBufferImageError, DispatchError,
+                              DrawError, DrawIndirectCommand, DrawIndirectError, DynamicState,
+                              ExecuteCommandsError};
+use vulkano::descriptor::descriptor_set::{DescriptorSetsCollection,
+                                          PersistentDescriptorSetBuildError,
+                                          PersistentDescriptorSetError};
+use vulkano::format::{AcceptsPixels, ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, FramebufferCreationError,
+                           RenderPassAbstract, RenderPassCreationError, Subpass};
+use vulkano::image::{Dimensions, ImageCreationError, ImmutableImage};
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::pipeline::{ComputePipelineCreationError, GraphicsPipelineAbstract};
+use vulkano::pipeline::vertex::{BufferlessDefinition, OneVertexOneInstanceDefinition, SingleBufferDefinition,
+                                VertexSource};
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::sampler::SamplerCreationError;
+use vulkano::swapchain::{AcquireError, CapabilitiesError, SurfaceCreationError, SwapchainCreationError};
+use vulkano::sync::{now, FlushError, GpuFuture};
+
+use config::{Config, ConfigError};
+use log_targets::{FRAME_STATS, INFO};
+
+use super::VulkanStruct;
+use super::correction::LutError;
+use super::compute::kernel::SpirvError;
+use super::graph::{GraphError, RenderGraph};
+use super::pipeline_cache::{Blend, CachedPipeline, PipelineError};
+use super::shaders::{Instance, Vertex};
+use super::shaders::canvas_fragment_shader::Shader as ColorFragmentShader;
+use super::shaders::default_vertex_shader::ty::PreRotation;
+use super::shaders::instanced_vertex_shader::Shader as InstancedVertexShader;
+use super::target::PresentTarget;
+use super::upload::{Upload, UploadManager};
+use super::watchdog::{wait_fence, Stage};
+
+#[derive(Debug)]
+pub enum RenderError {
+    /// the target no longer matches the surface, it is recreated on
+    /// the next `begin_frame` and the current frame should be skipped
+    OutOfDate,
+    DeviceLost,
+    /// the GPU took longer than the `[watchdog]` timeout, likely hung
+    Timeout(Stage),
+    Swapchain(SwapchainCreationError),
+    Acquire(AcquireError),
+    Flush(FlushError),
+    Correction(LutError),
+    Graph(GraphError),
+    /// a configured value the device or the display doesn't have
+    Config(ConfigError),
+    Spirv(SpirvError),
+    /// the device lacks a feature the call needs
+    Unsupported(&'static str),
+    /// the call isn't allowed at this point of the frame
+    Order(&'static str),
+    /// failed to create a resource or to record a command
+    Vulkan(Box<Error + Send + Sync>),
+}
+
+/// pipeline drawing `BufferlessVertices`, e.g. a fullscreen triangle;
+/// trait objects only accept vertex buffers
+pub type BufferlessPipeline = CachedPipeline<BufferlessDefinition>;
+
+/// an image acquired by a frame dropped before `end_frame`
+type Abandoned = Rc<RefCell<Option<(usize, Box<GpuFuture>)>>>;
+
+/// a frame being recorded, between `begin_frame` and `end_frame`; when
+/// dropped before, e.g. on an error, its image is presented cleared by
+/// the next `begin_frame`
+pub struct Frame {
+    image_index: usize,
+    /// `None` once submitted
+    acquire_future: Option<Box<GpuFuture>>,
+    abandoned: Abandoned,
+    builder: Option<AutoCommandBufferBuilder>,
+    /// how the scene render pass was begun, `None` before the first draw
+    contents: Option<Contents>,
+    /// `draw`s between secondary command buffers, recorded in one more
+    secondary: Option<AutoCommandBufferBuilder>,
+    /// set by `Renderer::begin_overlay`
+    overlay: bool,
+    started: Instant,
+}
+
+/// a render pass holds either draws or secondary command buffers
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Contents {
+    Inline,
+    Secondary,
+}
+
+pub struct Renderer {
+    vulkan_obj: Arc<VulkanStruct>,
+    target: Box<PresentTarget>,
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    dynamic_state: DynamicState,
+    /// compatible with `render_pass`, keeping what the graph wrote
+    overlay_pass: Arc<RenderPassAbstract + Send + Sync>,
+    /// on the target images, empty without a graph
+    overlay_framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    /// the scene's viewport scaled to the target
+    overlay_state: DynamicState,
+    clear_color: [f32; 4],
+    viewport_size: Option<[f32; 2]>,
+    dimensions: [u32; 2],
+    recreate_target: bool,
+    previous_frame_end: Option<Box<GpuFuture>>,
+    /// written after the next frame is rendered
+    screenshot: Option<PathBuf>,
+    /// post-processing after the scene pass, `draw` records into the
+    /// scene node
+    graph: RenderGraph,
+    /// the `create_*` uploads, on the transfer queue
+    uploads: UploadManager,
+    /// set on a lost device or a timeout, the frames in flight are then
+    /// leaked instead of waited for
+    lost: bool,
+    abandoned: Abandoned,
+}
+
+impl Frame {
+    pub fn image_index(&self) -> usize {
+        self.image_index
+    }
+
+    fn put_draw_builder(&mut self, builder: AutoCommandBufferBuilder) {
+        match self.contents {
+            Some(Contents::Secondary) => self.secondary = Some(builder),
+            _ => self.builder = Some(builder),
+        }
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        // vulkano panics when an acquired image is dropped unpresented
+        if let Some(future) = self.acquire_future.take() {
+            let mut abandoned = self.abandoned.borrow_mut();
+            if abandoned.is_none() && Rc::strong_count(&self.abandoned) > 1 {
+                *abandoned = Some((self.image_index, future));
+            } else {
+                warn!(
+                    target: INFO,
+                    "leaking image {} of a dropped frame, with its swapchain",
+                    self.image_index
+                );
+                mem::forget(future);
+            }
+        }
+    }
+}
+
+impl Renderer {
+    pub fn new(
+        vulkan_obj: Arc<VulkanStruct>,
+        target: Box<PresentTarget>,
+        config: &Config,
+    ) -> Result<Renderer, RenderError> {
+        let render_pass = Arc::new(single_pass_renderpass!(vulkan_obj.device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: target.format(),
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )?) as Arc<RenderPassAbstract + Send + Sync>;
+        let overlay_pass = Arc::new(single_pass_renderpass!(vulkan_obj.device.clone(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: target.format(),
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )?) as Arc<RenderPassAbstract + Send + Sync>;
+
+        let dimensions = target.dimensions();
+        let mut previous_frame_end = Box::new(now(vulkan_obj.device.clone())) as Box<GpuFuture>;
+
+        let mut uploads = UploadManager::new(vulkan_obj.clone());
+        let (mut graph, futures) = RenderGraph::from_config(&vulkan_obj, &mut uploads, config)?;
+        graph.compile(&vulkan_obj, target.format())?;
+        for future in futures {
+            previous_frame_end = Box::new(previous_frame_end.join(future)) as Box<GpuFuture>;
+        }
+
+        let mut renderer = Renderer {
+            vulkan_obj: vulkan_obj,
+            target: target,
+            render_pass: render_pass,
+            framebuffers: Vec::new(),
+            dynamic_state: DynamicState::none(),
+            overlay_pass: overlay_pass,
+            overlay_framebuffers: Vec::new(),
+            overlay_state: DynamicState::none(),
+            clear_color: config.clear_color,
+            viewport_size: config.viewport.size,
+            dimensions: dimensions,
+            recreate_target: false,
+            previous_frame_end: Some(previous_frame_end),
+            screenshot: None,
+            graph: graph,
+            uploads: uploads,
+            lost: false,
+            abandoned: Rc::new(RefCell::new(None)),
+        };
+        renderer.create_framebuffers()?;
+        Ok(renderer)
+    }
+
+    pub fn vulkan_obj(&self) -> &Arc<VulkanStruct> {
+        &self.vulkan_obj
+    }
+
+    pub fn format(&self) -> Format {
+        self.target.format()
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// size of the scene node's output, what `draw` renders into
+    pub fn scene_dimensions(&self) -> [u32; 2] {
+        self.graph.scene_dimensions(self.dimensions)
+    }
+
+    /// size of the upright content drawn through `pre_rotation()`: the
+    /// scene's, swapped when the shader rotates by 90 or 270 degrees
+    pub fn content_dimensions(&self) -> [u32; 2] {
+        let scene = self.scene_dimensions();
+        if self.target.orientation().pre_rotation().swaps_extent() {
+            [scene[1], scene[0]]
+        } else {
+            scene
+        }
+    }
+
+    /// push constants of the default vertex shader
+    pub fn pre_rotation(&self) -> PreRotation {
+        PreRotation {
+            matrix: self.target.orientation().pre_rotation().matrix(),
+        }
+    }
+
+    /// maps a position in pixels on the panel to pixels in the upright
+    /// content, whichever way the orientation is applied
+    pub fn map_input(&self, position: [f32; 2]) -> [f32; 2] {
+        let orientation = self.target.orientation();
+        orientation
+            .orientation
+            .map_input(position, orientation.panel_extent(self.dimensions))
+    }
+
+    /// the subpass every pipeline drawn through this renderer must use,
+    /// in the scene and in the overlay
+    pub fn subpass(&self) -> Subpass<Arc<RenderPassAbstract + Send + Sync>> {
+        Subpass::from(self.render_pass.clone(), 0).expect("render pass has no subpass 0")
+    }
+
+    /// reads the next frame back and writes it to `path` as PNG, the frame
+    /// waits for the GPU
+    pub fn request_screenshot(&mut self, path: PathBuf) {
+        self.screenshot = Some(path);
+    }
+
+    /// re-reads the files of the post-processing effects, e.g. the colour
+    /// correction LUT; keeps the current ones on error
+    pub fn reload_effects(&mut self) -> Result<(), RenderError> {
+        for future in self.graph.reload(&mut self.uploads)? {
+            self.chain(future);
+        }
+        Ok(())
+    }
+
+    /// the target is recreated with `dimensions` at the next frame
+    pub fn resize(&mut self, dimensions: [u32; 2]) {
+        self.dimensions = dimensions;
+        self.recreate_target = true;
+    }
+
+    /// pipeline of the built-in shaders, drawing `Vertex` in clip space,
+    /// takes `pre_rotation()` as push constants
+    pub fn create_default_pipeline(
+        &self,
+    ) -> Result<Arc<GraphicsPipelineAbstract + Send + Sync>, RenderError> {
+        let pipeline = CachedPipeline::new(
+            &self.vulkan_obj,
+            SingleBufferDefinition::<Vertex>::new(),
+            self.vulkan_obj.vertex_shader.main_entry_point(),
+            self.vulkan_obj.fragment_shader.main_entry_point(),
+            Blend::Opaque,
+            self.subpass(),
+        )?;
+        Ok(Arc::new(pipeline))
+    }
+
+    /// pipeline drawing the `Vertex` buffer once per element of the
+    /// `Instance` buffer, given in that order; takes `pre_rotation()` as
+    /// push constants
+    pub fn create_instanced_pipeline(
+        &self,
+    ) -> Result<Arc<GraphicsPipelineAbstract + Send + Sync>, RenderError> {
+        let device = self.vulkan_obj.device.clone();
+        let vertex_shader = InstancedVertexShader::load(device.clone())?;
+        let fragment_shader = ColorFragmentShader::load(device.clone())?;
+        let pipeline = CachedPipeline::new(
+            &self.vulkan_obj,
+            OneVertexOneInstanceDefinition::<Vertex, Instance>::new(),
+            vertex_shader.main_entry_point(),
+            fragment_shader.main_entry_point(),
+            Blend::Opaque,
+            self.subpass(),
+        )?;
+        Ok(Arc::new(pipeline))
+    }
+
+    /// uploads the commands of a `draw_indirect`; more than one needs the
+    /// `multi_draw_indirect` feature, enabled when the device has it
+    pub fn create_indirect_buffer<I>(
+        &mut self,
+        commands: I,
+    ) -> Result<Upload<Arc<ImmutableBuffer<[DrawIndirectCommand]>>>, RenderError>
+    where
+        I: ExactSizeIterator<Item = DrawIndirectCommand>,
+    {
+        if commands.len() > 1 && !self.vulkan_obj.device.enabled_features().multi_draw_indirect {
+            return Err(RenderError::Unsupported("multi_draw_indirect"));
+        }
+        self.uploads.buffer_from_iter(commands, BufferUsage::indirect_buffer())
+    }
+
+    /// uploads `data` to a device local vertex buffer on the transfer
+    /// queue, see `use_upload`
+    pub fn create_vertex_buffer<T, I>(
+        &mut self,
+        data: I,
+    ) -> Result<Upload<Arc<ImmutableBuffer<[T]>>>, RenderError>
+    where
+        I: ExactSizeIterator<Item = T>,
+        T: Send + Sync + 'static,
+    {
+        self.uploads.buffer_from_iter(data, BufferUsage::vertex_buffer())
+    }
+
+    /// host visible vertex buffers for geometry changing every frame;
+    /// a chunk goes back to the pool once the frames using it are done,
+    /// so the frames in flight never see their vertices overwritten
+    pub fn create_vertex_pool<T>(&self) -> CpuBufferPool<T> {
+        CpuBufferPool::vertex_buffer(self.vulkan_obj.device.clone())
+    }
+
+    /// uploads `data` to a device local buffer with `usage`
+    pub fn create_buffer<T>(
+        &mut self,
+        data: T,
+        usage: BufferUsage,
+    ) -> Result<Upload<Arc<ImmutableBuffer<T>>>, RenderError>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.uploads.buffer_from_data(data, usage)
+    }
+
+    /// uploads `pixels` to a sampled image, e.g. a texture
+    pub fn create_image<P, I>(
+        &mut self,
+        pixels: I,
+        dimensions: Dimensions,
+        format: Format,
+    ) -> Result<Upload<Arc<ImmutableImage<Format>>>, RenderError>
+    where
+        P: Send + Sync + Clone + 'static,
+        I: ExactSizeIterator<Item = P>,
+        Format: AcceptsPixels<P>,
+    {
+        self.uploads.image_from_iter(pixels, dimensions, format)
+    }
+
+    /// the resource of `upload`; the first time a resource of its batch
+    /// is used, the next submission waits for the batch on the GPU
+    pub fn use_upload<R: Clone>(&mut self, upload: &Upload<R>) -> Result<R, RenderError> {
+        if let Some(future) = upload.take_future()? {
+            self.chain(future);
+        }
+        Ok(upload.resource().clone())
+    }
+
+    /// makes the next submission wait for `future`
+    pub fn chain<F: GpuFuture + 'static>(&mut self, future: F) {
+        let previous = self.previous_frame_end
+            .take()
+            .unwrap_or_else(|| Box::new(now(self.vulkan_obj.device.clone())) as Box<GpuFuture>);
+        self.previous_frame_end = Some(Box::new(previous.join(future)) as Box<GpuFuture>);
+    }
+
+    /// viewport every draw in the scene render pass uses
+    pub fn dynamic_state(&self) -> &DynamicState {
+        &self.dynamic_state
+    }
+
+    /// acquires the next target image; its render pass starts with the
+    /// first `draw` or `execute_secondary`
+    pub fn begin_frame(&mut self) -> Result<Frame, RenderError> {
+        if let Some(ref mut previous) = self.previous_frame_end {
+            previous.cleanup_finished();
+        }
+
+        self.present_abandoned()?;
+
+        if self.recreate_target {
+            self.recreate()?;
+        }
+
+        let started = Instant::now();
+        self.vulkan_obj.enter_stage(Stage::Acquire);
+        let acquired = self.target.acquire();
+        self.vulkan_obj.leave_stage();
+        let (image_index, acquire_future) = match acquired {
+            Ok(r) => r,
+            Err(RenderError::OutOfDate) => {
+                self.recreate_target = true;
+                return Err(RenderError::OutOfDate);
+            }
+            Err(err) => return Err(self.failed(err)),
+        };
+        self.frame(image_index, acquire_future, started)
+    }
+
+    fn frame(
+        &self,
+        image_index: usize,
+        acquire_future: Box<GpuFuture>,
+        started: Instant,
+    ) -> Result<Frame, RenderError> {
+        let mut frame = Frame {
+            image_index: image_index,
+            acquire_future: Some(acquire_future),
+            abandoned: self.abandoned.clone(),
+            builder: None,
+            contents: None,
+            secondary: None,
+            overlay: false,
+            started: started,
+        };
+        frame.builder = Some(AutoCommandBufferBuilder::primary_one_time_submit(
+            self.vulkan_obj.device.clone(),
+            self.vulkan_obj.queue.family(),
+        )?);
+        Ok(frame)
+    }
+
+    /// submits the image of a frame dropped before `end_frame`, it can't
+    /// be acquired again until presented
+    fn present_abandoned(&mut self) -> Result<(), RenderError> {
+        let abandoned = self.abandoned.borrow_mut().take();
+        if let Some((image_index, acquire_future)) = abandoned {
+            let frame = self.frame(image_index, acquire_future, Instant::now())?;
+            self.end_frame(frame)?;
+        }
+        Ok(())
+    }
+
+    /// executes `command_buffers`, recorded for `subpass()` e.g. by a
+    /// `SceneRecorder`, in order after what was drawn so far; fails with
+    /// `RenderError::Order` when the frame was drawn into before, or in
+    /// the overlay
+    pub fn execute_secondary(
+        &self,
+        frame: &mut Frame,
+        command_buffers: Vec<AutoCommandBuffer>,
+    ) -> Result<(), RenderError> {
+        if frame.overlay {
+            return Err(RenderError::Order("execute_secondary in the overlay"));
+        }
+        let contents = frame.contents;
+        let mut builder = match contents {
+            None => {
+                frame.contents = Some(Contents::Secondary);
+                self.begin_render_pass(frame, true)?
+            }
+            Some(Contents::Secondary) => self.flush_secondary(frame)?,
+            // the render pass was begun for inline draws
+            Some(Contents::Inline) => return Err(RenderError::Order("execute_secondary after a draw")),
+        };
+        for command_buffer in command_buffers {
+            // the secondary command buffers only read buffers that are
+            // immutable or handed out once by a pool, vulkano doesn't
+            // synchronize their accesses yet
+            builder = unsafe { builder.execute_commands(command_buffer)? };
+        }
+        frame.builder = Some(builder);
+        Ok(())
+    }
+
+    pub fn draw<Gp, V, S, Pc>(
+        &self,
+        frame: &mut Frame,
+        pipeline: Gp,
+        vertices: V,
+        sets: S,
+        constants: Pc,
+    ) -> Result<(), RenderError>
+    where
+        Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone,
+        S: DescriptorSetsCollection,
+    {
+        let builder = self.draw_builder(frame)?;
+        let dynamic_state = self.draw_state(frame).clone();
+        frame.put_draw_builder(builder.draw(
+            pipeline,
+            dynamic_state,
+            vertices,
+            sets,
+            constants,
+        )?);
+        Ok(())
+    }
+
+    /// like `draw`, with the vertex and instance counts read by the GPU
+    /// from `commands`; they aren't checked against the buffers
+    pub fn draw_indirect<Gp, V, Ib, S, Pc>(
+        &self,
+        frame: &mut Frame,
+        pipeline: Gp,
+        vertices: V,
+        commands: Ib,
+        sets: S,
+        constants: Pc,
+    ) -> Result<(), RenderError>
+    where
+        Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone,
+        Ib: BufferAccess + TypedBufferAccess<Content = [DrawIndirectCommand]> + Send + Sync + 'static,
+        S: DescriptorSetsCollection,
+    {
+        let builder = self.draw_builder(frame)?;
+        let dynamic_state = self.draw_state(frame).clone();
+        frame.put_draw_builder(builder.draw_indirect(
+            pipeline,
+            dynamic_state,
+            vertices,
+            commands,
+            sets,
+            constants,
+        )?);
+        Ok(())
+    }
+
+    /// ends the scene render pass and records the post-processing after
+    /// it; the `draw`s that follow go straight to the target, in the
+    /// scene's coordinates, e.g. UI which mustn't be blurred
+    pub fn begin_overlay(&self, frame: &mut Frame) -> Result<(), RenderError> {
+        if frame.overlay {
+            return Err(RenderError::Order("begin_overlay twice"));
+        }
+        frame.overlay = true;
+        // the scene is rendered into the target already
+        if self.graph.is_empty() {
+            return Ok(());
+        }
+        let builder = self.end_scene(frame)?;
+        frame.builder = Some(builder.begin_render_pass(
+            self.overlay_framebuffers[frame.image_index].clone(),
+            false,
+            vec![ClearValue::None],
+        )?);
+        frame.contents = Some(Contents::Inline);
+        Ok(())
+    }
+
+    /// ends the render pass, submits the frame and presents it
+    pub fn end_frame(&mut self, frame: Frame) -> Result<(), RenderError> {
+        // the copies run while the frame renders, until one is used
+        let submitted = match self.uploads.flush() {
+            Ok(()) => {
+                self.vulkan_obj.enter_stage(Stage::Submit);
+                let submitted = self.submit(frame);
+                self.vulkan_obj.leave_stage();
+                submitted
+            }
+            Err(err) => Err(err),
+        };
+        submitted.map_err(|err| self.failed(err))
+    }
+
+    fn submit(&mut self, mut frame: Frame) -> Result<(), RenderError> {
+        let builder = if frame.overlay && !self.graph.is_empty() {
+            frame.builder
+                .take()
+                .expect("end_frame on a frame which failed to record")
+                .end_render_pass()?
+        } else {
+            self.end_scene(&mut frame)?
+        };
+
+        let screenshot = match self.screenshot.take() {
+            Some(path) => {
+                let dimensions = self.target.dimensions();
+                let buffer = CpuAccessibleBuffer::from_iter(
+                    self.vulkan_obj.device.clone(),
+                    BufferUsage::transfer_destination(),
+                    (0..dimensions[0] * dimensions[1] * 4).map(|_| 0u8),
+                )?;
+                Some((path, buffer))
+            }
+            None => None,
+        };
+        let builder = match screenshot {
+            Some((_, ref buffer)) => {
+                builder.copy_image_to_buffer(self.target.image(frame.image_index), buffer.clone())?
+            }
+            None => builder,
+        };
+        let command_buffer = builder.build()?;
+
+        let previous = self.previous_frame_end
+            .take()
+            .unwrap_or_else(|| Box::new(now(self.vulkan_obj.device.clone())) as Box<GpuFuture>);
+
+        let rendered = Box::new(
+            previous
+                .join(frame.acquire_future.take().expect("frame submitted twice"))
+                .then_execute(self.vulkan_obj.queue.clone(), command_buffer)?,
+        ) as Box<GpuFuture>;
+        let future = match self.target.present(rendered, frame.image_index) {
+            Ok(future) => future.then_signal_fence_and_flush(),
+            Err(err) => {
+                self.previous_frame_end =
+                    Some(Box::new(now(self.vulkan_obj.device.clone())) as Box<_>);
+                if let RenderError::OutOfDate = err {
+                    self.recreate_target = true;
+                }
+                return Err(err);
+            }
+        };
+
+        match future {
+            Ok(future) => {
+                let future = match screenshot {
+                    Some((path, buffer)) => {
+                        self.vulkan_obj.enter_stage(Stage::Readback);
+                        let future = wait_fence(future, self.vulkan_obj.timeouts.fence, Stage::Readback)?;
+                        let content = buffer.read().expect("failed to map readback buffer");
+                        save_screenshot(&content, self.format(), self.target.dimensions(), &path);
+                        future
+                    }
+                    None => future,
+                };
+                self.previous_frame_end = Some(Box::new(future) as Box<_>);
+                debug!(
+                    target: FRAME_STATS,
+                    "frame {} submitted in {:?}",
+                    frame.image_index,
+                    frame.started.elapsed()
+                );
+                Ok(())
+            }
+            Err(err) => {
+                self.previous_frame_end =
+                    Some(Box::new(now(self.vulkan_obj.device.clone())) as Box<_>);
+                match err {
+                    FlushError::OutOfDate => {
+                        self.recreate_target = true;
+                        Err(RenderError::OutOfDate)
+                    }
+                    FlushError::DeviceLost => Err(RenderError::DeviceLost),
+                    err => Err(RenderError::Flush(err)),
+                }
+            }
+        }
+    }
+
+    /// ends the scene render pass of `frame`, begun if nothing was drawn,
+    /// and records the graph after it
+    fn end_scene(&self, frame: &mut Frame) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let contents = frame.contents;
+        let builder = match contents {
+            None => self.begin_render_pass(frame, false)?,
+            Some(Contents::Inline) => frame.builder
+                .take()
+                .expect("end_frame on a frame which failed to record"),
+            Some(Contents::Secondary) => self.flush_secondary(frame)?,
+        };
+        let builder = builder.end_render_pass()?;
+        Ok(self.graph.record(builder, frame.image_index)?)
+    }
+
+    /// takes the primary command buffer of `frame` and begins the scene
+    /// render pass in it
+    fn begin_render_pass(
+        &self,
+        frame: &mut Frame,
+        secondary: bool,
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let builder = frame.builder.take().expect("draw on a frame which failed to record");
+        Ok(builder.begin_render_pass(
+            self.framebuffers[frame.image_index].clone(),
+            secondary,
+            vec![self.clear_color.into()],
+        )?)
+    }
+
+    /// the builder the next `draw` goes to, hand it back with
+    /// `Frame::put_draw_builder`
+    fn draw_builder(&self, frame: &mut Frame) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let contents = frame.contents;
+        match contents {
+            None => {
+                frame.contents = Some(Contents::Inline);
+                self.begin_render_pass(frame, false)
+            }
+            Some(Contents::Inline) => Ok(frame.builder.take().expect("draw on a frame which failed to record")),
+            Some(Contents::Secondary) => match frame.secondary.take() {
+                Some(builder) => Ok(builder),
+                None => Ok(AutoCommandBufferBuilder::secondary_graphics_one_time_submit(
+                    self.vulkan_obj.device.clone(),
+                    self.vulkan_obj.queue.family(),
+                    self.subpass(),
+                )?),
+            },
+        }
+    }
+
+    /// viewport of the next `draw` in `frame`
+    fn draw_state(&self, frame: &Frame) -> &DynamicState {
+        if frame.overlay {
+            &self.overlay_state
+        } else {
+            &self.dynamic_state
+        }
+    }
+
+    /// the primary command buffer of `frame`, having executed the `draw`s
+    /// recorded since the last secondary command buffers
+    fn flush_secondary(&self, frame: &mut Frame) -> Result<AutoCommandBufferBuilder, RenderError> {
+        let builder = frame.builder.take().expect("draw on a frame which failed to record");
+        match frame.secondary.take() {
+            // same as in `execute_secondary`
+            Some(secondary) => Ok(unsafe { builder.execute_commands(secondary.build()?)? }),
+            None => Ok(builder),
+        }
+    }
+
+    /// notes `err` when it means the device is gone
+    fn failed(&mut self, err: RenderError) -> RenderError {
+        if err.is_device_failure() {
+            self.lost = true;
+            self.uploads.device_lost();
+        }
+        err
+    }
+
+    fn recreate(&mut self) -> Result<(), RenderError> {
+        self.target.recreate(self.dimensions)?;
+        self.dimensions = self.target.dimensions();
+        self.create_framebuffers()?;
+        self.recreate_target = false;
+        Ok(())
+    }
+
+    fn create_framebuffers(&mut self) -> Result<(), RenderError> {
+        let dimensions = self.target.dimensions();
+        let targets = self.target.images();
+        let images = self.graph
+            .attach(&self.vulkan_obj.device, targets.clone(), dimensions)?;
+        let mut overlay_framebuffers = Vec::new();
+        if !self.graph.is_empty() {
+            for target in targets {
+                let framebuffer = Framebuffer::start(self.overlay_pass.clone())
+                    .add(target)?
+                    .build()?;
+                overlay_framebuffers.push(Arc::new(framebuffer) as Arc<FramebufferAbstract + Send + Sync>);
+            }
+        }
+        self.overlay_framebuffers = overlay_framebuffers;
+        let mut framebuffers = Vec::with_capacity(images.len());
+        for image in images {
+            let framebuffer = Framebuffer::start(self.render_pass.clone())
+                .add(image)?
+                .build()?;
+            framebuffers.push(Arc::new(framebuffer) as Arc<FramebufferAbstract + Send + Sync>);
+        }
+        self.framebuffers = framebuffers;
+
+        let scene = self.graph.scene_dimensions(dimensions);
+        let viewport = self.viewport_size
+            .unwrap_or([scene[0] as f32, scene[1] as f32]);
+        self.dynamic_state = viewport_state(viewport);
+        self.overlay_state = viewport_state([
+            viewport[0] * dimensions[0] as f32 / scene[0] as f32,
+            viewport[1] * dimensions[1] as f32 / scene[1] as f32,
+        ]);
+        Ok(())
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        if !self.lost {
+            if let Err(err) = self.present_abandoned() {
+                warn!(target: INFO, "failed to present the image of a dropped frame: {}", err);
+            }
+        }
+        if let Some((image_index, future)) = self.abandoned.borrow_mut().take() {
+            warn!(
+                target: INFO,
+                "leaking image {} of a dropped frame, with its swapchain",
+                image_index
+            );
+            mem::forget(future);
+        }
+        // dropping them would wait on fences that may never signal
+        if self.lost {
+            if let Some(frames) = self.previous_frame_end.take() {
+                warn!(
+                    target: INFO,
+                    "leaking the frames in flight of the lost device, with the device, swapchain and surface they hold"
+                );
+                mem::forget(frames);
+            }
+        }
+    }
+}
+
+fn viewport_state(dimensions: [f32; 2]) -> DynamicState {
+    DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: dimensions,
+            depth_range: 0.0..1.0,
+        }]),
+        ..DynamicState::none()
+    }
+}
+
+/// `pixels` as read back from an image of `format`
+fn save_screenshot(pixels: &[u8], format: Format, dimensions: [u32; 2], path: &PathBuf) {
+    let mut pixels = pixels.to_vec();
+    match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb | Format::A8B8G8R8UnormPack32
+        | Format::A8B8G8R8SrgbPack32 => (),
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        format => {
+            warn!(target: INFO, "can't write screenshots of {:?} images", format);
+            return;
+        }
+    }
+
+    match ImageBuffer::<Rgba<u8>, _>::from_raw(dimensions[0], dimensions[1], pixels) {
+        Some(image) => match image.save(path) {
+            Ok(()) => info!(target: INFO, "screenshot written to {}", path.display()),
+            Err(err) => warn!(target: INFO, "failed to write {}: {}", path.display(), err),
+        },
+        None => warn!(target: INFO, "screenshot readback has the wrong size"),
+    }
+}
+
+macro_rules! impl_from_vulkan_error {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for RenderError {
+                fn from(err: $ty) -> RenderError {
+                    RenderError::Vulkan(Box::new(err))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_vulkan_error!(
+    OomError,
+    DeviceMemoryAllocError,
+    RenderPassCreationError,
+    FramebufferCreationError,
+    PipelineError,
+    ComputePipelineCreationError,
+    BeginRenderPassError,
+    DrawError,
+    DrawIndirectError,
+    DispatchError,
+    ExecuteCommandsError,
+    AutoCommandBufferBuilderContextError,
+    BuildError,
+    CommandBufferExecError,
+    CopyBufferError,
+    CopyBufferImageError,
+    ImageCreationError,
+    SamplerCreationError,
+    PersistentDescriptorSetError,
+    SurfaceCreationError,
+    CapabilitiesError,
+    PersistentDescriptorSetBuildError
+);
+
+impl From<ConfigError> for RenderError {
+    fn from(err: ConfigError) -> RenderError {
+        RenderError::Config(err)
+    }
+}
+
+impl From<SpirvError> for RenderError {
+    fn from(err: SpirvError) -> RenderError {
+        RenderError::Spirv(err)
+    }
+}
+
+impl From<LutError> for RenderError {
+    fn from(err: LutError) -> RenderError {
+        RenderError::Correction(err)
+    }
+}
+
+impl From<GraphError> for RenderError {
+    fn from(err: GraphError) -> RenderError {
+        RenderError::Graph(err)
+    }
+}
+
+impl From<FlushError> for RenderError {
+    fn from(err: FlushError) -> RenderError {
+        match err {
+            FlushError::OutOfDate => RenderError::OutOfDate,
+            FlushError::DeviceLost => RenderError::DeviceLost,
+            err => RenderError::Flush(err),
+        }
+    }
+}
+
+impl RenderError {
+    /// whether the device can't be used anymore, everything made from its
+    /// `VulkanStruct` has to be recreated
+    pub fn is_device_failure(&self) -> bool {
+        match *self {
+            RenderError::DeviceLost | RenderError::Timeout(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RenderError::OutOfDate => write!(f, "swapchain is out of date"),
+            RenderError::DeviceLost => write!(f, "device lost"),
+            RenderError::Timeout(stage) => write!(f, "the GPU timed out {}", stage),
+            RenderError::Swapchain(ref err) => write!(f, "failed to create swapchain: {}", err),
+            RenderError::Acquire(ref err) => write!(f, "failed to acquire image: {}", err),
+            RenderError::Flush(ref err) => write!(f, "failed to submit frame: {}", err),
+            RenderError::Correction(ref err) => write!(f, "colour correction: {}", err),
+            RenderError::Graph(ref err) => write!(f, "render graph: {}", err),
+            RenderError::Config(ref err) => write!(f, "{}", err),
+            RenderError::Spirv(ref err) => write!(f, "compute kernel: {}", err),
+            RenderError::Unsupported(feature) => write!(f, "the device doesn't support {}", feature),
+            RenderError::Order(call) => write!(f, "{} isn't allowed at this point of the frame", call),
+            RenderError::Vulkan(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for RenderError {
+    fn description(&self) -> &str {
+        match *self {
+            RenderError::OutOfDate => "swapchain is out of date",
+            RenderError::DeviceLost => "device lost",
+            RenderError::Timeout(_) => "the GPU timed out",
+            RenderError::Swapchain(_) => "failed to create swapchain",
+            RenderError::Acquire(_) => "failed to acquire image",
+            RenderError::Flush(_) => "failed to submit frame",
+            RenderError::Correction(ref err) => err.description(),
+            RenderError::Graph(ref err) => err.description(),
+            RenderError::Config(ref err) => err.description(),
+            RenderError::Spirv(ref err) => err.description(),
+            RenderError::Unsupported(_) => "unsupported device feature",
+            RenderError::Order(_) => "call out of order",
+            RenderError::Vulkan(ref err) => err.description(),
+        }
+    }
+}
@@ -0,0 +1,25 @@
+#version 450
+layout(local_size_x = 16, local_size_y = 16) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D source;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D destination;
+
+// bilinear, with the pixel centres of both images lined up
+void main() {
+    ivec2 size = imageSize(destination);
+    ivec2 p = ivec2(gl_GlobalInvocationID.xy);
+    if (p.x >= size.x || p.y >= size.y) {
+        return;
+    }
+
+    ivec2 source_size = imageSize(source);
+    vec2 scale = vec2(source_size) / vec2(size);
+    vec2 position = clamp((vec2(p) + 0.5) * scale - 0.5, vec2(0.0), vec2(source_size - 1));
+    ivec2 corner = ivec2(floor(position));
+    ivec2 opposite = min(corner + 1, source_size - 1);
+    vec2 f = position - vec2(corner);
+
+    vec4 top = mix(imageLoad(source, corner), imageLoad(source, ivec2(opposite.x, corner.y)), f.x);
+    vec4 bottom = mix(imageLoad(source, ivec2(corner.x, opposite.y)), imageLoad(source, opposite), f.x);
+    imageStore(destination, p, mix(top, bottom, f.y));
+}
@@ -0,0 +1,141 @@
+//! Scenes recorded on worker threads into secondary command buffers
+//!
+//! A `Scene` is an ordered list of `Drawable`s. `SceneRecorder` splits it
+//! into as many contiguous parts as it has workers, each worker records
+//! its part into a secondary command buffer and the renderer executes
+//! them in order, so the draws keep the order of the scene:
+//!
+//! ```ignore
+//! let recorder = SceneRecorder::new(4)?;
+//! let mut scene = Scene::new();
+//! for vertices in meshes {
+//!     scene.add(Mesh::new(pipeline.clone(), vec![vertices]));
+//! }
+//! loop {
+//!     let mut frame = renderer.begin_frame()?;
+//!     recorder.draw(&renderer, &mut frame, &scene)?;
+//!     renderer.end_frame(frame)?;
+//! }
+//! ```
+//!
+//! `draw`s into the same frame afterwards, e.g. an overlay, go into one
+//! more secondary command buffer recorded on the calling thread.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use vulkano::buffer::BufferAccess;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::pipeline::GraphicsPipelineAbstract;
+
+use super::renderer::RenderError;
+use super::shaders::default_vertex_shader::ty::PreRotation;
+
+mod recorder;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::recorder::SceneRecorder;
+
+/// what the draws of a frame share
+#[derive(Clone)]
+pub struct DrawContext {
+    pub dynamic_state: DynamicState,
+    pub pre_rotation: PreRotation,
+}
+
+/// records draws into a secondary command buffer of `Renderer::subpass()`,
+/// on any thread
+pub trait Drawable: Send + Sync {
+    fn record(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        context: &DrawContext,
+    ) -> Result<AutoCommandBufferBuilder, RenderError>;
+}
+
+/// vertex buffers drawn by a pipeline taking `pre_rotation` as push
+/// constants, like the default or the instanced one
+pub struct Mesh {
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    vertices: Vec<Arc<BufferAccess + Send + Sync>>,
+}
+
+impl Mesh {
+    pub fn new(
+        pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+        vertices: Vec<Arc<BufferAccess + Send + Sync>>,
+    ) -> Mesh {
+        Mesh {
+            pipeline: pipeline,
+            vertices: vertices,
+        }
+    }
+}
+
+impl Drawable for Mesh {
+    fn record(
+        &self,
+        builder: AutoCommandBufferBuilder,
+        context: &DrawContext,
+    ) -> Result<AutoCommandBufferBuilder, RenderError> {
+        Ok(builder.draw(
+            self.pipeline.clone(),
+            context.dynamic_state.clone(),
+            self.vertices.clone(),
+            (),
+            context.pre_rotation,
+        )?)
+    }
+}
+
+/// drawables in the order they are drawn
+#[derive(Clone, Default)]
+pub struct Scene {
+    items: Vec<Arc<Drawable>>,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    pub fn add<D: Drawable + 'static>(&mut self, item: D) {
+        self.items.push(Arc::new(item));
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// the drawables of each of `workers`, see `partition`
+    fn parts(&self, workers: usize) -> Vec<Vec<Arc<Drawable>>> {
+        partition(self.items.len(), workers)
+            .into_iter()
+            .map(|range| self.items[range].to_vec())
+            .collect()
+    }
+}
+
+/// splits `len` items into at most `parts` contiguous, non-empty ranges,
+/// in order, whose lengths differ by one at most
+pub fn partition(len: usize, parts: usize) -> Vec<Range<usize>> {
+    let parts = parts.min(len);
+    let mut ranges = Vec::with_capacity(parts);
+    let mut start = 0;
+    for part in 0..parts {
+        let size = len / parts + if part < len % parts { 1 } else { 0 };
+        ranges.push(start..start + size);
+        start += size;
+    }
+    ranges
+}
@@ -0,0 +1,161 @@
+//! Worker threads recording the parts of a `Scene`
+//!
+//! Command pools are per thread in vulkano; the workers live as long as
+//! the recorder, so theirs are reused from frame to frame.
+
+use std::io;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder};
+use vulkano::device::Queue;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+
+use super::{DrawContext, Drawable, Scene};
+use super::super::renderer::{Frame, RenderError, Renderer};
+
+/// work for a `Pool` thread
+pub trait Task: Send + 'static {
+    type Output: Send + 'static;
+
+    fn run(&self) -> Result<Self::Output, RenderError>;
+}
+
+/// one part of a scene, recorded on a worker
+struct Job {
+    items: Vec<Arc<Drawable>>,
+    queue: Arc<Queue>,
+    subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    context: DrawContext,
+}
+
+impl Task for Job {
+    type Output = AutoCommandBuffer;
+
+    fn run(&self) -> Result<AutoCommandBuffer, RenderError> {
+        let mut builder = AutoCommandBufferBuilder::secondary_graphics_one_time_submit(
+            self.queue.device().clone(),
+            self.queue.family(),
+            self.subpass.clone(),
+        )?;
+        for item in &self.items {
+            builder = item.record(builder, &self.context)?;
+        }
+        Ok(builder.build()?)
+    }
+}
+
+type Outcome<T> = Result<<T as Task>::Output, RenderError>;
+
+struct Worker<T: Task> {
+    tasks: Sender<(T, Sender<Outcome<T>>)>,
+    thread: JoinHandle<()>,
+}
+
+/// threads named `<name>-<index>`, running one task each at a time
+pub struct Pool<T: Task> {
+    workers: Vec<Worker<T>>,
+}
+
+impl<T: Task> Pool<T> {
+    /// starts `workers` threads, at least one
+    pub fn new(name: &str, workers: usize) -> io::Result<Pool<T>> {
+        let mut spawned = Vec::with_capacity(workers.max(1));
+        for index in 0..workers.max(1) {
+            let (tasks, received) = mpsc::channel::<(T, Sender<Outcome<T>>)>();
+            let thread = thread::Builder::new()
+                .name(format!("{}-{}", name, index))
+                .spawn(move || {
+                    for (task, result) in received {
+                        // nobody waits for the result if another task
+                        // failed
+                        let _ = result.send(task.run());
+                    }
+                })?;
+            spawned.push(Worker {
+                tasks: tasks,
+                thread: thread,
+            });
+        }
+        Ok(Pool { workers: spawned })
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// runs each of `tasks`, at most `workers()`, on its own worker; the
+    /// outputs are in the order of `tasks`, or the first error in it
+    pub fn run(&self, tasks: Vec<T>) -> Result<Vec<T::Output>, RenderError> {
+        assert!(tasks.len() <= self.workers.len(), "more tasks than workers");
+        let results: Vec<Receiver<Outcome<T>>> = tasks
+            .into_iter()
+            .zip(&self.workers)
+            .map(|(task, worker)| {
+                let (result, received) = mpsc::channel();
+                worker.tasks.send((task, result)).expect("a pool thread panicked");
+                received
+            })
+            .collect();
+
+        results
+            .into_iter()
+            .map(|received| received.recv().expect("a pool thread panicked"))
+            .collect()
+    }
+}
+
+impl<T: Task> Drop for Pool<T> {
+    fn drop(&mut self) {
+        for Worker { tasks, thread } in self.workers.drain(..) {
+            // ends the loop of the thread
+            drop(tasks);
+            let _ = thread.join();
+        }
+    }
+}
+
+pub struct SceneRecorder {
+    pool: Pool<Job>,
+}
+
+impl SceneRecorder {
+    /// starts `workers` threads, at least one
+    pub fn new(workers: usize) -> io::Result<SceneRecorder> {
+        Ok(SceneRecorder {
+            pool: Pool::new("scene-recorder", workers)?,
+        })
+    }
+
+    pub fn workers(&self) -> usize {
+        self.pool.workers()
+    }
+
+    /// secondary command buffers drawing `scene` in order, one per worker
+    /// with something to draw
+    pub fn record(&self, renderer: &Renderer, scene: &Scene) -> Result<Vec<AutoCommandBuffer>, RenderError> {
+        let context = DrawContext {
+            dynamic_state: renderer.dynamic_state().clone(),
+            pre_rotation: renderer.pre_rotation(),
+        };
+        let jobs = scene
+            .parts(self.pool.workers())
+            .into_iter()
+            .map(|items| Job {
+                items: items,
+                queue: renderer.vulkan_obj().queue.clone(),
+                subpass: renderer.subpass(),
+                context: context.clone(),
+            })
+            .collect();
+        self.pool.run(jobs)
+    }
+
+    /// records `scene` and executes it in `frame`, see
+    /// `Renderer::execute_secondary`
+    pub fn draw(&self, renderer: &Renderer, frame: &mut Frame, scene: &Scene) -> Result<(), RenderError> {
+        let command_buffers = self.record(renderer, scene)?;
+        renderer.execute_secondary(frame, command_buffers)
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+use run::renderer::RenderError;
+
+use super::partition;
+use super::recorder::{Pool, Task};
+
+#[test]
+fn partition_keeps_the_order() {
+    assert_eq!(partition(10, 4), vec![0..3, 3..6, 6..8, 8..10]);
+    assert_eq!(partition(8, 4), vec![0..2, 2..4, 4..6, 6..8]);
+    assert_eq!(partition(1000, 1), vec![0..1000]);
+}
+
+#[test]
+fn partition_skips_idle_workers() {
+    assert_eq!(partition(2, 4), vec![0..1, 1..2]);
+    assert!(partition(0, 4).is_empty());
+    assert!(partition(5, 0).is_empty());
+}
+
+/// sleeps `delay`, then reports its index and thread
+struct Probe {
+    index: usize,
+    delay: Duration,
+    fail: bool,
+}
+
+impl Task for Probe {
+    type Output = (usize, ThreadId);
+
+    fn run(&self) -> Result<(usize, ThreadId), RenderError> {
+        thread::sleep(self.delay);
+        if self.fail {
+            return Err(RenderError::Unsupported("probe"));
+        }
+        Ok((self.index, thread::current().id()))
+    }
+}
+
+/// `count` probes, the first ones finishing last
+fn probes(count: usize, failing: Option<usize>) -> Vec<Probe> {
+    (0..count)
+        .map(|index| Probe {
+            index: index,
+            delay: Duration::from_millis(10 * (count - index) as u64),
+            fail: failing == Some(index),
+        })
+        .collect()
+}
+
+#[test]
+fn pool_runs_tasks_on_several_threads() {
+    let pool = Pool::new("test-pool", 4).unwrap();
+    let outputs = pool.run(probes(4, None)).unwrap();
+    let threads: HashSet<ThreadId> = outputs.iter().map(|&(_, thread)| thread).collect();
+    assert_eq!(threads.len(), 4);
+    assert!(!threads.contains(&thread::current().id()));
+}
+
+#[test]
+fn pool_keeps_the_order_of_the_tasks() {
+    let pool = Pool::new("test-pool", 4).unwrap();
+    let outputs = pool.run(probes(4, None)).unwrap();
+    let order: Vec<usize> = outputs.iter().map(|&(index, _)| index).collect();
+    assert_eq!(order, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn pool_reports_a_failed_task() {
+    let pool = Pool::new("test-pool", 3).unwrap();
+    match pool.run(probes(3, Some(1))) {
+        Err(RenderError::Unsupported("probe")) => (),
+        other => panic!("expected the probe's error, got {:?}", other.map(|_| ())),
+    }
+    // the workers survive it
+    assert_eq!(pool.run(probes(3, None)).unwrap().len(), 3);
+}
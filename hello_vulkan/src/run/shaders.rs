@@ -5,6 +5,34 @@ pub struct Vertex {
 }
 impl_vertex!(Vertex, position);
 
+/// per-instance attributes of `instanced_vertex_shader`, with `Vertex`
+/// for the shape
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Instance {
+    /// in clip space
+    pub offset: [f32; 2],
+    pub scale: f32,
+    pub color: [f32; 4],
+}
+impl_vertex!(Instance, offset, scale, color);
+
+/// vertex of the 2D canvas, in pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+impl_vertex!(ColorVertex, position, color);
+
+/// vertex of a glyph quad, in pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+impl_vertex!(TextVertex, position, uv, color);
+
 
 pub mod default_vertex_shader {
     #[derive(VulkanoShader)]
@@ -20,4 +48,135 @@ pub mod default_fragment_shader {
     #[path = "src/run/default_fragment.glsl"]
     #[allow(dead_code)]
     struct Dummy;
-}
\ No newline at end of file
+}
+/// `Vertex` scaled and moved by an `Instance`
+pub mod instanced_vertex_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "vertex"]
+    #[path = "src/run/instanced_vertex.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+/// one triangle covering the viewport, drawn with `BufferlessVertices`
+pub mod fullscreen_vertex_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "vertex"]
+    #[path = "src/run/fullscreen_vertex.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod pattern_fragment_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/run/pattern_fragment.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod correction_fragment_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/run/correction_fragment.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod blit_fragment_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/run/blit_fragment.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod blur_fragment_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/run/blur_fragment.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod vignette_fragment_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/run/vignette_fragment.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod canvas_vertex_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "vertex"]
+    #[path = "src/run/canvas_vertex.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod canvas_fragment_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/run/canvas_fragment.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod text_vertex_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "vertex"]
+    #[path = "src/run/text_vertex.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod text_fragment_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/run/text_fragment.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+/// separable gaussian over storage images, see `compute::GaussianBlur`
+pub mod gaussian_compute_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "compute"]
+    #[path = "src/run/gaussian_compute.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+/// bilinear resize between storage images, see `compute::FilterChain`
+pub mod resize_compute_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "compute"]
+    #[path = "src/run/resize_compute.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod color_compute_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "compute"]
+    #[path = "src/run/color_compute.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod convolve_compute_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "compute"]
+    #[path = "src/run/convolve_compute.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
+
+pub mod threshold_compute_shader {
+    #[derive(VulkanoShader)]
+    #[ty = "compute"]
+    #[path = "src/run/threshold_compute.glsl"]
+    #[allow(dead_code)]
+    struct Dummy;
+}
@@ -0,0 +1,137 @@
+//! What the renderer draws into and presents
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::{ImageAccess, ImageViewAccess};
+use vulkano::image::swapchain::SwapchainImage;
+use vulkano::swapchain::{self, AcquireError, Swapchain, SwapchainCreationError};
+use vulkano::sync::GpuFuture;
+
+use super::orientation::AppliedOrientation;
+use super::renderer::RenderError;
+use super::watchdog::Stage;
+
+pub trait PresentTarget {
+    fn format(&self) -> Format;
+
+    fn dimensions(&self) -> [u32; 2];
+
+    /// how the configured orientation is applied to the images
+    fn orientation(&self) -> AppliedOrientation;
+
+    /// one image per index returned by `acquire`
+    fn images(&self) -> Vec<Arc<ImageViewAccess + Send + Sync>>;
+
+    /// image `index` for transfers, e.g. reading it back
+    fn image(&self, index: usize) -> Arc<ImageAccess + Send + Sync>;
+
+    /// the next image to render into, and the future rendering must wait on
+    fn acquire(&mut self) -> Result<(usize, Box<GpuFuture>), RenderError>;
+
+    /// presents `image_index` once `future` completes
+    fn present(
+        &mut self,
+        future: Box<GpuFuture>,
+        image_index: usize,
+    ) -> Result<Box<GpuFuture>, RenderError>;
+
+    /// recreates the images, `images()` returns the new ones afterwards
+    fn recreate(&mut self, dimensions: [u32; 2]) -> Result<(), RenderError>;
+}
+
+pub struct SwapchainTarget<W> {
+    swapchain: Arc<Swapchain<W>>,
+    images: Vec<Arc<SwapchainImage<W>>>,
+    queue: Arc<Queue>,
+    orientation: AppliedOrientation,
+    acquire_timeout: Duration,
+}
+
+impl<W> SwapchainTarget<W> {
+    pub fn new(
+        swapchain: Arc<Swapchain<W>>,
+        images: Vec<Arc<SwapchainImage<W>>>,
+        queue: Arc<Queue>,
+        orientation: AppliedOrientation,
+        acquire_timeout: Duration,
+    ) -> SwapchainTarget<W> {
+        SwapchainTarget {
+            swapchain: swapchain,
+            images: images,
+            queue: queue,
+            orientation: orientation,
+            acquire_timeout: acquire_timeout,
+        }
+    }
+
+    pub fn swapchain(&self) -> &Arc<Swapchain<W>> {
+        &self.swapchain
+    }
+}
+
+impl<W> PresentTarget for SwapchainTarget<W>
+where
+    W: Send + Sync + 'static,
+{
+    fn format(&self) -> Format {
+        self.swapchain.format()
+    }
+
+    fn dimensions(&self) -> [u32; 2] {
+        self.swapchain.dimensions()
+    }
+
+    fn orientation(&self) -> AppliedOrientation {
+        self.orientation
+    }
+
+    fn images(&self) -> Vec<Arc<ImageViewAccess + Send + Sync>> {
+        self.images
+            .iter()
+            .map(|image| image.clone() as Arc<ImageViewAccess + Send + Sync>)
+            .collect()
+    }
+
+    fn image(&self, index: usize) -> Arc<ImageAccess + Send + Sync> {
+        self.images[index].clone()
+    }
+
+    fn acquire(&mut self) -> Result<(usize, Box<GpuFuture>), RenderError> {
+        match swapchain::acquire_next_image(self.swapchain.clone(), Some(self.acquire_timeout)) {
+            Ok((image_index, future)) => Ok((image_index, Box::new(future) as Box<GpuFuture>)),
+            Err(AcquireError::OutOfDate) => Err(RenderError::OutOfDate),
+            Err(AcquireError::DeviceLost) => Err(RenderError::DeviceLost),
+            Err(AcquireError::Timeout) => Err(RenderError::Timeout(Stage::Acquire)),
+            Err(err) => Err(RenderError::Acquire(err)),
+        }
+    }
+
+    fn present(
+        &mut self,
+        future: Box<GpuFuture>,
+        image_index: usize,
+    ) -> Result<Box<GpuFuture>, RenderError> {
+        Ok(Box::new(future.then_swapchain_present(
+            self.queue.clone(),
+            self.swapchain.clone(),
+            image_index,
+        )))
+    }
+
+    /// `dimensions` are the surface's, swapped if the surface rotates
+    fn recreate(&mut self, dimensions: [u32; 2]) -> Result<(), RenderError> {
+        let dimensions = self.orientation.image_extent(dimensions);
+        let (swapchain, images) = match self.swapchain.recreate_with_dimension(dimensions) {
+            Ok(r) => r,
+            // the window is being resized faster than we can follow
+            Err(SwapchainCreationError::UnsupportedDimensions) => return Err(RenderError::OutOfDate),
+            Err(err) => return Err(RenderError::Swapchain(err)),
+        };
+        self.swapchain = swapchain;
+        self.images = images;
+        Ok(())
+    }
+}
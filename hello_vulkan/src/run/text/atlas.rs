@@ -0,0 +1,96 @@
+//! Glyph bitmaps packed into one coverage texture
+
+/// free pixels around every glyph, so bilinear filtering doesn't bleed
+const PADDING: u32 = 1;
+
+/// a row of glyphs of up to `height` pixels
+#[derive(Debug, Clone)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// first free column
+    x: u32,
+}
+
+/// coverage bitmap, one byte per pixel, packed in shelves
+pub struct Atlas {
+    size: [u32; 2],
+    pixels: Vec<u8>,
+    shelves: Vec<Shelf>,
+    /// set when the pixels changed since the last upload
+    dirty: bool,
+}
+
+impl Atlas {
+    pub fn new(size: [u32; 2]) -> Atlas {
+        Atlas {
+            size: size,
+            pixels: vec![0; (size[0] * size[1]) as usize],
+            shelves: Vec::new(),
+            dirty: true,
+        }
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// true once, after something was written
+    pub fn take_dirty(&mut self) -> bool {
+        let dirty = self.dirty;
+        self.dirty = false;
+        dirty
+    }
+
+    pub fn clear(&mut self) {
+        for pixel in &mut self.pixels {
+            *pixel = 0;
+        }
+        self.shelves.clear();
+        self.dirty = true;
+    }
+
+    /// top left corner of a free `size` region, `None` when full
+    pub fn allocate(&mut self, size: [u32; 2]) -> Option<[u32; 2]> {
+        let (width, height) = (size[0] + PADDING, size[1] + PADDING);
+        if width > self.size[0] {
+            return None;
+        }
+
+        // the lowest shelf it fits in wastes the least height
+        let atlas_width = self.size[0];
+        let best = self.shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && shelf.x + width <= atlas_width)
+            .min_by_key(|shelf| shelf.height);
+        if let Some(shelf) = best {
+            let origin = [shelf.x, shelf.y];
+            shelf.x += width;
+            return Some(origin);
+        }
+
+        let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+        if y + height > self.size[1] {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y: y,
+            height: height,
+            x: width,
+        });
+        Some([0, y])
+    }
+
+    /// writes `coverage` of `width` pixels per row at `origin`
+    pub fn write(&mut self, origin: [u32; 2], width: u32, coverage: &[u8]) {
+        for (row, line) in coverage.chunks(width as usize).enumerate() {
+            let start = ((origin[1] + row as u32) * self.size[0] + origin[0]) as usize;
+            self.pixels[start..start + line.len()].copy_from_slice(line);
+        }
+        self.dirty = true;
+    }
+}
@@ -0,0 +1,116 @@
+//! Line breaking and placement, independent of the font format
+
+/// what the layout needs from a font at a given size, in pixels
+pub trait GlyphMetrics {
+    fn advance(&self, ch: char) -> f32;
+    fn kerning(&self, first: char, second: char) -> f32;
+    /// from the top of a line to its baseline
+    fn ascent(&self) -> f32;
+    /// from a baseline to the next one
+    fn line_height(&self) -> f32;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// a glyph with the pen position on its baseline, relative to the top
+/// left of the text
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlacedGlyph {
+    pub ch: char,
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    /// width of the widest line, or `max_width`, and height of the lines
+    pub size: [f32; 2],
+}
+
+/// breaks `text` at line ends and, with `max_width`, between words; words
+/// wider than the line are broken between characters
+pub fn layout<M: GlyphMetrics>(metrics: &M, text: &str, max_width: Option<f32>, align: Align) -> TextLayout {
+    let mut lines: Vec<Vec<char>> = Vec::new();
+    for paragraph in text.lines() {
+        match max_width {
+            Some(max_width) => wrap(metrics, paragraph, max_width, &mut lines),
+            None => lines.push(paragraph.chars().collect()),
+        }
+    }
+
+    let widths: Vec<f32> = lines.iter().map(|line| measure(metrics, line)).collect();
+    let width = max_width.unwrap_or_else(|| widths.iter().cloned().fold(0.0, f32::max));
+
+    let mut glyphs = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let mut x = match align {
+            Align::Left => 0.0,
+            Align::Center => (width - widths[index]) * 0.5,
+            Align::Right => width - widths[index],
+        };
+        let y = metrics.ascent() + index as f32 * metrics.line_height();
+        let mut previous = None;
+        for &ch in line {
+            if let Some(previous) = previous {
+                x += metrics.kerning(previous, ch);
+            }
+            glyphs.push(PlacedGlyph {
+                ch: ch,
+                position: [x, y],
+            });
+            x += metrics.advance(ch);
+            previous = Some(ch);
+        }
+    }
+
+    TextLayout {
+        glyphs: glyphs,
+        size: [width, lines.len() as f32 * metrics.line_height()],
+    }
+}
+
+/// advance of `line`, kerning included
+pub fn measure<M: GlyphMetrics>(metrics: &M, line: &[char]) -> f32 {
+    let mut width = 0.0;
+    for (index, &ch) in line.iter().enumerate() {
+        if index > 0 {
+            width += metrics.kerning(line[index - 1], ch);
+        }
+        width += metrics.advance(ch);
+    }
+    width
+}
+
+/// greedy, spaces at the breaks are dropped
+fn wrap<M: GlyphMetrics>(metrics: &M, paragraph: &str, max_width: f32, lines: &mut Vec<Vec<char>>) {
+    let mut line: Vec<char> = Vec::new();
+    for (index, word) in paragraph.split(' ').enumerate() {
+        let word: Vec<char> = word.chars().collect();
+        if index > 0 {
+            let mut candidate = line.clone();
+            candidate.push(' ');
+            candidate.extend(&word);
+            if measure(metrics, &candidate) <= max_width {
+                line = candidate;
+                continue;
+            }
+            lines.push(line);
+            line = Vec::new();
+        }
+
+        for ch in word {
+            line.push(ch);
+            if line.len() > 1 && measure(metrics, &line) > max_width {
+                line.pop();
+                lines.push(line);
+                line = vec![ch];
+            }
+        }
+    }
+    lines.push(line);
+}
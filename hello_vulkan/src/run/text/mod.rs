@@ -0,0 +1,345 @@
+//! Text drawing from TrueType and OpenType fonts
+//!
+//! Glyphs are rasterized on the CPU the first time they are used at a
+//! size, packed into one coverage texture and drawn as textured quads,
+//! blended over the frame:
+//!
+//! ```ignore
+//! let font = Font::load(&config.text.font)?;
+//! let mut text = TextRenderer::new(&renderer, font)?;
+//! let style = TextStyle::new(16.0);
+//! loop {
+//!     let mut frame = renderer.begin_frame()?;
+//!     renderer.begin_overlay(&mut frame)?;
+//!     text.queue("Hello, Vulkan!", [20.0, 20.0], &style);
+//!     text.draw(&mut renderer, &mut frame)?;
+//!     renderer.end_frame(frame)?;
+//! }
+//! ```
+//!
+//! Positions are in pixels with the origin at the top left of the upright
+//! content, like the canvas.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use rusttype::{self, point, Scale};
+use vulkano::buffer::{BufferAccess, CpuBufferPool};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::sampler::Sampler;
+
+use log_targets::INFO;
+use super::graph::linear_sampler;
+use super::pipeline_cache::{Blend, CachedPipeline};
+use super::renderer::{Frame, RenderError, Renderer};
+use super::shaders::TextVertex;
+use super::shaders::text_fragment_shader::Shader as FragmentShader;
+use super::shaders::text_vertex_shader::Shader as VertexShader;
+use super::shaders::text_vertex_shader::ty::TextTransform;
+
+mod atlas;
+mod layout;
+mod overlay;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::atlas::Atlas;
+pub use self::layout::{layout, measure, Align, GlyphMetrics, PlacedGlyph, TextLayout};
+pub use self::overlay::StatsOverlay;
+
+const ATLAS_SIZE: [u32; 2] = [1024, 1024];
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    Invalid(rusttype::Error),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FontError::Io(ref err) => write!(f, "can't read the font: {}", err),
+            FontError::Invalid(ref err) => write!(f, "not a usable font: {}", err),
+        }
+    }
+}
+
+impl Error for FontError {
+    fn description(&self) -> &str {
+        match *self {
+            FontError::Io(_) => "can't read the font",
+            FontError::Invalid(_) => "not a usable font",
+        }
+    }
+}
+
+/// the first font of a TrueType or OpenType file
+pub struct Font {
+    font: rusttype::Font<'static>,
+}
+
+impl Font {
+    pub fn load(path: &Path) -> Result<Font, FontError> {
+        let mut bytes = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .map_err(FontError::Io)?;
+        Font::from_bytes(bytes)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Font, FontError> {
+        let font = rusttype::Font::from_bytes(bytes).map_err(FontError::Invalid)?;
+        Ok(Font { font: font })
+    }
+
+    /// metrics at `size` pixels per em
+    pub fn metrics<'a>(&'a self, size: f32) -> ScaledFont<'a> {
+        ScaledFont {
+            font: &self.font,
+            scale: Scale::uniform(size),
+        }
+    }
+}
+
+pub struct ScaledFont<'a> {
+    font: &'a rusttype::Font<'static>,
+    scale: Scale,
+}
+
+impl<'a> GlyphMetrics for ScaledFont<'a> {
+    fn advance(&self, ch: char) -> f32 {
+        self.font.glyph(ch).scaled(self.scale).h_metrics().advance_width
+    }
+
+    fn kerning(&self, first: char, second: char) -> f32 {
+        self.font.pair_kerning(self.scale, first, second)
+    }
+
+    fn ascent(&self) -> f32 {
+        self.font.v_metrics(self.scale).ascent
+    }
+
+    fn line_height(&self) -> f32 {
+        let v_metrics = self.font.v_metrics(self.scale);
+        v_metrics.ascent - v_metrics.descent + v_metrics.line_gap
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextStyle {
+    /// pixels per em
+    pub size: f32,
+    pub color: [f32; 4],
+    pub align: Align,
+    /// wraps between words when set
+    pub max_width: Option<f32>,
+}
+
+impl TextStyle {
+    /// white, left aligned, not wrapped
+    pub fn new(size: f32) -> TextStyle {
+        TextStyle {
+            size: size,
+            color: [1.0, 1.0, 1.0, 1.0],
+            align: Align::Left,
+            max_width: None,
+        }
+    }
+}
+
+/// where a rasterized glyph is, relative to the pen and in the atlas
+#[derive(Debug, Copy, Clone)]
+struct GlyphEntry {
+    offset: [f32; 2],
+    size: [f32; 2],
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+}
+
+pub struct TextRenderer {
+    font: Font,
+    atlas: Atlas,
+    /// `None` for glyphs without pixels, like spaces
+    glyphs: HashMap<(char, u32), Option<GlyphEntry>>,
+    /// set when a glyph didn't fit, the atlas is emptied after the frame
+    full: bool,
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    pool: CpuBufferPool<TextVertex>,
+    sampler: Arc<Sampler>,
+    set: Option<Arc<DescriptorSet + Send + Sync>>,
+    vertices: Vec<TextVertex>,
+}
+
+impl TextRenderer {
+    pub fn new(renderer: &Renderer, font: Font) -> Result<TextRenderer, RenderError> {
+        let device = renderer.vulkan_obj().device.clone();
+        let vertex_shader = VertexShader::load(device.clone())?;
+        let fragment_shader = FragmentShader::load(device.clone())?;
+
+        let pipeline = CachedPipeline::new(
+            renderer.vulkan_obj(),
+            SingleBufferDefinition::<TextVertex>::new(),
+            vertex_shader.main_entry_point(),
+            fragment_shader.main_entry_point(),
+            Blend::Alpha,
+            renderer.subpass(),
+        )?;
+
+        Ok(TextRenderer {
+            font: font,
+            atlas: Atlas::new(ATLAS_SIZE),
+            glyphs: HashMap::new(),
+            full: false,
+            pipeline: Arc::new(pipeline),
+            pool: CpuBufferPool::vertex_buffer(device.clone()),
+            sampler: linear_sampler(&device)?,
+            set: None,
+            vertices: Vec::new(),
+        })
+    }
+
+    /// size `text` would take when queued with `style`
+    pub fn measure(&self, text: &str, style: &TextStyle) -> [f32; 2] {
+        layout(&self.font.metrics(style.size), text, style.max_width, style.align).size
+    }
+
+    /// lays out `text` with its top left corner at `origin`, for the next
+    /// `draw`; returns its size
+    pub fn queue(&mut self, text: &str, origin: [f32; 2], style: &TextStyle) -> [f32; 2] {
+        let text_layout = layout(&self.font.metrics(style.size), text, style.max_width, style.align);
+        for placed in &text_layout.glyphs {
+            let entry = match self.glyph(placed.ch, style.size) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            // whole pixels keep the rasterized coverage sharp
+            let x = (origin[0] + placed.position[0]).round() + entry.offset[0];
+            let y = (origin[1] + placed.position[1]).round() + entry.offset[1];
+            let (x1, y1) = (x + entry.size[0], y + entry.size[1]);
+            let (u0, v0) = (entry.uv_min[0], entry.uv_min[1]);
+            let (u1, v1) = (entry.uv_max[0], entry.uv_max[1]);
+            for &(position, uv) in &[
+                ([x, y], [u0, v0]),
+                ([x1, y], [u1, v0]),
+                ([x1, y1], [u1, v1]),
+                ([x, y], [u0, v0]),
+                ([x1, y1], [u1, v1]),
+                ([x, y1], [u0, v1]),
+            ] {
+                self.vertices.push(TextVertex {
+                    position: position,
+                    uv: uv,
+                    color: style.color,
+                });
+            }
+        }
+        text_layout.size
+    }
+
+    /// records the text queued since the last call
+    pub fn draw(&mut self, renderer: &mut Renderer, frame: &mut Frame) -> Result<(), RenderError> {
+        if self.atlas.take_dirty() || self.set.is_none() {
+            let size = self.atlas.size();
+            let upload = renderer.create_image(
+                self.atlas.pixels().iter().cloned(),
+                Dimensions::Dim2d {
+                    width: size[0],
+                    height: size[1],
+                },
+                Format::R8Unorm,
+            )?;
+            // drawn right away, the frame waits for the copy
+            let image = renderer.use_upload(&upload)?;
+            let set = PersistentDescriptorSet::start(self.pipeline.clone(), 0)
+                .add_sampled_image(image, self.sampler.clone())?
+                .build()?;
+            self.set = Some(Arc::new(set));
+        }
+
+        if !self.vertices.is_empty() {
+            let vertices = self.pool.chunk(self.vertices.drain(..))?;
+            let size = renderer.content_dimensions();
+            let transform = TextTransform {
+                matrix: renderer.pre_rotation().matrix,
+                size: [size[0] as f32, size[1] as f32],
+            };
+            renderer.draw(
+                frame,
+                self.pipeline.clone(),
+                vec![Arc::new(vertices) as Arc<BufferAccess + Send + Sync>],
+                self.set.clone().expect("atlas uploaded above"),
+                transform,
+            )?;
+        }
+
+        if self.full {
+            info!(target: INFO, "glyph atlas full, emptied");
+            self.atlas.clear();
+            self.glyphs.clear();
+            self.full = false;
+        }
+        Ok(())
+    }
+
+    /// rasterizes `ch` at `size` into the atlas the first time
+    fn glyph(&mut self, ch: char, size: f32) -> Option<GlyphEntry> {
+        let key = (ch, size.to_bits());
+        if let Some(entry) = self.glyphs.get(&key) {
+            return *entry;
+        }
+
+        let glyph = self.font
+            .font
+            .glyph(ch)
+            .scaled(Scale::uniform(size))
+            .positioned(point(0.0, 0.0));
+        let bounds = match glyph.pixel_bounding_box() {
+            Some(bounds) => bounds,
+            None => {
+                self.glyphs.insert(key, None);
+                return None;
+            }
+        };
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        let origin = match self.atlas.allocate([width, height]) {
+            Some(origin) => origin,
+            None => {
+                // not cached, it is drawn once the atlas is emptied
+                self.full = true;
+                return None;
+            }
+        };
+
+        let mut coverage = vec![0u8; (width * height) as usize];
+        glyph.draw(|x, y, value| {
+            coverage[(y * width + x) as usize] = (value * 255.0 + 0.5) as u8;
+        });
+        self.atlas.write(origin, width, &coverage);
+
+        let atlas_size = self.atlas.size();
+        let entry = GlyphEntry {
+            offset: [bounds.min.x as f32, bounds.min.y as f32],
+            size: [width as f32, height as f32],
+            uv_min: [
+                origin[0] as f32 / atlas_size[0] as f32,
+                origin[1] as f32 / atlas_size[1] as f32,
+            ],
+            uv_max: [
+                (origin[0] + width) as f32 / atlas_size[0] as f32,
+                (origin[1] + height) as f32 / atlas_size[1] as f32,
+            ],
+        };
+        self.glyphs.insert(key, Some(entry));
+        Some(entry)
+    }
+}
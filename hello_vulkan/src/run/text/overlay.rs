@@ -0,0 +1,77 @@
+//! Frame rate and frame time, drawn in the top left corner
+
+use std::time::{Duration, Instant};
+
+use super::super::canvas::Batch;
+use super::{TextRenderer, TextStyle};
+
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
+
+/// averages over one second
+pub struct StatsOverlay {
+    frames: u32,
+    since: Instant,
+    last: Option<Instant>,
+    /// longest frame of the current second
+    longest: Duration,
+    label: String,
+}
+
+impl StatsOverlay {
+    pub fn new(now: Instant) -> StatsOverlay {
+        StatsOverlay {
+            frames: 0,
+            since: now,
+            last: None,
+            longest: Duration::from_secs(0),
+            label: "-- fps".to_owned(),
+        }
+    }
+
+    /// call once per frame; true when the label changed
+    pub fn frame(&mut self, now: Instant) -> bool {
+        if let Some(last) = self.last {
+            self.longest = self.longest.max(now - last);
+        }
+        self.last = Some(now);
+        self.frames += 1;
+
+        let elapsed = now - self.since;
+        if elapsed < Duration::from_secs(1) {
+            return false;
+        }
+        let elapsed = seconds(elapsed);
+        self.label = format!(
+            "{:.1} fps  {:.2} ms  max {:.2} ms",
+            self.frames as f64 / elapsed,
+            elapsed * 1000.0 / self.frames as f64,
+            seconds(self.longest) * 1000.0
+        );
+        self.frames = 0;
+        self.since = now;
+        self.longest = Duration::from_secs(0);
+        true
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// the background goes in `batch`, to be drawn before `text`
+    pub fn queue(&self, batch: &mut Batch, text: &mut TextRenderer, style: &TextStyle) {
+        let origin = [MARGIN + PADDING, MARGIN + PADDING];
+        let size = text.queue(&self.label, origin, style);
+        batch.fill_rounded_rect(
+            [MARGIN, MARGIN],
+            [size[0] + 2.0 * PADDING, size[1] + 2.0 * PADDING],
+            PADDING,
+            BACKGROUND,
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+use super::{layout, Align, Atlas, GlyphMetrics, StatsOverlay};
+
+/// 10 pixels per character, "AV" kerned by 2
+struct Monospace;
+
+impl GlyphMetrics for Monospace {
+    fn advance(&self, _: char) -> f32 {
+        10.0
+    }
+
+    fn kerning(&self, first: char, second: char) -> f32 {
+        if first == 'A' && second == 'V' {
+            -2.0
+        } else {
+            0.0
+        }
+    }
+
+    fn ascent(&self) -> f32 {
+        15.0
+    }
+
+    fn line_height(&self) -> f32 {
+        20.0
+    }
+}
+
+fn lines(text: &str, max_width: Option<f32>) -> Vec<String> {
+    let text_layout = layout(&Monospace, text, max_width, Align::Left);
+    let mut lines: Vec<String> = Vec::new();
+    let mut baseline = None;
+    for glyph in &text_layout.glyphs {
+        if baseline != Some(glyph.position[1]) {
+            baseline = Some(glyph.position[1]);
+            lines.push(String::new());
+        }
+        lines.last_mut().unwrap().push(glyph.ch);
+    }
+    lines
+}
+
+#[test]
+fn wraps_between_words() {
+    assert_eq!(lines("the quick brown fox", Some(100.0)), ["the quick", "brown fox"]);
+    assert_eq!(lines("the quick brown fox", None), ["the quick brown fox"]);
+    assert_eq!(lines("one\ntwo", None), ["one", "two"]);
+    // too long for a line on its own
+    assert_eq!(lines("abcdefgh ij", Some(50.0)), ["abcde", "fgh", "ij"]);
+}
+
+#[test]
+fn aligns_and_kerns() {
+    let text_layout = layout(&Monospace, "AVA\nab", None, Align::Right);
+    assert_eq!(text_layout.size, [28.0, 40.0]);
+    let positions: Vec<[f32; 2]> = text_layout.glyphs.iter().map(|g| g.position).collect();
+    assert_eq!(positions, [[0.0, 15.0], [8.0, 15.0], [18.0, 15.0], [8.0, 35.0], [18.0, 35.0]]);
+
+    let centred = layout(&Monospace, "ab", Some(40.0), Align::Center);
+    assert_eq!(centred.glyphs[0].position, [10.0, 15.0]);
+    // not ASCII
+    assert_eq!(layout(&Monospace, "héllo wörld", None, Align::Left).size, [110.0, 20.0]);
+}
+
+#[test]
+fn atlas_packs_in_shelves() {
+    let mut atlas = Atlas::new([32, 32]);
+    assert_eq!(atlas.allocate([10, 10]), Some([0, 0]));
+    assert_eq!(atlas.allocate([10, 8]), Some([11, 0]));
+    assert_eq!(atlas.allocate([10, 12]), Some([0, 11]));
+    assert_eq!(atlas.allocate([40, 1]), None);
+    assert_eq!(atlas.allocate([20, 20]), None);
+
+    atlas.write([11, 0], 2, &[1, 2, 3, 4]);
+    assert_eq!(&atlas.pixels()[11..13], &[1, 2]);
+    assert_eq!(&atlas.pixels()[32 + 11..32 + 13], &[3, 4]);
+    assert!(atlas.take_dirty() && !atlas.take_dirty());
+
+    atlas.clear();
+    assert_eq!(atlas.allocate([20, 20]), Some([0, 0]));
+}
+
+#[test]
+fn overlay_averages_over_a_second() {
+    let start = Instant::now();
+    let mut overlay = StatsOverlay::new(start);
+    // every 25ms, with one frame missed
+    for frame in (1..40).filter(|frame| *frame != 20) {
+        assert!(!overlay.frame(start + Duration::from_millis(frame * 25)));
+    }
+    assert!(overlay.frame(start + Duration::from_millis(1000)));
+    assert_eq!(overlay.label(), "39.0 fps  25.64 ms  max 50.00 ms");
+}
@@ -0,0 +1,11 @@
+#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec4 v_color;
+layout(location = 0) out vec4 f_color;
+
+// coverage of the glyphs, in the red channel
+layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+void main() {
+    f_color = vec4(v_color.rgb, v_color.a * texture(atlas, v_uv).r);
+}
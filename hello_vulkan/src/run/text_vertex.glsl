@@ -0,0 +1,24 @@
+#version 450
+// in pixels, origin at the top left
+layout(location = 0) in vec2 position;
+// in the glyph atlas, normalized
+layout(location = 1) in vec2 uv;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+
+layout(push_constant) uniform TextTransform {
+    // column major 2x2 matrix, the pre-rotation of `PreRotation`
+    vec4 matrix;
+    // size of the upright content in pixels
+    vec2 size;
+} transform;
+
+void main() {
+    mat2 m = mat2(transform.matrix.xy, transform.matrix.zw);
+    vec2 clip = position / transform.size * 2.0 - 1.0;
+    gl_Position = vec4(m * clip, 0.0, 1.0);
+    v_uv = uv;
+    v_color = color;
+}
@@ -0,0 +1,24 @@
+#version 450
+layout(local_size_x = 16, local_size_y = 16) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D source;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D destination;
+
+layout(push_constant) uniform Threshold {
+    float level;
+} threshold;
+
+// Rec. 709 luma
+const vec3 LUMA = vec3(0.2126, 0.7152, 0.0722);
+
+void main() {
+    ivec2 size = imageSize(source);
+    ivec2 p = ivec2(gl_GlobalInvocationID.xy);
+    if (p.x >= size.x || p.y >= size.y) {
+        return;
+    }
+
+    vec4 color = imageLoad(source, p);
+    float v = dot(color.rgb, LUMA) >= threshold.level ? 1.0 : 0.0;
+    imageStore(destination, p, vec4(vec3(v), color.a));
+}
@@ -0,0 +1,280 @@
+//! Uploads to device local resources on `VulkanStruct::transfer_queue`
+//!
+//! The copies from the staging buffers are recorded into one command
+//! buffer, a batch, submitted once it holds `BATCH_BYTES`, on `flush` or
+//! when one of its resources is first used. Nothing waits for the copies
+//! on the CPU: the batch signals a semaphore, and the first submission
+//! using one of its resources waits for it, see `Renderer::use_upload`.
+//!
+//! The semaphore has no timeout of its own: copies hung on the transfer
+//! queue hold back the frames waiting for them, until acquiring an image
+//! or reading a frame back times out. The submission itself is watched
+//! as `Stage::Upload`.
+//!
+//! The buffers and images are created with concurrent sharing between
+//! all the queue families of the device, so no ownership transfer is
+//! needed from the transfer queue to the others.
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, ImmutableBuffer};
+use vulkano::buffer::immutable::ImmutableBufferInitialization;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::device::{Device, Queue};
+use vulkano::format::{AcceptsPixels, Format};
+use vulkano::image::{Dimensions, ImageLayout, ImageUsage, ImmutableImage, MipmapsCount};
+use vulkano::sync::GpuFuture;
+
+use super::VulkanStruct;
+use super::renderer::RenderError;
+use super::watchdog::Stage;
+
+/// staging bytes a batch takes before it is submitted on its own
+pub const BATCH_BYTES: usize = 16 * 1024 * 1024;
+
+/// copies submitted together
+struct Batch {
+    vulkan_obj: Arc<VulkanStruct>,
+    /// `None` once submitted
+    builder: RefCell<Option<AutoCommandBufferBuilder>>,
+    /// the semaphore of the submission, until the first user takes it
+    future: RefCell<Option<Box<GpuFuture>>>,
+    /// the device is gone, the future is leaked instead of waited for
+    lost: Rc<Cell<bool>>,
+}
+
+impl Batch {
+    fn is_recording(&self) -> bool {
+        self.builder.borrow().is_some()
+    }
+
+    fn record<F>(&self, copy: F) -> Result<(), RenderError>
+    where
+        F: FnOnce(AutoCommandBufferBuilder) -> Result<AutoCommandBufferBuilder, RenderError>,
+    {
+        let mut builder = self.builder.borrow_mut();
+        let recorded = copy(builder.take().expect("batch already submitted"))?;
+        *builder = Some(recorded);
+        Ok(())
+    }
+
+    fn submit(&self) -> Result<(), RenderError> {
+        let builder = match self.builder.borrow_mut().take() {
+            Some(builder) => builder,
+            None => return Ok(()),
+        };
+        self.vulkan_obj.enter_stage(Stage::Upload);
+        let future = execute(builder, &self.vulkan_obj.transfer_queue);
+        self.vulkan_obj.leave_stage();
+        *self.future.borrow_mut() = Some(future?);
+        Ok(())
+    }
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        // a semaphore future waits for the queue to be idle when dropped
+        if self.lost.get() {
+            mem::forget(self.future.borrow_mut().take());
+        }
+    }
+}
+
+/// a resource whose copy may still be running on the transfer queue
+pub struct Upload<R> {
+    resource: R,
+    batch: Rc<Batch>,
+}
+
+impl<R> Upload<R> {
+    /// usable in a submission once `take_future` was waited for
+    pub fn resource(&self) -> &R {
+        &self.resource
+    }
+
+    /// submits the batch if it wasn't; the future the first time it is
+    /// asked for any resource of the batch, `None` after that
+    pub fn take_future(&self) -> Result<Option<Box<GpuFuture>>, RenderError> {
+        self.batch.submit()?;
+        Ok(self.batch.future.borrow_mut().take())
+    }
+}
+
+pub struct UploadManager {
+    vulkan_obj: Arc<VulkanStruct>,
+    /// the batch being recorded, `None` until the next copy
+    batch: Option<Rc<Batch>>,
+    batch_bytes: usize,
+    lost: Rc<Cell<bool>>,
+}
+
+impl UploadManager {
+    pub fn new(vulkan_obj: Arc<VulkanStruct>) -> UploadManager {
+        UploadManager {
+            vulkan_obj: vulkan_obj,
+            batch: None,
+            batch_bytes: 0,
+            lost: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// uploads `data` to a device local buffer with `usage`
+    pub fn buffer_from_iter<T, I>(
+        &mut self,
+        data: I,
+        usage: BufferUsage,
+    ) -> Result<Upload<Arc<ImmutableBuffer<[T]>>>, RenderError>
+    where
+        I: ExactSizeIterator<Item = T>,
+        T: Send + Sync + 'static,
+    {
+        let device = &self.vulkan_obj.device;
+        let staging = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(), data)?;
+        let usage = BufferUsage {
+            transfer_destination: true,
+            ..usage
+        };
+        let (buffer, init) = shared_buffer(device, staging.size(), usage)?;
+        let batch = self.record(staging.size(), |builder| Ok(builder.copy_buffer(staging, init)?))?;
+        Ok(Upload {
+            resource: buffer,
+            batch: batch,
+        })
+    }
+
+    /// uploads `data` to a device local buffer with `usage`
+    pub fn buffer_from_data<T>(
+        &mut self,
+        data: T,
+        usage: BufferUsage,
+    ) -> Result<Upload<Arc<ImmutableBuffer<T>>>, RenderError>
+    where
+        T: Send + Sync + 'static,
+    {
+        let device = &self.vulkan_obj.device;
+        let staging = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::transfer_source(), data)?;
+        let usage = BufferUsage {
+            transfer_destination: true,
+            ..usage
+        };
+        let (buffer, init) = shared_buffer(device, staging.size(), usage)?;
+        let batch = self.record(staging.size(), |builder| Ok(builder.copy_buffer(staging, init)?))?;
+        Ok(Upload {
+            resource: buffer,
+            batch: batch,
+        })
+    }
+
+    /// uploads `data` to a sampled image without mipmaps
+    pub fn image_from_iter<P, I>(
+        &mut self,
+        data: I,
+        dimensions: Dimensions,
+        format: Format,
+    ) -> Result<Upload<Arc<ImmutableImage<Format>>>, RenderError>
+    where
+        P: Send + Sync + Clone + 'static,
+        I: ExactSizeIterator<Item = P>,
+        Format: AcceptsPixels<P>,
+    {
+        let device = &self.vulkan_obj.device;
+        let staging = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(), data)?;
+        let usage = ImageUsage {
+            transfer_destination: true,
+            sampled: true,
+            ..ImageUsage::none()
+        };
+        let (image, init) = ImmutableImage::uninitialized(
+            device.clone(),
+            dimensions,
+            format,
+            MipmapsCount::One,
+            usage,
+            ImageLayout::ShaderReadOnlyOptimal,
+            device.active_queue_families(),
+        )?;
+        let batch = self.record(staging.size(), |builder| {
+            Ok(builder.copy_buffer_to_image_dimensions(
+                staging,
+                init,
+                [0, 0, 0],
+                dimensions.width_height_depth(),
+                0,
+                dimensions.array_layers_with_cube(),
+                0,
+            )?)
+        })?;
+        Ok(Upload {
+            resource: image,
+            batch: batch,
+        })
+    }
+
+    /// submits the copies recorded so far
+    pub fn flush(&mut self) -> Result<(), RenderError> {
+        self.batch_bytes = 0;
+        match self.batch.take() {
+            Some(batch) => batch.submit(),
+            None => Ok(()),
+        }
+    }
+
+    /// the futures of the batches are leaked from now on, waiting for
+    /// them could block forever
+    pub fn device_lost(&self) {
+        self.lost.set(true);
+    }
+
+    /// records `copy` of `bytes` into the current batch
+    fn record<F>(&mut self, bytes: usize, copy: F) -> Result<Rc<Batch>, RenderError>
+    where
+        F: FnOnce(AutoCommandBufferBuilder) -> Result<AutoCommandBufferBuilder, RenderError>,
+    {
+        // submitted early when a resource was used before `flush`
+        let recording = match self.batch {
+            Some(ref batch) => batch.is_recording(),
+            None => false,
+        };
+        if !recording {
+            let queue = self.vulkan_obj.transfer_queue.clone();
+            let builder = AutoCommandBufferBuilder::primary_one_time_submit(queue.device().clone(), queue.family())?;
+            self.batch = Some(Rc::new(Batch {
+                vulkan_obj: self.vulkan_obj.clone(),
+                builder: RefCell::new(Some(builder)),
+                future: RefCell::new(None),
+                lost: self.lost.clone(),
+            }));
+            self.batch_bytes = 0;
+        }
+
+        let batch = self.batch.clone().expect("batch started above");
+        batch.record(copy)?;
+        self.batch_bytes += bytes;
+        if self.batch_bytes >= BATCH_BYTES {
+            self.flush()?;
+        }
+        Ok(batch)
+    }
+}
+
+fn execute(builder: AutoCommandBufferBuilder, queue: &Arc<Queue>) -> Result<Box<GpuFuture>, RenderError> {
+    let future = builder
+        .build()?
+        .execute(queue.clone())?
+        .then_signal_semaphore_and_flush()?;
+    Ok(Box::new(future))
+}
+
+/// a buffer of `size` bytes, `uninitialized` and `uninitialized_array`
+/// would make it exclusive to one family
+fn shared_buffer<T: ?Sized>(
+    device: &Arc<Device>,
+    size: usize,
+    usage: BufferUsage,
+) -> Result<(Arc<ImmutableBuffer<T>>, ImmutableBufferInitialization<T>), RenderError> {
+    // initialized by the copy recorded right after, before any use
+    Ok(unsafe { ImmutableBuffer::raw(device.clone(), size, usage, device.active_queue_families())? })
+}
@@ -0,0 +1,20 @@
+#version 450
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+layout(push_constant) uniform VignetteParams {
+    // output size in pixels
+    vec2 size;
+    // how much the corners are darkened, 0 to 1
+    float strength;
+} params;
+
+void main() {
+    vec2 uv = gl_FragCoord.xy / params.size;
+    // 0 at the centre, 1 in the corners
+    float distance = length(uv - 0.5) * sqrt(2.0);
+    float falloff = 1.0 - params.strength * smoothstep(0.4, 1.0, distance);
+    vec4 color = texture(source, uv);
+    f_color = vec4(color.rgb * falloff, color.a);
+}
@@ -0,0 +1,239 @@
+//! Bounded waits on the GPU, and a thread reporting stalled frames
+//!
+//! Image acquisitions and fence waits give up after the timeouts of the
+//! `[watchdog]` section with `RenderError::Timeout`. Driver calls without
+//! a timeout can still block, e.g. a submission to a hung queue: the
+//! `Watchdog` thread notices the frame loop staying in one `Stage` for
+//! longer than `stall` and logs which one, from outside the blocked
+//! thread.
+//!
+//! A fence that wasn't signalled is leaked rather than dropped, vulkano
+//! waits for it without a timeout when it's dropped, and panics if the
+//! device is lost. What it waits for goes with it: the command buffers
+//! and, through them, the device, and for a frame the swapchain and the
+//! surface. Every recovery leaks the old device that way, which is why
+//! `watchdog.recoveries` bounds them; the swapchain keeps its surface,
+//! e.g. a display plane, which the new device may then fail to use.
+
+use std::cmp;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use vulkano::sync::{FenceSignalFuture, FlushError, GpuFuture};
+
+use config::WatchdogConfig;
+use log_targets::INFO;
+
+use super::renderer::RenderError;
+
+#[cfg(test)]
+mod tests;
+
+/// the thread never checks more often, short `stall`s are reported late
+/// rather than spinning
+const MIN_PERIOD_MS: u64 = 10;
+
+/// what the frame loop waits for
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stage {
+    Acquire,
+    Submit,
+    Readback,
+    Compute,
+    Upload,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Timeouts {
+    pub acquire: Duration,
+    pub fence: Duration,
+}
+
+impl Timeouts {
+    pub fn from_config(config: &WatchdogConfig) -> Timeouts {
+        Timeouts {
+            acquire: Duration::from_millis(config.acquire_timeout),
+            fence: Duration::from_millis(config.fence_timeout),
+        }
+    }
+}
+
+/// waits at most `timeout` for `fence`, which is handed back once
+/// signalled and leaked otherwise
+pub fn wait_fence<F: GpuFuture>(
+    fence: FenceSignalFuture<F>,
+    timeout: Duration,
+    stage: Stage,
+) -> Result<FenceSignalFuture<F>, RenderError> {
+    match fence.wait(Some(timeout)) {
+        Ok(()) => Ok(fence),
+        Err(err) => {
+            warn!(target: INFO, "leaking the fence of {}, with what it holds", stage);
+            mem::forget(fence);
+            match err {
+                FlushError::Timeout => Err(RenderError::Timeout(stage)),
+                err => Err(err.into()),
+            }
+        }
+    }
+}
+
+/// how many more times a failed device is recreated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    done: u32,
+    limit: u32,
+}
+
+impl Recovery {
+    /// `watchdog.recoveries`
+    pub fn new(limit: u32) -> Recovery {
+        Recovery { done: 0, limit: limit }
+    }
+
+    /// whether to recreate the device after `err`, counted when it is
+    pub fn recover(&mut self, err: &RenderError) -> bool {
+        if !err.is_device_failure() || self.done >= self.limit {
+            return false;
+        }
+        self.done += 1;
+        true
+    }
+
+    /// recoveries so far
+    pub fn done(&self) -> u32 {
+        self.done
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+}
+
+/// the stage the frame loop is in, and since when
+#[derive(Debug, Default)]
+struct Progress {
+    stage: Option<(Stage, Instant)>,
+    reported: bool,
+}
+
+impl Progress {
+    fn enter(&mut self, stage: Stage, now: Instant) {
+        self.stage = Some((stage, now));
+        self.reported = false;
+    }
+
+    /// the stage and how long it took when it had been reported
+    fn leave(&mut self, now: Instant) -> Option<(Stage, Duration)> {
+        let reported = self.reported;
+        self.reported = false;
+        match self.stage.take() {
+            Some((stage, since)) if reported => Some((stage, now - since)),
+            _ => None,
+        }
+    }
+
+    /// the stage entered more than `limit` ago, once per stall
+    fn stalled(&mut self, now: Instant, limit: Duration) -> Option<(Stage, Duration)> {
+        match self.stage {
+            Some((stage, since)) if !self.reported && now - since >= limit => {
+                self.reported = true;
+                Some((stage, now - since))
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Shared {
+    progress: Progress,
+    stop: bool,
+}
+
+/// reports a frame loop stuck in a stage for longer than `limit`
+pub struct Watchdog {
+    shared: Arc<(Mutex<Shared>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub fn start(limit: Duration) -> io::Result<Watchdog> {
+        let shared = Arc::new((
+            Mutex::new(Shared {
+                progress: Progress::default(),
+                stop: false,
+            }),
+            Condvar::new(),
+        ));
+        let watched = shared.clone();
+        let thread = thread::Builder::new()
+            .name("watchdog".to_owned())
+            .spawn(move || {
+                let (ref lock, ref condvar) = *watched;
+                let mut shared = lock.lock().expect("watchdog lock poisoned");
+                while !shared.stop {
+                    if let Some((stage, elapsed)) = shared.progress.stalled(Instant::now(), limit) {
+                        error!(target: INFO, "GPU stalled: {} for {:?}", stage, elapsed);
+                    }
+                    shared = condvar
+                        .wait_timeout(shared, period(limit))
+                        .expect("watchdog lock poisoned")
+                        .0;
+                }
+            })?;
+        Ok(Watchdog {
+            shared: shared,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn enter(&self, stage: Stage) {
+        let mut shared = self.shared.0.lock().expect("watchdog lock poisoned");
+        shared.progress.enter(stage, Instant::now());
+    }
+
+    pub fn leave(&self) {
+        let mut shared = self.shared.0.lock().expect("watchdog lock poisoned");
+        if let Some((stage, elapsed)) = shared.progress.leave(Instant::now()) {
+            warn!(target: INFO, "GPU resumed: {} took {:?}", stage, elapsed);
+        }
+    }
+
+    /// ends the thread without waiting for it, for a watchdog that is
+    /// leaked with its lost device and never dropped
+    pub fn stop(&self) {
+        self.shared.0.lock().expect("watchdog lock poisoned").stop = true;
+        self.shared.1.notify_one();
+    }
+}
+
+/// how often the thread checks for a stall longer than `limit`
+fn period(limit: Duration) -> Duration {
+    cmp::max(limit / 4, Duration::from_millis(MIN_PERIOD_MS))
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.stop();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Stage::Acquire => "acquiring an image",
+            Stage::Submit => "submitting a frame",
+            Stage::Readback => "reading a frame back",
+            Stage::Compute => "running a compute kernel",
+            Stage::Upload => "submitting uploads",
+        };
+        write!(f, "{}", name)
+    }
+}
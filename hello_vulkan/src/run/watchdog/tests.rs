@@ -0,0 +1,62 @@
+use super::{period, Progress, Recovery, Stage};
+
+use std::time::{Duration, Instant};
+
+use run::renderer::RenderError;
+
+#[test]
+fn stalls_are_reported_once() {
+    let start = Instant::now();
+    let limit = Duration::from_millis(100);
+    let mut progress = Progress::default();
+    assert_eq!(progress.stalled(start + limit * 10, limit), None);
+
+    progress.enter(Stage::Acquire, start);
+    assert_eq!(progress.stalled(start + limit / 2, limit), None);
+    assert_eq!(progress.stalled(start + limit, limit), Some((Stage::Acquire, limit)));
+    assert_eq!(progress.stalled(start + limit * 2, limit), None);
+    assert_eq!(progress.leave(start + limit * 3), Some((Stage::Acquire, limit * 3)));
+    assert_eq!(progress.stalled(start + limit * 4, limit), None);
+}
+
+#[test]
+fn each_stage_has_its_own_limit() {
+    let start = Instant::now();
+    let limit = Duration::from_millis(100);
+    let mut progress = Progress::default();
+
+    progress.enter(Stage::Submit, start);
+    assert_eq!(progress.leave(start + limit / 2), None);
+    progress.enter(Stage::Readback, start + limit / 2);
+    assert_eq!(progress.stalled(start + limit, limit), None);
+    assert_eq!(
+        progress.stalled(start + limit * 2, limit),
+        Some((Stage::Readback, limit * 3 / 2))
+    );
+}
+
+#[test]
+fn short_limits_do_not_spin() {
+    assert_eq!(period(Duration::from_millis(1)), Duration::from_millis(10));
+    assert_eq!(period(Duration::from_millis(3)), Duration::from_millis(10));
+    assert_eq!(period(Duration::from_millis(400)), Duration::from_millis(100));
+}
+
+#[test]
+fn lost_devices_are_recovered_up_to_the_limit() {
+    let mut recovery = Recovery::new(2);
+    assert!(recovery.recover(&RenderError::Timeout(Stage::Acquire)));
+    assert!(recovery.recover(&RenderError::DeviceLost));
+    assert!(!recovery.recover(&RenderError::Timeout(Stage::Submit)));
+    assert_eq!(recovery.done(), 2);
+}
+
+#[test]
+fn other_errors_are_not_recovered() {
+    let mut recovery = Recovery::new(3);
+    assert!(!recovery.recover(&RenderError::OutOfDate));
+    assert_eq!(recovery.done(), 0);
+
+    let mut never = Recovery::new(0);
+    assert!(!never.recover(&RenderError::DeviceLost));
+}
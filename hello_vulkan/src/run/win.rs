@@ -1,75 +1,341 @@
-use super::VulkanStruct;
-#[allow(unused_imports)]
-use vulkano_win::VkSurfaceBuild;
+use super::{choose_format, choose_present_mode, VulkanStruct};
+use super::backend::{BackendEvent, SurfaceBackend};
+use super::orientation::{AppliedOrientation, Orientation};
+use super::renderer::RenderError;
+use super::target::{PresentTarget, SwapchainTarget};
+use config::{Config, WindowConfig};
+use input::{InputEvent, Key, MouseButton};
+use log_targets::INFO;
 use winit;
-use winit::EventsLoop;
+use winit::{CursorState, ElementState, EventsLoop, MonitorId, MouseScrollDelta, VirtualKeyCode,
+            Window, WindowBuilder};
 
 use std::sync::Arc;
 use vulkano_win;
-use vulkano::image::swapchain::SwapchainImage;
-use vulkano::instance::InstanceExtensions;
-use vulkano::swapchain::{PresentMode, SurfaceTransform, Swapchain, SwapchainCreationError};
+use vulkano::instance::{DeviceExtensions, Instance, InstanceExtensions};
+use vulkano::swapchain::{Surface, SurfaceCreationError, Swapchain};
 
-pub fn required_extensions() -> InstanceExtensions {
-    let extensions = vulkano_win::required_extensions();
-    extensions
+#[cfg(target_os = "macos")]
+use cocoa::appkit::{NSView, NSWindow};
+#[cfg(target_os = "macos")]
+use cocoa::base::id as cocoa_id;
+#[cfg(target_os = "macos")]
+use metal_rs::CoreAnimationLayer;
+#[cfg(target_os = "macos")]
+use objc::runtime::YES;
+#[cfg(target_os = "macos")]
+use std::mem;
+#[cfg(target_os = "windows")]
+use std::ptr;
+
+pub struct WindowBackend {
+    events_loop: EventsLoop,
+    /// opened by the first `create_target`, the later ones after a device
+    /// loss create their surface on it too
+    window: Option<Arc<Window>>,
+    config: WindowConfig,
+}
+
+impl WindowBackend {
+    pub fn new() -> WindowBackend {
+        WindowBackend {
+            events_loop: winit::EventsLoop::new(),
+            window: None,
+            config: WindowConfig::default(),
+        }
+    }
+
+    /// `window.monitor`, else the primary monitor
+    fn monitor(&self) -> MonitorId {
+        if let Some(ref name) = self.config.monitor {
+            let found = self.events_loop
+                .get_available_monitors()
+                .find(|monitor| monitor.get_name().map_or(false, |n| n.contains(name.as_str())));
+            match found {
+                Some(monitor) => return monitor,
+                None => warn!(target: INFO, "`window.monitor`: no monitor matches '{}'", name),
+            }
+        }
+        self.events_loop.get_primary_monitor()
+    }
+
+    fn window_builder(&self) -> WindowBuilder {
+        let config = &self.config;
+        let mut builder = WindowBuilder::new()
+            .with_title(config.title.clone())
+            .with_dimensions(config.size[0], config.size[1])
+            .with_decorations(config.decorations);
+        if !config.resizable {
+            builder = builder
+                .with_min_dimensions(config.size[0], config.size[1])
+                .with_max_dimensions(config.size[0], config.size[1]);
+        }
+        if config.fullscreen {
+            builder = builder.with_fullscreen(Some(self.monitor()));
+        }
+        builder
+    }
+}
+
+/// pixels per unit of the sizes and positions winit reports, which are
+/// points on macOS and pixels everywhere else
+fn pixel_factor(window: &Window) -> f32 {
+    if cfg!(target_os = "macos") {
+        window.hidpi_factor()
+    } else {
+        1.0
+    }
+}
+
+/// size of the window's client area in pixels
+fn physical_size(window: &Window, size: (u32, u32)) -> [u32; 2] {
+    let factor = pixel_factor(window);
+    [
+        (size.0 as f32 * factor).round() as u32,
+        (size.1 as f32 * factor).round() as u32,
+    ]
+}
+
+/// a surface of `instance` on `window`, made the way vulkano-win makes
+/// them, which only does it for a window it opens itself
+#[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
+fn create_surface(
+    instance: Arc<Instance>,
+    window: Arc<Window>,
+) -> Result<Arc<Surface<Arc<Window>>>, SurfaceCreationError> {
+    use winit::os::unix::WindowExt;
+
+    unsafe {
+        match (window.get_wayland_display(), window.get_wayland_surface()) {
+            (Some(display), Some(surface)) => Surface::from_wayland(instance, display, surface, window),
+            _ => {
+                let xlib_window = window.get_xlib_window().expect("neither a Wayland nor an X11 window");
+                if instance.loaded_extensions().khr_xlib_surface {
+                    let display = window.get_xlib_display().expect("X11 window without a display");
+                    Surface::from_xlib(instance, display, xlib_window as _, window)
+                } else {
+                    let connection = window.get_xcb_connection().expect("X11 window without a connection");
+                    Surface::from_xcb(instance, connection, xlib_window as _, window)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn create_surface(
+    instance: Arc<Instance>,
+    window: Arc<Window>,
+) -> Result<Arc<Surface<Arc<Window>>>, SurfaceCreationError> {
+    use winit::os::windows::WindowExt;
+
+    let hwnd = window.get_hwnd() as *const ();
+    unsafe { Surface::from_hwnd(instance, ptr::null() as *const (), hwnd, window) }
+}
+
+/// MoltenVK draws into a `CAMetalLayer`, set on the window's view
+#[cfg(target_os = "macos")]
+fn create_surface(
+    instance: Arc<Instance>,
+    window: Arc<Window>,
+) -> Result<Arc<Surface<Arc<Window>>>, SurfaceCreationError> {
+    use winit::os::macos::WindowExt;
+
+    unsafe {
+        let wnd: cocoa_id = mem::transmute(window.get_nswindow());
+        let layer = CoreAnimationLayer::new();
+        layer.set_edge_antialiasing_mask(0);
+        layer.set_presents_with_transaction(false);
+        layer.remove_all_animations();
+
+        let view = wnd.contentView();
+        layer.set_contents_scale(view.backingScaleFactor());
+        view.setLayer(mem::transmute(layer.as_ref()));
+        view.setWantsLayer(YES);
+
+        let nsview = window.get_nsview() as *const ();
+        Surface::from_macos_moltenvk(instance, nsview, window)
+    }
+}
+
+impl SurfaceBackend for WindowBackend {
+    fn name(&self) -> &'static str {
+        "win"
+    }
+
+    fn required_extensions(&self) -> InstanceExtensions {
+        vulkano_win::required_extensions()
+    }
+
+    fn required_device_extensions(&self) -> DeviceExtensions {
+        DeviceExtensions {
+            khr_swapchain: true,
+            ..DeviceExtensions::none()
+        }
+    }
+
+    fn create_target(
+        &mut self,
+        vulkan_obj: Arc<VulkanStruct>,
+        config: &Config,
+    ) -> Result<Box<PresentTarget>, RenderError> {
+        self.config = config.window.clone();
+        let window = match self.window.clone() {
+            Some(window) => window,
+            None => {
+                let window = self.window_builder()
+                    .build(&self.events_loop)
+                    .map_err(|err| RenderError::Vulkan(Box::new(err)))?;
+                let window = Arc::new(window);
+                if !self.config.cursor {
+                    if let Err(err) = window.set_cursor_state(CursorState::Hide) {
+                        warn!(target: INFO, "failed to hide the cursor: {}", err);
+                    }
+                }
+                self.window = Some(window.clone());
+                window
+            }
+        };
+        let surface = create_surface(vulkan_obj.device.physical_device().instance().clone(), window.clone())?;
+
+        // if do not call is_supported, validation layer will report warnings
+        if !surface.is_supported(vulkan_obj.queue.family())? {
+            return Err(RenderError::Unsupported("presenting to the window from the graphics queue"));
+        }
+
+        let caps = surface.capabilities(vulkan_obj.device.physical_device())?;
+
+        let orientation = AppliedOrientation::choose(&caps, Orientation::from_config(&config.orientation)?);
+        let inner_size = window
+            .get_inner_size()
+            .map(|size| physical_size(&window, size));
+        let dim = orientation.image_extent(
+            caps.current_extent
+                .or(inner_size)
+                .unwrap_or_else(|| physical_size(&window, (self.config.size[0], self.config.size[1]))),
+        );
+        let alpha = caps.supported_composite_alpha.iter().next().unwrap();
+        let format = choose_format(&caps, config)?;
+        let present_mode = choose_present_mode(&caps, config);
+
+        let (swap_chain, images) = Swapchain::new(
+            vulkan_obj.device.clone(),
+            surface,
+            caps.min_image_count,
+            format,
+            dim,
+            1,
+            caps.supported_usage_flags,
+            &vulkan_obj.queue,
+            orientation.surface_transform,
+            alpha,
+            present_mode,
+            true,
+            None,
+        ).map_err(RenderError::Swapchain)?;
+
+        Ok(Box::new(SwapchainTarget::new(
+            swap_chain,
+            images,
+            vulkan_obj.queue.clone(),
+            orientation,
+            vulkan_obj.timeouts.acquire,
+        )))
+    }
+
+    fn pump_events(&mut self) -> Vec<BackendEvent> {
+        let mut events = Vec::new();
+        let window = self.window.clone();
+        let factor = window.as_ref().map_or(1.0, |window| pixel_factor(window));
+        self.events_loop.poll_events(|event| match event {
+            winit::Event::WindowEvent {
+                event: winit::WindowEvent::Closed,
+                ..
+            } => events.push(BackendEvent::Quit),
+            winit::Event::WindowEvent {
+                event: winit::WindowEvent::Resized(width, height),
+                ..
+            } => {
+                let size = match window {
+                    Some(ref window) => physical_size(window, (width, height)),
+                    None => [width, height],
+                };
+                events.push(BackendEvent::Resized(size));
+            }
+            winit::Event::WindowEvent { event, .. } => {
+                if let Some(input) = translate_input(event, factor) {
+                    events.push(BackendEvent::Input(input));
+                }
+            }
+            _ => (),
+        });
+        events
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        if let Some(ref window) = self.window {
+            let monitor = if fullscreen {
+                Some(self.monitor())
+            } else {
+                None
+            };
+            window.set_fullscreen(monitor);
+        }
+    }
+}
+
+/// `factor` converts positions to pixels
+fn translate_input(event: winit::WindowEvent, factor: f32) -> Option<InputEvent> {
+    match event {
+        winit::WindowEvent::KeyboardInput { input, .. } => {
+            input.virtual_keycode.and_then(translate_key).map(|key| InputEvent::Key {
+                key: key,
+                pressed: input.state == ElementState::Pressed,
+            })
+        }
+        winit::WindowEvent::MouseInput { state, button, .. } => Some(InputEvent::MouseButton {
+            button: match button {
+                winit::MouseButton::Left => MouseButton::Left,
+                winit::MouseButton::Right => MouseButton::Right,
+                winit::MouseButton::Middle => MouseButton::Middle,
+                winit::MouseButton::Other(n) => MouseButton::Other(n),
+            },
+            pressed: state == ElementState::Pressed,
+        }),
+        winit::WindowEvent::CursorMoved { position, .. } => {
+            Some(InputEvent::CursorMoved([position.0 as f32 * factor, position.1 as f32 * factor]))
+        }
+        winit::WindowEvent::MouseWheel { delta, .. } => Some(InputEvent::Scroll(match delta {
+            MouseScrollDelta::LineDelta(x, y) => [x, y],
+            // roughly one line per 20 pixels, like most toolkits
+            MouseScrollDelta::PixelDelta(x, y) => [x / 20.0, y / 20.0],
+        })),
+        _ => None,
+    }
 }
 
-pub fn create_swapchain(
-    vulkan_obj: Arc<VulkanStruct>,
-) -> Result<
-    (
-        Arc<Swapchain<winit::Window>>,
-        Vec<Arc<SwapchainImage<winit::Window>>>,
-        winit::EventsLoop,
-    ),
-    SwapchainCreationError,
-> {
-    let events_loop = winit::EventsLoop::new();
-    let window = winit::WindowBuilder::new()
-        .build_vk_surface(
-            &events_loop,
-            vulkan_obj.device.physical_device().instance().clone(),
-        )
-        .unwrap();
-
-    // if do not call is_supported, validation layer will report warnings
-    let _r = window.is_supported(vulkan_obj.queue.family()).unwrap();
-
-    let _win = window.window();
-    let caps = window
-        .capabilities(vulkan_obj.device.physical_device())
-        .expect("failed to get surface capabalities");
-
-    let dim = caps.current_extent.unwrap_or([1280, 1024]);
-    let alpha = caps.supported_composite_alpha.iter().next().unwrap();
-    let format = caps.supported_formats[0].0;
-
-    let (swap_chain, images) = Swapchain::new(
-        vulkan_obj.device.clone(),
-        window.clone(),
-        caps.min_image_count,
-        format,
-        dim,
-        1,
-        caps.supported_usage_flags,
-        &vulkan_obj.queue,
-        SurfaceTransform::Identity,
-        alpha,
-        PresentMode::Fifo,
-        true,
-        None,
-    ).expect("Failed to create swapchain");
-
-    Ok((swap_chain, images, events_loop))
+macro_rules! translate_keys {
+    ($code:expr, { $($winit:ident => $key:ident),* }, [$($same:ident),*]) => {
+        match $code {
+            $(VirtualKeyCode::$winit => Some(Key::$key),)*
+            $(VirtualKeyCode::$same => Some(Key::$same),)*
+            _ => None,
+        }
+    };
 }
 
-pub fn run_loop(events_loop: &mut EventsLoop) {
-    events_loop.run_forever(|event| match event {
-        winit::Event::WindowEvent {
-            event: winit::WindowEvent::Closed,
-            ..
-        } => winit::ControlFlow::Break,
-        _ => winit::ControlFlow::Continue,
-    });
+fn translate_key(code: VirtualKeyCode) -> Option<Key> {
+    translate_keys!(code, {
+        Return => Enter,
+        Back => Backspace,
+        Subtract => Minus
+    }, [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        Escape, Space, Tab, Insert, Delete, Home, End, PageUp, PageDown,
+        Left, Right, Up, Down,
+        LShift, RShift, LControl, RControl, LAlt, RAlt,
+        Minus, Equals, Comma, Period, Slash
+    ])
 }
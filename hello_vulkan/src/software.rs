@@ -0,0 +1,83 @@
+//! Minimal CPU rasterizer, used to draw the demo when there is no Vulkan
+//! driver at all
+
+/// tightly packed RGBA8 image
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas {
+            width: width,
+            height: height,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn clear(&mut self, color: [f32; 4]) {
+        let color = to_rgba8(color);
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// fills a triangle given in Vulkan clip space (y pointing down),
+    /// pixels are covered when their centre is inside, like on the GPU
+    pub fn fill_triangle(&mut self, vertices: &[[f32; 2]; 3], color: [f32; 4]) {
+        let color = to_rgba8(color);
+        let (w, h) = (self.width as f32, self.height as f32);
+        let p: Vec<(f32, f32)> = vertices
+            .iter()
+            .map(|v| ((v[0] + 1.0) * 0.5 * w, (v[1] + 1.0) * 0.5 * h))
+            .collect();
+
+        let area = edge(p[0], p[1], p[2]);
+        if area == 0.0 {
+            return;
+        }
+
+        let min_x = p.iter().map(|v| v.0).fold(w, f32::min).max(0.0) as u32;
+        let max_x = p.iter().map(|v| v.0).fold(0.0, f32::max).min(w).ceil() as u32;
+        let min_y = p.iter().map(|v| v.1).fold(h, f32::min).max(0.0) as u32;
+        let max_y = p.iter().map(|v| v.1).fold(0.0, f32::max).min(h).ceil() as u32;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let centre = (x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = edge(p[1], p[2], centre);
+                let w1 = edge(p[2], p[0], centre);
+                let w2 = edge(p[0], p[1], centre);
+                // accept both windings, the pipeline doesn't cull either
+                let inside = (w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0)
+                    || (w0 <= 0.0 && w1 <= 0.0 && w2 <= 0.0);
+                if inside {
+                    let i = ((y * self.width + x) * 4) as usize;
+                    self.pixels[i..i + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+}
+
+fn edge(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn to_rgba8(color: [f32; 4]) -> [u8; 4] {
+    let mut rgba = [0u8; 4];
+    for (dst, src) in rgba.iter_mut().zip(color.iter()) {
+        *dst = (src.max(0.0).min(1.0) * 255.0 + 0.5) as u8;
+    }
+    rgba
+}